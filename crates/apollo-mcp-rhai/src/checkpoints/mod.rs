@@ -1,5 +1,75 @@
 mod on_execute_graphql_operation;
+mod on_graphql_response;
+mod on_list_tools;
+mod on_operations_updated;
+mod on_schema_updated;
 mod on_startup;
 
 pub use on_execute_graphql_operation::*;
+pub use on_graphql_response::*;
+pub use on_list_tools::*;
+pub use on_operations_updated::*;
+pub use on_schema_updated::*;
 pub use on_startup::*;
+
+use rhai::{EvalAltResult, Position};
+use rmcp::model::ErrorCode;
+use tracing::{error, warn};
+
+use crate::limits;
+use crate::types::RhaiErrorCode;
+
+pub type McpError = rmcp::model::ErrorData;
+
+/// Convert an error raised by a hook into the error returned to the MCP client
+pub(crate) fn hook_error(hook_name: &str, err: EvalAltResult) -> McpError {
+    script_error(&format!("{hook_name} hook"), err)
+}
+
+/// Convert an error raised by a script into the error returned to the MCP client.
+///
+/// Scripts can throw a map with a `message` and an `ErrorCode` to return that error. Scripts
+/// stopped by their limits return an internal error saying which limit they hit. Anything else is
+/// logged and returned as a generic internal error.
+pub(crate) fn script_error(source: &str, err: EvalAltResult) -> McpError {
+    if let Some((reason, position)) = limits::limit_exceeded(&err) {
+        match position {
+            Position::NONE => error!("Error when executing {source}: Script {reason}"),
+            position => error!("Error when executing {source}: Script {reason} at {position}"),
+        }
+        return McpError::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Rhai {source} {reason}"),
+            None,
+        );
+    }
+
+    match err {
+        EvalAltResult::ErrorRuntime(error_data, _) => match error_data.as_map_ref() {
+            Ok(error_data) => {
+                let message = error_data
+                    .get("message")
+                    .map(|val| val.to_string())
+                    .unwrap_or_else(|| {
+                        warn!("Error was thrown with no 'message' field, using default.");
+                        "Internal error".to_string()
+                    });
+                let code = error_data
+                    .get("code")
+                    .and_then(|val| val.clone().try_cast::<RhaiErrorCode>())
+                    .unwrap_or(RhaiErrorCode::InternalError);
+                McpError::new(ErrorCode::from(code), message, None)
+            }
+            Err(inner_err) => {
+                error!(
+                    "Error when executing {source}: Error when converting error_data to map: {inner_err}, actual error: {error_data}"
+                );
+                McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+            }
+        },
+        err => {
+            error!("Error when executing {source}: {err}");
+            McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+        }
+    }
+}
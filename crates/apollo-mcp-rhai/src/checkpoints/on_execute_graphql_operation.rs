@@ -3,18 +3,33 @@ use std::sync::Arc;
 use http::HeaderMap;
 use http::request::Parts;
 use parking_lot::Mutex;
-use rhai::{CustomType, Engine, EvalAltResult, TypeBuilder};
+use rhai::{Array, CustomType, Dynamic, Engine, Map, TypeBuilder};
 use rmcp::model::ErrorCode;
+use serde_json::Value;
 use tracing::{error, warn};
 use url::Url;
 
+use super::{McpError, hook_error};
 use crate::{
     engine::RhaiEngine,
     shared_mut::{SharedMut, WithMut},
-    types::{RhaiErrorCode, RhaiHeaderMap, RhaiHttpParts},
+    types::{RhaiHeaderMap, RhaiHttpParts},
 };
 
-pub type McpError = rmcp::model::ErrorData;
+/// The claims and scopes of the validated access token of a request
+#[derive(Clone, Copy, Debug)]
+pub struct TokenClaims<'a> {
+    pub claims: &'a serde_json::Map<String, Value>,
+    pub scopes: &'a [String],
+}
+
+/// The GraphQL operation a tool is about to execute
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphqlOperation {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables: Value,
+}
 
 #[derive(Clone, Debug, CustomType)]
 pub struct OnExecuteGraphqlOperationContext {
@@ -23,6 +38,14 @@ pub struct OnExecuteGraphqlOperationContext {
     pub incoming_request: RhaiHttpParts,
     pub tool_name: String,
     pub trace_id: String,
+    /// Claims of the validated access token, empty when auth is disabled
+    pub claims: Map,
+    /// Scopes of the validated access token, empty when auth is disabled
+    pub scopes: Array,
+    pub query: String,
+    #[rhai_type(skip)]
+    pub operation_name: Option<String>,
+    pub variables: Map,
 }
 
 impl OnExecuteGraphqlOperationContext {
@@ -64,23 +87,81 @@ impl OnExecuteGraphqlOperationContext {
                 |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> String {
                     obj.with_mut(|ctx| ctx.trace_id.clone())
                 },
+            )
+            .register_get(
+                "claims",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> Map {
+                    obj.with_mut(|ctx| ctx.claims.clone())
+                },
+            )
+            .register_get(
+                "scopes",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> Array {
+                    obj.with_mut(|ctx| ctx.scopes.clone())
+                },
+            )
+            .register_get_set(
+                "query",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> String {
+                    obj.with_mut(|ctx| ctx.query.clone())
+                },
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>, value: String| {
+                    obj.with_mut(|ctx| ctx.query = value);
+                },
+            )
+            .register_get(
+                "operation_name",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> Dynamic {
+                    obj.with_mut(|ctx| {
+                        ctx.operation_name
+                            .clone()
+                            .map_or(Dynamic::UNIT, Dynamic::from)
+                    })
+                },
+            )
+            .register_set(
+                "operation_name",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>, value: String| {
+                    obj.with_mut(|ctx| ctx.operation_name = Some(value));
+                },
+            )
+            .register_set(
+                "operation_name",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>, _: ()| {
+                    obj.with_mut(|ctx| ctx.operation_name = None);
+                },
+            )
+            .register_get_set(
+                "variables",
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>| -> Map {
+                    obj.with_mut(|ctx| ctx.variables.clone())
+                },
+                |obj: &mut SharedMut<OnExecuteGraphqlOperationContext>, value: Map| {
+                    obj.with_mut(|ctx| ctx.variables = value);
+                },
             );
     }
 }
 
+/// Run the `on_execute_graphql_operation` hook, returning the endpoint and headers to use.
+///
+/// Changes the script makes to the query, operation name, or variables are applied to
+/// `operation`.
+#[allow(clippy::too_many_arguments)]
 pub fn on_execute_graphql_operation(
-    engine: &Arc<Mutex<RhaiEngine>>,
+    engine: &Arc<RhaiEngine>,
     endpoint: &Url,
     headers: &HeaderMap,
+    operation: &mut GraphqlOperation,
     axum_parts: Option<&Parts>,
     tool_name: &str,
+    token: Option<TokenClaims<'_>>,
     trace_id: impl FnOnce() -> String,
 ) -> Result<(Url, HeaderMap), McpError> {
     let hook_name = "on_execute_graphql_operation";
-    let mut engine_guard = engine.lock();
 
     // Exit early if method doesn't exist, allow us to skip some more expensive cloning later in this method
-    if !engine_guard.ast_has_function(hook_name) {
+    if !engine.ast_has_function(hook_name) {
         return Ok((endpoint.clone(), headers.clone()));
     }
 
@@ -93,38 +174,25 @@ pub fn on_execute_graphql_operation(
         },
         tool_name: tool_name.to_string(),
         trace_id: trace_id(),
+        claims: token
+            .map(|token| json_map(token.claims, "Claim"))
+            .unwrap_or_default(),
+        scopes: token
+            .map(|token| token.scopes.iter().cloned().map(Dynamic::from).collect())
+            .unwrap_or_default(),
+        query: operation.query.clone(),
+        operation_name: operation.operation_name.clone(),
+        variables: match &operation.variables {
+            Value::Object(variables) => json_map(variables, "Variable"),
+            _ => Map::new(),
+        },
     };
 
     let shared_context = Arc::new(Mutex::new(context));
 
-    engine_guard
+    engine
         .execute_hook(hook_name, (shared_context.clone(),))
-        // TODO: How much of this could be made generic and/or moved into execute_hook?
-        .map_err(|err| match *err {
-            EvalAltResult::ErrorRuntime(error_data, _) => {
-                match error_data.as_map_ref() {
-                    Ok(error_data) => {
-                        let message = error_data.get("message").map(|val| val.to_string()).unwrap_or_else(|| {
-                            warn!("Error was thrown with no 'message' field, using default.");
-                            "Internal error".to_string()
-                        });
-                        let code = error_data
-                            .get("code")
-                            .and_then(|val| val.clone().try_cast::<RhaiErrorCode>())
-                            .unwrap_or(RhaiErrorCode::InternalError);
-                        McpError::new(ErrorCode::from(code), message, None)
-                    },
-                    Err(inner_err) =>{
-                        error!("Error when executing on_execute_graphql_operation hook: Error when converting error_data to map: {inner_err}, actual error: {error_data}");
-                        McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
-                    },
-                }
-            }
-            _ => {
-                error!("Error when executing on_execute_graphql_operation hook: {err}");
-                McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
-            }
-        })?;
+        .map_err(|err| hook_error(hook_name, *err))?;
 
     let context = shared_context.lock();
 
@@ -134,28 +202,61 @@ pub fn on_execute_graphql_operation(
     })?;
     let headers = context.headers.as_header_map();
 
+    operation.query.clone_from(&context.query);
+    operation.operation_name.clone_from(&context.operation_name);
+    // Variables that aren't an object are left alone unless the script sets some
+    if operation.variables.is_object() || !context.variables.is_empty() {
+        operation.variables = rhai::serde::from_dynamic(&Dynamic::from_map(context.variables.clone()))
+            .map_err(|err| {
+                error!("Error when executing on_execute_graphql_operation hook: Error converting context.variables: {err}");
+                McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+            })?;
+    }
+
     Ok((url, headers))
 }
 
+fn json_map(object: &serde_json::Map<String, Value>, kind: &str) -> Map {
+    object
+        .iter()
+        .filter_map(|(name, value)| match rhai::serde::to_dynamic(value) {
+            Ok(value) => Some((name.into(), value)),
+            Err(err) => {
+                warn!("{kind} {name} could not be converted for Rhai scripts: {err}");
+                None
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
     use http::HeaderMap;
     use http::request::Parts;
-    use parking_lot::Mutex;
     use rmcp::model::ErrorCode;
+    use serde_json::json;
     use url::Url;
 
-    use super::on_execute_graphql_operation;
+    use super::{GraphqlOperation, TokenClaims, on_execute_graphql_operation};
     use crate::engine::RhaiEngine;
+    use crate::limits::Limits;
 
-    fn create_engine(script: &str) -> Arc<Mutex<RhaiEngine>> {
-        let mut engine = RhaiEngine::new("rhai");
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
         engine
             .load_from_string(script)
             .expect("Script should compile");
-        Arc::new(Mutex::new(engine))
+        Arc::new(engine)
+    }
+
+    fn test_operation() -> GraphqlOperation {
+        GraphqlOperation {
+            query: "query GetUser($id: ID!) { user(id: $id) { name } }".to_string(),
+            operation_name: Some("GetUser".to_string()),
+            variables: json!({"id": "1"}),
+        }
     }
 
     fn create_parts(method: &str, uri: &str, headers: HeaderMap) -> Parts {
@@ -173,9 +274,17 @@ mod tests {
         let mut headers = HeaderMap::new();
         headers.insert("authorization", "Bearer token123".parse().unwrap());
 
-        let (result_url, result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (result_url, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(result_url, url);
         assert_eq!(
@@ -194,9 +303,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (result_url, _result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (result_url, _result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(result_url, url);
     }
@@ -211,9 +328,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (result_url, _) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (result_url, _) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(
             result_url,
@@ -233,9 +358,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (_, result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (_, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(result_headers.get("x-custom").unwrap(), "custom-value");
     }
@@ -253,9 +386,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let err =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect_err("Should return error");
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error");
 
         assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
         assert_eq!(err.message, "unauthorized request");
@@ -273,9 +414,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let err =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect_err("Should return error");
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error");
 
         assert_eq!(err.message, "Internal error");
     }
@@ -290,14 +439,57 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let err =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect_err("Should return error");
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error");
 
         assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
         assert_eq!(err.message, "Internal error");
     }
 
+    #[test]
+    fn should_return_internal_error_when_hook_hits_a_limit() {
+        let engine = RhaiEngine::new("rhai").with_limits(Limits {
+            max_operations: Some(100),
+            ..Default::default()
+        });
+        engine
+            .load_from_string(
+                r#"fn on_execute_graphql_operation(ctx) {
+                    loop { ctx.tool_name; }
+                }"#,
+            )
+            .expect("Script should compile");
+        let url = Url::parse("https://example.com/graphql").expect("Valid URL");
+        let headers = HeaderMap::new();
+
+        let err = on_execute_graphql_operation(
+            &Arc::new(engine),
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+        assert_eq!(
+            err.message,
+            "Rhai on_execute_graphql_operation hook ran more than the maximum number of operations"
+        );
+    }
+
     #[test]
     fn should_return_error_when_hook_sets_invalid_url() {
         let engine = create_engine(
@@ -308,9 +500,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let err =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect_err("Should return error");
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error");
 
         assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
     }
@@ -327,9 +527,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (_, result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (_, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(result_headers.get("x-tool-name").unwrap(), "my-tool");
     }
@@ -351,8 +559,10 @@ mod tests {
             &engine,
             &url,
             &headers,
+            &mut test_operation(),
             Some(&parts),
             "my-tool",
+            None,
             String::new,
         )
         .expect("Should not error");
@@ -377,8 +587,10 @@ mod tests {
             &engine,
             &url,
             &headers,
+            &mut test_operation(),
             Some(&parts),
             "my-tool",
+            None,
             String::new,
         )
         .expect("Should not error");
@@ -405,8 +617,10 @@ mod tests {
             &engine,
             &url,
             &headers,
+            &mut test_operation(),
             Some(&parts),
             "my-tool",
+            None,
             String::new,
         )
         .expect("Should not error");
@@ -429,11 +643,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (_, result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", || {
-                "4bf92f3577b34da6a3ce929d0e0e4736".to_string()
-            })
-            .expect("Should not error");
+        let (_, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            || "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+        )
+        .expect("Should not error");
 
         assert_eq!(
             result_headers.get("x-trace-id").unwrap(),
@@ -457,9 +677,17 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let (_, result_headers) =
-            on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", String::new)
-                .expect("Should not error");
+        let (_, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
 
         assert_eq!(result_headers.get("x-trace-id").unwrap(), "absent");
     }
@@ -474,11 +702,176 @@ mod tests {
         let url = Url::parse("https://example.com/graphql").expect("Valid URL");
         let headers = HeaderMap::new();
 
-        let err = on_execute_graphql_operation(&engine, &url, &headers, None, "my-tool", || {
-            "4bf92f3577b34da6a3ce929d0e0e4736".to_string()
-        })
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            || "4bf92f3577b34da6a3ce929d0e0e4736".to_string(),
+        )
         .expect_err("Should return error because trace_id has no setter");
 
         assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
     }
+
+    #[test]
+    fn should_read_token_claims_and_scopes() {
+        let engine = create_engine(
+            r#"fn on_execute_graphql_operation(ctx) {
+                let h = ctx.headers;
+                h["x-user"] = ctx.claims.sub;
+                h["x-tenant"] = ctx.claims.org.tenant;
+                h["x-can-write"] = `${"write" in ctx.scopes}`;
+                ctx.headers = h;
+            }"#,
+        );
+        let url = Url::parse("https://example.com/graphql").expect("Valid URL");
+        let headers = HeaderMap::new();
+        let claims = serde_json::json!({"sub": "alice", "org": {"tenant": "acme"}});
+        let scopes = vec!["read".to_string(), "write".to_string()];
+        let token = TokenClaims {
+            claims: claims.as_object().unwrap(),
+            scopes: &scopes,
+        };
+
+        let (_, result_headers) = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            Some(token),
+            String::new,
+        )
+        .expect("Should not error");
+
+        assert_eq!(result_headers.get("x-user").unwrap(), "alice");
+        assert_eq!(result_headers.get("x-tenant").unwrap(), "acme");
+        assert_eq!(result_headers.get("x-can-write").unwrap(), "true");
+    }
+
+    #[test]
+    fn should_read_empty_claims_without_token() {
+        let engine = create_engine(
+            r#"fn on_execute_graphql_operation(ctx) {
+                if ctx.claims.len() != 0 || ctx.scopes.len() != 0 || ctx.claims.sub != () {
+                    throw "expected no claims";
+                }
+            }"#,
+        );
+        let url = Url::parse("https://example.com/graphql").expect("Valid URL");
+        let headers = HeaderMap::new();
+
+        let result = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        );
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_not_allow_writing_claims() {
+        let engine = create_engine(
+            r#"fn on_execute_graphql_operation(ctx) {
+                ctx.claims = #{ sub: "mallory" };
+            }"#,
+        );
+        let url = Url::parse("https://example.com/graphql").expect("Valid URL");
+        let headers = HeaderMap::new();
+
+        let err = on_execute_graphql_operation(
+            &engine,
+            &url,
+            &headers,
+            &mut test_operation(),
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect_err("Should return error because claims has no setter");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+    }
+
+    fn run_with_operation(script: &str, mut operation: GraphqlOperation) -> GraphqlOperation {
+        let engine = create_engine(script);
+        let url = Url::parse("https://example.com/graphql").expect("Valid URL");
+
+        on_execute_graphql_operation(
+            &engine,
+            &url,
+            &HeaderMap::new(),
+            &mut operation,
+            None,
+            "my-tool",
+            None,
+            String::new,
+        )
+        .expect("Should not error");
+        operation
+    }
+
+    #[test]
+    fn should_rewrite_variables() {
+        let operation = run_with_operation(
+            r#"fn on_execute_graphql_operation(ctx) {
+                if ctx.variables.first == () || ctx.variables.first > 50 {
+                    ctx.variables.first = 50;
+                }
+                ctx.variables.tenant = "acme";
+            }"#,
+            GraphqlOperation {
+                variables: json!({"id": "1", "first": 1000}),
+                ..test_operation()
+            },
+        );
+
+        assert_eq!(
+            operation.variables,
+            json!({"id": "1", "first": 50, "tenant": "acme"})
+        );
+    }
+
+    #[test]
+    fn should_leave_null_variables_alone_when_unchanged() {
+        let operation = run_with_operation(
+            r#"fn on_execute_graphql_operation(ctx) {
+                // no-op
+            }"#,
+            GraphqlOperation {
+                variables: json!(null),
+                ..test_operation()
+            },
+        );
+
+        assert_eq!(operation.variables, json!(null));
+    }
+
+    #[test]
+    fn should_rewrite_query_and_operation_name() {
+        let operation = run_with_operation(
+            r#"fn on_execute_graphql_operation(ctx) {
+                if ctx.operation_name == "GetUser" {
+                    ctx.query = "{ me { name } }";
+                    ctx.operation_name = ();
+                }
+            }"#,
+            test_operation(),
+        );
+
+        assert_eq!(operation.query, "{ me { name } }");
+        assert_eq!(operation.operation_name, None);
+    }
 }
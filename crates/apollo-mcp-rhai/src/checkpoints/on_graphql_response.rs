@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use http::{HeaderMap, StatusCode};
+use parking_lot::Mutex;
+use rhai::{Array, CustomType, Dynamic, Engine, INT, TypeBuilder};
+use rmcp::model::ErrorCode;
+use serde_json::Value;
+use tracing::error;
+
+use super::{McpError, hook_error};
+use crate::{
+    engine::RhaiEngine,
+    shared_mut::{SharedMut, WithMut},
+    types::RhaiHeaderMap,
+};
+
+#[derive(Clone, Debug, CustomType)]
+pub struct OnGraphqlResponseContext {
+    pub tool_name: String,
+    pub variables: Dynamic,
+    pub status: INT,
+    pub headers: RhaiHeaderMap,
+    /// The parsed GraphQL response body
+    pub body: Dynamic,
+    /// Text added to the content of the tool result
+    pub content: Array,
+    /// Whether to mark the tool result as an error
+    pub is_error: bool,
+}
+
+impl OnGraphqlResponseContext {
+    pub fn register(engine: &mut Engine) {
+        engine
+            .register_type::<OnGraphqlResponseContext>()
+            .register_get(
+                "tool_name",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> String {
+                    obj.with_mut(|ctx| ctx.tool_name.clone())
+                },
+            )
+            .register_get(
+                "variables",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> Dynamic {
+                    obj.with_mut(|ctx| ctx.variables.clone())
+                },
+            )
+            .register_get(
+                "status",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> INT {
+                    obj.with_mut(|ctx| ctx.status)
+                },
+            )
+            .register_get(
+                "headers",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> RhaiHeaderMap {
+                    obj.with_mut(|ctx| ctx.headers.clone())
+                },
+            )
+            .register_get_set(
+                "body",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> Dynamic {
+                    obj.with_mut(|ctx| ctx.body.clone())
+                },
+                |obj: &mut SharedMut<OnGraphqlResponseContext>, value: Dynamic| {
+                    obj.with_mut(|ctx| ctx.body = value);
+                },
+            )
+            .register_get_set(
+                "content",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> Array {
+                    obj.with_mut(|ctx| ctx.content.clone())
+                },
+                |obj: &mut SharedMut<OnGraphqlResponseContext>, value: Array| {
+                    obj.with_mut(|ctx| ctx.content = value);
+                },
+            )
+            .register_get_set(
+                "is_error",
+                |obj: &mut SharedMut<OnGraphqlResponseContext>| -> bool {
+                    obj.with_mut(|ctx| ctx.is_error)
+                },
+                |obj: &mut SharedMut<OnGraphqlResponseContext>, value: bool| {
+                    obj.with_mut(|ctx| ctx.is_error = value);
+                },
+            );
+    }
+}
+
+/// A GraphQL response, after any changes made by the `on_graphql_response` hook
+#[derive(Debug, PartialEq)]
+pub struct GraphqlResponse {
+    pub body: Value,
+    /// Text to add to the content of the tool result
+    pub content: Vec<String>,
+    /// Whether the script marked the tool result as an error
+    pub is_error: bool,
+}
+
+impl From<Value> for GraphqlResponse {
+    fn from(body: Value) -> Self {
+        Self {
+            body,
+            content: Vec::new(),
+            is_error: false,
+        }
+    }
+}
+
+pub fn on_graphql_response(
+    engine: &Arc<RhaiEngine>,
+    tool_name: &str,
+    variables: &Value,
+    status: StatusCode,
+    headers: &HeaderMap,
+    body: Value,
+) -> Result<GraphqlResponse, McpError> {
+    let hook_name = "on_graphql_response";
+
+    // Exit early if the hook doesn't exist, to skip converting the response
+    if !engine.ast_has_function(hook_name) {
+        return Ok(GraphqlResponse::from(body));
+    }
+
+    let context = OnGraphqlResponseContext {
+        tool_name: tool_name.to_string(),
+        variables: to_dynamic(variables)?,
+        status: INT::from(status.as_u16()),
+        headers: RhaiHeaderMap::from(headers.clone()),
+        body: to_dynamic(&body)?,
+        content: Array::new(),
+        is_error: false,
+    };
+
+    let shared_context = Arc::new(Mutex::new(context));
+
+    engine
+        .execute_hook(hook_name, (shared_context.clone(),))
+        .map_err(|err| hook_error(hook_name, *err))?;
+
+    let context = shared_context.lock();
+
+    let body = rhai::serde::from_dynamic(&context.body).map_err(|err| {
+        error!("Error when executing {hook_name} hook: Error converting context.body: {err}");
+        McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+    })?;
+
+    Ok(GraphqlResponse {
+        body,
+        content: context.content.iter().map(ToString::to_string).collect(),
+        is_error: context.is_error,
+    })
+}
+
+fn to_dynamic(value: &Value) -> Result<Dynamic, McpError> {
+    rhai::serde::to_dynamic(value).map_err(|err| {
+        error!("Error when executing on_graphql_response hook: Error converting JSON: {err}");
+        McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::{HeaderMap, StatusCode};
+    use rmcp::model::ErrorCode;
+    use serde_json::{Value, json};
+
+    use super::{GraphqlResponse, on_graphql_response};
+    use crate::engine::RhaiEngine;
+
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        Arc::new(engine)
+    }
+
+    fn run(script: &str, body: Value) -> Result<GraphqlResponse, super::McpError> {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-cache", "HIT".parse().unwrap());
+        on_graphql_response(
+            &create_engine(script),
+            "GetUser",
+            &json!({"id": "1"}),
+            StatusCode::OK,
+            &headers,
+            body,
+        )
+    }
+
+    #[test]
+    fn should_pass_through_when_no_hook_defined() {
+        let body = json!({"data": {"user": {"name": "Ada"}}});
+
+        let result = run("", body.clone()).expect("Should not error");
+
+        assert_eq!(result, GraphqlResponse::from(body));
+    }
+
+    #[test]
+    fn should_expose_request_and_response_details() {
+        let result = run(
+            r#"fn on_graphql_response(ctx) {
+                ctx.content.push(`${ctx.tool_name} ${ctx.variables.id} ${ctx.status} ${ctx.headers["x-cache"]} ${ctx.body.data.user.name}`);
+            }"#,
+            json!({"data": {"user": {"name": "Ada"}}}),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result.content, vec!["GetUser 1 200 HIT Ada"]);
+    }
+
+    #[test]
+    fn should_rewrite_body() {
+        let result = run(
+            r#"fn on_graphql_response(ctx) {
+                ctx.body.data.user.email = "[redacted]";
+                ctx.body.extensions = ();
+            }"#,
+            json!({"data": {"user": {"email": "ada@example.com"}}, "extensions": {"cost": 3}}),
+        )
+        .expect("Should not error");
+
+        assert_eq!(
+            result.body,
+            json!({"data": {"user": {"email": "[redacted]"}}, "extensions": null})
+        );
+    }
+
+    #[test]
+    fn should_mark_result_as_error() {
+        let result = run(
+            r#"fn on_graphql_response(ctx) {
+                if ctx.body.data.user == () {
+                    ctx.is_error = true;
+                    ctx.content.push("No user was found");
+                }
+            }"#,
+            json!({"data": {"user": null}}),
+        )
+        .expect("Should not error");
+
+        assert!(result.is_error);
+        assert_eq!(result.content, vec!["No user was found"]);
+    }
+
+    #[test]
+    fn should_return_thrown_error() {
+        let err = run(
+            r#"fn on_graphql_response(ctx) {
+                throw #{ message: "Blocked", code: ErrorCode::INVALID_REQUEST };
+            }"#,
+            json!({"data": null}),
+        )
+        .expect_err("Should error");
+
+        assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(err.message, "Blocked");
+    }
+}
@@ -0,0 +1,272 @@
+use std::sync::Arc;
+
+use http::request::Parts;
+use parking_lot::Mutex;
+use rhai::{Array, CustomType, Dynamic, Engine, TypeBuilder};
+use rmcp::model::{ErrorCode, Tool};
+use tracing::error;
+
+use super::{McpError, hook_error};
+use crate::{
+    engine::RhaiEngine,
+    shared_mut::{SharedMut, WithMut},
+    types::{RhaiHttpParts, RhaiTool},
+};
+
+const HOOK_NAME: &str = "on_list_tools";
+
+#[derive(Clone, Debug, CustomType)]
+pub struct OnListToolsContext {
+    /// The tools to list, which scripts can filter, reorder, and change
+    pub tools: Array,
+    pub incoming_request: RhaiHttpParts,
+}
+
+impl OnListToolsContext {
+    pub fn register(engine: &mut Engine) {
+        engine
+            .register_type::<OnListToolsContext>()
+            .register_get_set(
+                "tools",
+                |obj: &mut SharedMut<OnListToolsContext>| -> Array {
+                    obj.with_mut(|ctx| ctx.tools.clone())
+                },
+                |obj: &mut SharedMut<OnListToolsContext>, value: Array| {
+                    obj.with_mut(|ctx| ctx.tools = value);
+                },
+            )
+            .register_get(
+                "incoming_request",
+                |obj: &mut SharedMut<OnListToolsContext>| -> RhaiHttpParts {
+                    obj.with_mut(|ctx| ctx.incoming_request.clone())
+                },
+            );
+    }
+}
+
+/// A tool listed by the `on_list_tools` hook
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedTool {
+    /// The name of the tool before the hook renamed it
+    pub original_name: String,
+    pub tool: Tool,
+}
+
+impl From<Tool> for ListedTool {
+    fn from(tool: Tool) -> Self {
+        Self {
+            original_name: tool.name.to_string(),
+            tool,
+        }
+    }
+}
+
+/// Whether the scripts define the `on_list_tools` hook
+pub fn has_on_list_tools(engine: &Arc<RhaiEngine>) -> bool {
+    engine.ast_has_function(HOOK_NAME)
+}
+
+/// Run the `on_list_tools` hook on the tools that would be listed for a request
+pub fn on_list_tools(
+    engine: &Arc<RhaiEngine>,
+    tools: Vec<Tool>,
+    axum_parts: Option<&Parts>,
+) -> Result<Vec<ListedTool>, McpError> {
+    if !engine.ast_has_function(HOOK_NAME) {
+        return Ok(tools.into_iter().map(ListedTool::from).collect());
+    }
+
+    let context = OnListToolsContext {
+        tools: tools
+            .into_iter()
+            .map(|tool| Dynamic::from(RhaiTool::from(tool)))
+            .collect(),
+        incoming_request: match axum_parts {
+            Some(parts) => RhaiHttpParts::from(parts.clone()),
+            None => RhaiHttpParts::default(),
+        },
+    };
+
+    let shared_context = Arc::new(Mutex::new(context));
+
+    engine
+        .execute_hook(HOOK_NAME, (shared_context.clone(),))
+        .map_err(|err| hook_error(HOOK_NAME, *err))?;
+
+    let context = shared_context.lock();
+
+    context
+        .tools
+        .iter()
+        .map(|tool| match tool.clone().try_cast::<RhaiTool>() {
+            Some(tool) => Ok(ListedTool {
+                original_name: tool.original_name().to_string(),
+                tool: tool.into_tool(),
+            }),
+            None => {
+                error!(
+                    "Error when executing {HOOK_NAME} hook: ctx.tools contains a {} instead of a Tool",
+                    tool.type_name()
+                );
+                Err(McpError::new(
+                    ErrorCode::INTERNAL_ERROR,
+                    "Internal error",
+                    None,
+                ))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rmcp::model::{ErrorCode, Tool, ToolAnnotations};
+    use serde_json::{Map, json};
+
+    use super::{ListedTool, on_list_tools};
+    use crate::engine::RhaiEngine;
+
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        Arc::new(engine)
+    }
+
+    fn tools() -> Vec<Tool> {
+        let schema = json!({"type": "object", "properties": {"id": {"type": "string"}}});
+        let schema: Map<_, _> = serde_json::from_value(schema).unwrap();
+        vec![
+            Tool::new("GetUser", "Get a user", schema.clone())
+                .annotate(ToolAnnotations::new().read_only(true)),
+            Tool::new("DeleteUser", "Delete a user", schema),
+        ]
+    }
+
+    fn names(tools: &[ListedTool]) -> Vec<(&str, &str)> {
+        tools
+            .iter()
+            .map(|listed| (listed.tool.name.as_ref(), listed.original_name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn should_pass_through_when_no_hook_defined() {
+        let result = on_list_tools(&create_engine(""), tools(), None).expect("Should not error");
+
+        assert_eq!(
+            result,
+            tools()
+                .into_iter()
+                .map(ListedTool::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn should_filter_and_reorder_tools() {
+        let engine = create_engine(
+            r#"fn on_list_tools(ctx) {
+                ctx.tools = ctx.tools.filter(|tool| tool.annotations.readOnlyHint == true);
+                ctx.tools.push(ctx.tools[0]);
+                ctx.tools.reverse();
+            }"#,
+        );
+
+        let result = on_list_tools(&engine, tools(), None).expect("Should not error");
+
+        assert_eq!(
+            names(&result),
+            vec![("GetUser", "GetUser"), ("GetUser", "GetUser")]
+        );
+    }
+
+    #[test]
+    fn should_rename_and_describe_tools() {
+        let engine = create_engine(
+            r#"fn on_list_tools(ctx) {
+                ctx.tools = ctx.tools.map(|tool| {
+                    tool.name = "acme_" + tool.name;
+                    tool.description += " in the Acme directory";
+                    tool.title = tool.original_name;
+                    tool
+                });
+            }"#,
+        );
+
+        let result = on_list_tools(&engine, tools(), None).expect("Should not error");
+
+        assert_eq!(
+            names(&result),
+            vec![
+                ("acme_GetUser", "GetUser"),
+                ("acme_DeleteUser", "DeleteUser")
+            ]
+        );
+        assert_eq!(
+            result[0].tool.description.as_deref(),
+            Some("Get a user in the Acme directory")
+        );
+        assert_eq!(result[0].tool.title.as_deref(), Some("GetUser"));
+    }
+
+    #[test]
+    fn should_change_annotations_and_input_schema() {
+        let engine = create_engine(
+            r#"fn on_list_tools(ctx) {
+                let tools = [];
+                for tool in ctx.tools {
+                    let destructive = tool.name == "DeleteUser";
+                    tool.annotations = #{ destructiveHint: destructive };
+                    let schema = tool.input_schema;
+                    schema.required = ["id"];
+                    tool.input_schema = schema;
+                    tools.push(tool);
+                }
+                ctx.tools = tools;
+            }"#,
+        );
+
+        let result = on_list_tools(&engine, tools(), None).expect("Should not error");
+
+        let annotations = result[1].tool.annotations.as_ref().expect("annotations");
+        assert_eq!(annotations.destructive_hint, Some(true));
+        assert_eq!(annotations.read_only_hint, None);
+        assert_eq!(
+            result[0].tool.input_schema.get("required"),
+            Some(&json!(["id"]))
+        );
+    }
+
+    #[test]
+    fn should_reject_values_that_are_not_tools() {
+        let engine = create_engine(
+            r#"fn on_list_tools(ctx) {
+                ctx.tools.push(#{ name: "Fake" });
+            }"#,
+        );
+
+        let err = on_list_tools(&engine, tools(), None).expect_err("Should error");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn should_reject_invalid_annotations() {
+        let engine = create_engine(
+            r#"fn on_list_tools(ctx) {
+                ctx.tools = ctx.tools.map(|tool| {
+                    tool.annotations = #{ readOnlyHint: "yes" };
+                    tool
+                });
+            }"#,
+        );
+
+        let err = on_list_tools(&engine, tools(), None).expect_err("Should error");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use rhai::Map;
+
+use super::on_schema_updated::insert_tool_changes;
+use super::{McpError, hook_error};
+use crate::engine::RhaiEngine;
+
+const HOOK_NAME: &str = "on_operations_updated";
+
+/// New operations that the running server is about to apply
+#[derive(Clone, Debug, Default)]
+pub struct OperationsUpdate {
+    /// The hex-encoded SHA-256 hash of the schema the operations were validated against
+    pub schema_hash: String,
+    /// The names of the operation tools before the update
+    pub tools_before: Vec<String>,
+    /// The names of the operation tools after the update
+    pub tools_after: Vec<String>,
+}
+
+/// Whether the scripts define the `on_operations_updated` hook
+pub fn has_on_operations_updated(engine: &Arc<RhaiEngine>) -> bool {
+    engine.ast_has_function(HOOK_NAME)
+}
+
+/// Run the `on_operations_updated` hook before the server applies new operations.
+///
+/// Returns an error when the hook throws, in which case the server keeps the previous operations.
+pub fn on_operations_updated(
+    engine: &Arc<RhaiEngine>,
+    update: &OperationsUpdate,
+) -> Result<(), McpError> {
+    if !engine.ast_has_function(HOOK_NAME) {
+        return Ok(());
+    }
+
+    let mut info = Map::new();
+    info.insert("schema_hash".into(), update.schema_hash.clone().into());
+    insert_tool_changes(&mut info, &update.tools_before, &update.tools_after);
+
+    engine
+        .execute_hook(HOOK_NAME, (info,))
+        .map_err(|err| hook_error(HOOK_NAME, *err))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rmcp::model::ErrorCode;
+
+    use super::{OperationsUpdate, on_operations_updated};
+    use crate::engine::RhaiEngine;
+
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        Arc::new(engine)
+    }
+
+    fn test_update() -> OperationsUpdate {
+        OperationsUpdate {
+            schema_hash: "hash".to_string(),
+            tools_before: vec!["GetUser".to_string()],
+            tools_after: vec!["GetUser".to_string(), "GetOrders".to_string()],
+        }
+    }
+
+    #[test]
+    fn should_succeed_when_no_hook_defined() {
+        let engine = create_engine("");
+
+        let result = on_operations_updated(&engine, &test_update());
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_pass_the_update_to_the_hook() {
+        let engine = create_engine(
+            r#"fn on_operations_updated(info) {
+                if info.schema_hash != "hash" {
+                    throw "wrong hash";
+                }
+                if info.tools_before != ["GetUser"] || info.tools_after != ["GetUser", "GetOrders"] {
+                    throw "wrong tools";
+                }
+                if info.added_tools != ["GetOrders"] || info.removed_tools != [] {
+                    throw "wrong tool changes";
+                }
+            }"#,
+        );
+
+        let result = on_operations_updated(&engine, &test_update());
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn should_return_error_when_hook_rejects_the_update() {
+        let engine = create_engine(
+            r#"fn on_operations_updated(info) {
+                throw #{ message: "Operations are frozen", code: ErrorCode::INVALID_REQUEST };
+            }"#,
+        );
+
+        let err = on_operations_updated(&engine, &test_update()).expect_err("Should reject");
+
+        assert_eq!(err.code, ErrorCode::INVALID_REQUEST);
+        assert_eq!(err.message, "Operations are frozen");
+    }
+}
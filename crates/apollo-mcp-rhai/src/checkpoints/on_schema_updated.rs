@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use rhai::{Array, Dynamic, Map};
+
+use super::{McpError, hook_error};
+use crate::engine::RhaiEngine;
+
+const HOOK_NAME: &str = "on_schema_updated";
+
+/// A new schema that the running server is about to apply
+#[derive(Clone, Debug, Default)]
+pub struct SchemaUpdate {
+    /// The hex-encoded SHA-256 hash of the new schema
+    pub schema_hash: String,
+    /// The hex-encoded SHA-256 hash of the schema it replaces
+    pub previous_schema_hash: String,
+    /// The names of the types the new schema adds
+    pub added_types: Vec<String>,
+    /// The names of the types the new schema removes
+    pub removed_types: Vec<String>,
+    /// The names of the operation tools before the update
+    pub tools_before: Vec<String>,
+    /// The names of the operation tools after the update
+    pub tools_after: Vec<String>,
+}
+
+/// Whether the scripts define the `on_schema_updated` hook
+pub fn has_on_schema_updated(engine: &Arc<RhaiEngine>) -> bool {
+    engine.ast_has_function(HOOK_NAME)
+}
+
+/// Run the `on_schema_updated` hook before the server applies a new schema.
+///
+/// Returns an error when the hook throws, in which case the server keeps the previous schema.
+pub fn on_schema_updated(engine: &Arc<RhaiEngine>, update: &SchemaUpdate) -> Result<(), McpError> {
+    if !engine.ast_has_function(HOOK_NAME) {
+        return Ok(());
+    }
+
+    let mut info = Map::new();
+    info.insert("schema_hash".into(), update.schema_hash.clone().into());
+    info.insert(
+        "previous_schema_hash".into(),
+        update.previous_schema_hash.clone().into(),
+    );
+    info.insert("added_types".into(), names(&update.added_types).into());
+    info.insert("removed_types".into(), names(&update.removed_types).into());
+    insert_tool_changes(&mut info, &update.tools_before, &update.tools_after);
+
+    engine
+        .execute_hook(HOOK_NAME, (info,))
+        .map_err(|err| hook_error(HOOK_NAME, *err))?;
+    Ok(())
+}
+
+pub(super) fn names(names: &[String]) -> Array {
+    names.iter().cloned().map(Dynamic::from).collect()
+}
+
+/// Add the tool names before and after an update, and which tools it adds and removes
+pub(super) fn insert_tool_changes(info: &mut Map, before: &[String], after: &[String]) {
+    let added: Vec<String> = after
+        .iter()
+        .filter(|name| !before.contains(name))
+        .cloned()
+        .collect();
+    let removed: Vec<String> = before
+        .iter()
+        .filter(|name| !after.contains(name))
+        .cloned()
+        .collect();
+
+    info.insert("tools_before".into(), names(before).into());
+    info.insert("tools_after".into(), names(after).into());
+    info.insert("added_tools".into(), names(&added).into());
+    info.insert("removed_tools".into(), names(&removed).into());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rmcp::model::ErrorCode;
+
+    use super::{SchemaUpdate, on_schema_updated};
+    use crate::engine::RhaiEngine;
+
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        Arc::new(engine)
+    }
+
+    fn test_update() -> SchemaUpdate {
+        SchemaUpdate {
+            schema_hash: "new-hash".to_string(),
+            previous_schema_hash: "old-hash".to_string(),
+            added_types: vec!["Review".to_string()],
+            removed_types: vec!["Rating".to_string()],
+            tools_before: vec!["GetUser".to_string(), "GetRating".to_string()],
+            tools_after: vec!["GetUser".to_string(), "GetReview".to_string()],
+        }
+    }
+
+    #[test]
+    fn should_succeed_when_no_hook_defined() {
+        let engine = create_engine("");
+
+        let result = on_schema_updated(&engine, &test_update());
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn should_pass_the_update_to_the_hook() {
+        let engine = create_engine(
+            r#"fn on_schema_updated(info) {
+                if info.schema_hash != "new-hash" || info.previous_schema_hash != "old-hash" {
+                    throw "wrong hashes";
+                }
+                if info.added_types != ["Review"] || info.removed_types != ["Rating"] {
+                    throw "wrong types";
+                }
+                if info.tools_before != ["GetUser", "GetRating"] || info.tools_after != ["GetUser", "GetReview"] {
+                    throw "wrong tools";
+                }
+                if info.added_tools != ["GetReview"] || info.removed_tools != ["GetRating"] {
+                    throw "wrong tool changes";
+                }
+            }"#,
+        );
+
+        let result = on_schema_updated(&engine, &test_update());
+
+        assert!(result.is_ok(), "{result:?}");
+    }
+
+    #[test]
+    fn should_return_error_when_hook_rejects_the_update() {
+        let engine = create_engine(
+            r#"fn on_schema_updated(info) {
+                if info.removed_tools.contains("GetRating") {
+                    throw #{ message: "GetRating is required" };
+                }
+            }"#,
+        );
+
+        let err = on_schema_updated(&engine, &test_update()).expect_err("Should reject");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+        assert_eq!(err.message, "GetRating is required");
+    }
+}
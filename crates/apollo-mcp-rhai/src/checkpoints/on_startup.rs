@@ -1,19 +1,17 @@
 use std::sync::Arc;
 
-use parking_lot::Mutex;
 use rhai::EvalAltResult;
 
 use crate::engine::RhaiEngine;
 
-pub fn on_startup(engine: &Arc<Mutex<RhaiEngine>>) -> Result<(), Box<EvalAltResult>> {
+pub fn on_startup(engine: &Arc<RhaiEngine>) -> Result<(), Box<EvalAltResult>> {
     let hook_name = "on_startup";
-    let mut engine_guard = engine.lock();
 
-    if !engine_guard.ast_has_function(hook_name) {
+    if !engine.ast_has_function(hook_name) {
         return Ok(());
     }
 
-    engine_guard.execute_hook(hook_name, ())?;
+    engine.execute_hook(hook_name, ())?;
     Ok(())
 }
 
@@ -21,17 +19,15 @@ pub fn on_startup(engine: &Arc<Mutex<RhaiEngine>>) -> Result<(), Box<EvalAltResu
 mod tests {
     use std::sync::Arc;
 
-    use parking_lot::Mutex;
-
     use super::on_startup;
     use crate::engine::RhaiEngine;
 
-    fn create_engine(script: &str) -> Arc<Mutex<RhaiEngine>> {
-        let mut engine = RhaiEngine::new("rhai");
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
         engine
             .load_from_string(script)
             .expect("Script should compile");
-        Arc::new(Mutex::new(engine))
+        Arc::new(engine)
     }
 
     #[test]
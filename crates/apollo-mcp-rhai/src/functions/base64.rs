@@ -0,0 +1,153 @@
+use rhai::plugin::*;
+use rhai::{Engine, Module};
+use rhai::{export_module, exported_module};
+
+pub struct RhaiBase64 {}
+
+impl RhaiBase64 {
+    pub fn register(engine: &mut Engine) {
+        engine.register_static_module("Base64", exported_module!(rhai_base64_module).into());
+    }
+}
+
+/// Decode base64 `input` with `engine` into a UTF-8 string
+pub(crate) fn decode_to_string(
+    engine: &impl base64::Engine,
+    input: &str,
+) -> Result<String, Box<EvalAltResult>> {
+    let bytes = engine
+        .decode(input)
+        .map_err(|e| format!("Invalid base64: {e}"))?;
+    String::from_utf8(bytes).map_err(|_| "Decoded base64 is not valid UTF-8".into())
+}
+
+// Rhai's #[export_module] macro generates code that uses unwrap internally
+#[allow(clippy::unwrap_used)]
+#[export_module]
+mod rhai_base64_module {
+    use base64::Engine as _;
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+    use rhai::Dynamic;
+    use rhai::ImmutableString;
+    use rhai::plugin::TypeId;
+
+    #[rhai_fn(pure)]
+    pub(crate) fn encode(input: &mut ImmutableString) -> String {
+        STANDARD.encode(input.as_bytes())
+    }
+
+    #[rhai_fn(pure, return_raw)]
+    pub(crate) fn decode(input: &mut ImmutableString) -> Result<String, Box<EvalAltResult>> {
+        super::decode_to_string(&STANDARD, input)
+    }
+
+    #[rhai_fn(pure)]
+    pub(crate) fn encode_url(input: &mut ImmutableString) -> String {
+        URL_SAFE_NO_PAD.encode(input.as_bytes())
+    }
+
+    /// Accepts input with or without padding
+    #[rhai_fn(pure, return_raw)]
+    pub(crate) fn decode_url(input: &mut ImmutableString) -> Result<String, Box<EvalAltResult>> {
+        super::decode_to_string(&URL_SAFE_NO_PAD, input.trim_end_matches('='))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::{Engine, EvalAltResult, FuncArgs, Scope};
+
+    use crate::functions::RhaiBase64;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        script: &str,
+        args: impl FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+
+        RhaiBase64::register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .expect("Script should be able to run with AST");
+
+        engine.call_fn::<T>(&mut scope, &ast, "test", args)
+    }
+
+    #[test]
+    fn should_encode_and_decode_base64() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::encode("hello?>");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "aGVsbG8/Pg==");
+
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::decode("aGVsbG8/Pg==");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "hello?>");
+    }
+
+    #[test]
+    fn should_encode_and_decode_base64url() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::encode_url("hello?>");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "aGVsbG8_Pg");
+
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::decode_url("aGVsbG8_Pg") + Base64::decode_url("aGVsbG8_Pg==");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "hello?>hello?>");
+    }
+
+    #[test]
+    fn should_error_for_invalid_base64() {
+        let err = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::decode("not base64!");
+            }"#,
+            (),
+        )
+        .expect_err("Should error");
+
+        assert!(err.to_string().contains("Invalid base64"));
+    }
+
+    #[test]
+    fn should_error_when_decoded_bytes_are_not_utf8() {
+        let err = run_rhai_script::<String>(
+            r#"fn test() {
+                return Base64::decode("/w==");
+            }"#,
+            (),
+        )
+        .expect_err("Should error");
+
+        assert!(
+            err.to_string()
+                .contains("Decoded base64 is not valid UTF-8")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use rhai::plugin::*;
+use rhai::{Engine, Module};
+use rhai::{export_module, exported_module};
+
+pub struct RhaiHmac {}
+
+impl RhaiHmac {
+    pub fn register(engine: &mut Engine) {
+        engine.register_static_module("Hmac", exported_module!(rhai_hmac_module).into());
+    }
+}
+
+fn sha256_mac(key: &str, message: &str) -> Vec<u8> {
+    use hmac::Mac;
+
+    // HMAC accepts keys of any length, so creating it can't fail
+    #[allow(clippy::expect_used)]
+    let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Rhai's #[export_module] macro generates code that uses unwrap internally
+#[allow(clippy::unwrap_used)]
+#[export_module]
+mod rhai_hmac_module {
+    use base64::Engine as _;
+    use base64::engine::general_purpose::STANDARD;
+    use rhai::Dynamic;
+    use rhai::ImmutableString;
+    use rhai::plugin::TypeId;
+
+    pub(crate) fn sha256(key: ImmutableString, message: ImmutableString) -> String {
+        hex::encode(super::sha256_mac(&key, &message))
+    }
+
+    pub(crate) fn sha256_base64(key: ImmutableString, message: ImmutableString) -> String {
+        STANDARD.encode(super::sha256_mac(&key, &message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::{Engine, EvalAltResult, FuncArgs, Scope};
+
+    use crate::functions::RhaiHmac;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        script: &str,
+        args: impl FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+
+        RhaiHmac::register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .expect("Script should be able to run with AST");
+
+        engine.call_fn::<T>(&mut scope, &ast, "test", args)
+    }
+
+    #[test]
+    fn should_return_correct_hmac_sha256() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Hmac::sha256("key", "The quick brown fox jumps over the lazy dog");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(
+            result,
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn should_return_base64_hmac_sha256() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Hmac::sha256_base64("key", "The quick brown fox jumps over the lazy dog");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "97yD9DBThCSxMpjmqm+xQ+9NWaFJRhdZl0edvC0aPNg=");
+    }
+
+    #[test]
+    fn should_return_different_macs_for_different_keys() {
+        let result_a = run_rhai_script::<String>(
+            r#"fn test() {
+                return Hmac::sha256("a", "message");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        let result_b = run_rhai_script::<String>(
+            r#"fn test() {
+                return Hmac::sha256("b", "message");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_ne!(result_a, result_b);
+    }
+}
@@ -1,12 +1,11 @@
-use std::sync::{Arc, LazyLock};
+use std::sync::LazyLock;
 use std::time::Duration;
 
-use parking_lot::Mutex;
 use reqwest::Method;
 use rhai::plugin::*;
 use rhai::{Engine, EvalAltResult, Map, Module};
 
-use crate::types::{HttpResponse, Promise, PromiseState};
+use crate::types::{HttpResponse, Promise};
 
 static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
 
@@ -68,19 +67,11 @@ impl HttpOptions {
 }
 
 fn spawn_request(method: Method, url: String, options: HttpOptions) -> Promise {
-    let (tx, rx) = tokio::sync::oneshot::channel();
-
-    tokio::spawn(async move {
-        let result = execute_request(method, url, options).await;
-        let value = result.map(Dynamic::from);
-        let _ = tx.send(value);
-    });
-
-    Promise {
-        state: PromiseState::Pending,
-        resolved_value: None,
-        receiver: Arc::new(Mutex::new(Some(rx))),
-    }
+    Promise::spawn(async move {
+        execute_request(method, url, options)
+            .await
+            .map(Dynamic::from)
+    })
 }
 
 async fn execute_request(
@@ -1,11 +1,27 @@
+mod base64;
 mod env;
+mod hmac;
 mod http;
 mod json;
+mod jwt;
 mod regex;
 mod sha256;
+mod store;
+mod telemetry;
+mod time;
+mod url;
+mod uuid;
 
+pub use base64::*;
 pub use env::*;
+pub use hmac::*;
 pub use http::*;
 pub use json::*;
+pub use jwt::*;
 pub use regex::*;
 pub use sha256::*;
+pub use store::*;
+pub use telemetry::*;
+pub use time::*;
+pub use url::*;
+pub use uuid::*;
@@ -0,0 +1,433 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use rhai::{Dynamic, Engine, EvalAltResult, INT, ImmutableString, Module};
+use tracing::warn;
+
+/// The default maximum size of the values in the store, in bytes
+pub const DEFAULT_MAX_STORE_SIZE: usize = 10 * 1024 * 1024;
+
+/// Key-value store shared by every call into the scripts, which keeps its entries when the scripts
+/// are reloaded
+#[derive(Clone)]
+pub struct RhaiStore {
+    entries: Arc<Mutex<Entries>>,
+    max_size: usize,
+}
+
+struct Entries {
+    values: HashMap<String, Entry>,
+    size: usize,
+}
+
+struct Entry {
+    value: Dynamic,
+    size: usize,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) -> Option<Entry> {
+        let entry = self.values.remove(key)?;
+        self.size -= entry.size;
+        Some(entry)
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        let mut removed = 0;
+        self.values.retain(|_, entry| {
+            let expired = entry.is_expired(now);
+            if expired {
+                removed += entry.size;
+            }
+            !expired
+        });
+        self.size -= removed;
+    }
+
+    /// Set `key` to `entry`, making room by removing expired values if needed
+    fn insert(
+        &mut self,
+        key: &str,
+        entry: Entry,
+        now: Instant,
+        max_size: usize,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let previous_size = self.values.get(key).map_or(0, |entry| entry.size);
+        if self.size - previous_size + entry.size > max_size {
+            self.remove_expired(now);
+            let previous_size = self.values.get(key).map_or(0, |entry| entry.size);
+            if self.size - previous_size + entry.size > max_size {
+                warn!("Rhai store is full, could not set '{key}'");
+                return Err(format!("Store is full, could not set '{key}'").into());
+            }
+        }
+
+        self.remove(key);
+        self.size += entry.size;
+        self.values.insert(key.to_string(), entry);
+        Ok(())
+    }
+
+    fn live(&mut self, key: &str, now: Instant) -> Option<&mut Entry> {
+        if self.values.get(key)?.is_expired(now) {
+            self.remove(key);
+            return None;
+        }
+        self.values.get_mut(key)
+    }
+}
+
+impl Default for RhaiStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_STORE_SIZE)
+    }
+}
+
+impl RhaiStore {
+    /// Create a store holding up to `max_size` bytes of keys and values
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                values: HashMap::new(),
+                size: 0,
+            })),
+            max_size,
+        }
+    }
+
+    pub(crate) fn register(&self, engine: &mut Engine) {
+        let mut module = Module::new();
+
+        let store = self.clone();
+        module.set_native_fn("get", move |key: ImmutableString| Ok(store.get(&key)));
+
+        let store = self.clone();
+        module.set_native_fn("set", move |key: ImmutableString, value: Dynamic| {
+            store.set(&key, value, None)
+        });
+
+        let store = self.clone();
+        module.set_native_fn(
+            "set",
+            move |key: ImmutableString, value: Dynamic, ttl: INT| {
+                store.set(&key, value, Some(ttl_from_seconds(ttl)?))
+            },
+        );
+
+        let store = self.clone();
+        module.set_native_fn("increment", move |key: ImmutableString| {
+            store.increment(&key, 1, None)
+        });
+
+        let store = self.clone();
+        module.set_native_fn("increment", move |key: ImmutableString, by: INT| {
+            store.increment(&key, by, None)
+        });
+
+        let store = self.clone();
+        module.set_native_fn(
+            "increment",
+            move |key: ImmutableString, by: INT, ttl: INT| {
+                store.increment(&key, by, Some(ttl_from_seconds(ttl)?))
+            },
+        );
+
+        let store = self.clone();
+        module.set_native_fn("delete", move |key: ImmutableString| Ok(store.delete(&key)));
+
+        engine.register_static_module("Store", module.into());
+    }
+
+    /// Get the value of `key`, or `()` if it isn't set or has expired
+    fn get(&self, key: &str) -> Dynamic {
+        self.entries
+            .lock()
+            .live(key, Instant::now())
+            .map(|entry| entry.value.clone())
+            .unwrap_or(Dynamic::UNIT)
+    }
+
+    /// Set `key` to `value`, expiring it after `ttl` if given
+    fn set(
+        &self,
+        key: &str,
+        value: Dynamic,
+        ttl: Option<Duration>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        let now = Instant::now();
+        let value = value.flatten();
+        let entry = Entry {
+            size: key.len() + size_of_value(&value),
+            value,
+            expires_at: ttl.map(|ttl| now + ttl),
+        };
+
+        self.entries.lock().insert(key, entry, now, self.max_size)
+    }
+
+    /// Add `by` to the integer value of `key`, starting from 0 if it isn't set. A `ttl` only
+    /// applies when this creates the value.
+    fn increment(
+        &self,
+        key: &str,
+        by: INT,
+        ttl: Option<Duration>,
+    ) -> Result<INT, Box<EvalAltResult>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+
+        if let Some(entry) = entries.live(key, now) {
+            let current = entry
+                .value
+                .as_int()
+                .map_err(|_| format!("Store value of '{key}' is not an integer"))?;
+            let value = current
+                .checked_add(by)
+                .ok_or_else(|| format!("Store value of '{key}' overflowed"))?;
+            entry.value = Dynamic::from_int(value);
+            return Ok(value);
+        }
+
+        // Create the value under the same lock, so that concurrent first increments all count
+        let value = Dynamic::from_int(by);
+        let entry = Entry {
+            size: key.len() + size_of_value(&value),
+            value,
+            expires_at: ttl.map(|ttl| now + ttl),
+        };
+        entries.insert(key, entry, now, self.max_size)?;
+        Ok(by)
+    }
+
+    /// Remove `key`, returning whether it was set
+    fn delete(&self, key: &str) -> bool {
+        let mut entries = self.entries.lock();
+        let now = Instant::now();
+        entries
+            .remove(key)
+            .is_some_and(|entry| !entry.is_expired(now))
+    }
+}
+
+fn ttl_from_seconds(seconds: INT) -> Result<Duration, Box<EvalAltResult>> {
+    u64::try_from(seconds)
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(|| {
+            format!("Store TTL must be a positive number of seconds, got {seconds}").into()
+        })
+}
+
+/// Approximate how many bytes a value takes up in the store
+fn size_of_value(value: &Dynamic) -> usize {
+    if let Some(string) = value.read_lock::<ImmutableString>() {
+        string.len()
+    } else if let Some(array) = value.read_lock::<rhai::Array>() {
+        array.iter().map(size_of_value).sum::<usize>() + size_of::<Dynamic>()
+    } else if let Some(map) = value.read_lock::<rhai::Map>() {
+        map.iter()
+            .map(|(key, value)| key.len() + size_of_value(value))
+            .sum::<usize>()
+            + size_of::<Dynamic>()
+    } else if let Some(blob) = value.read_lock::<rhai::Blob>() {
+        blob.len()
+    } else {
+        size_of::<Dynamic>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rhai::{Dynamic, Engine, EvalAltResult, INT, Scope};
+
+    use super::RhaiStore;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        store: &RhaiStore,
+        script: &str,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        store.register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine.call_fn::<T>(&mut Scope::new(), &ast, "test", ())
+    }
+
+    #[test]
+    fn should_get_values_that_were_set() {
+        let store = RhaiStore::default();
+
+        run_rhai_script::<()>(
+            &store,
+            r#"fn test() { Store::set("token", #{ value: "abc" }); }"#,
+        )
+        .expect("Should not error");
+        let result =
+            run_rhai_script::<String>(&store, r#"fn test() { Store::get("token").value }"#)
+                .expect("Should not error");
+
+        assert_eq!(result, "abc");
+    }
+
+    #[test]
+    fn should_return_unit_for_missing_keys() {
+        let store = RhaiStore::default();
+
+        let result = run_rhai_script::<Dynamic>(&store, r#"fn test() { Store::get("missing") }"#)
+            .expect("Should not error");
+
+        assert!(result.is_unit());
+    }
+
+    #[test]
+    fn should_expire_values_after_their_ttl() {
+        let store = RhaiStore::default();
+        store
+            .set(
+                "short",
+                Dynamic::from_int(1),
+                Some(Duration::from_millis(10)),
+            )
+            .expect("Should set");
+        store
+            .set("long", Dynamic::from_int(2), Some(Duration::from_secs(60)))
+            .expect("Should set");
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(store.get("short").is_unit());
+        assert_eq!(store.get("long").as_int(), Ok(2));
+    }
+
+    #[test]
+    fn should_increment_counters() {
+        let store = RhaiStore::default();
+
+        let result = run_rhai_script::<INT>(
+            &store,
+            r#"fn test() {
+                Store::increment("count");
+                Store::increment("count", 5, 60);
+                Store::increment("count", -2)
+            }"#,
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    fn should_count_every_concurrent_first_increment() {
+        let store = RhaiStore::default();
+
+        for round in 0..20 {
+            let key = format!("hits-{round}");
+            let barrier = std::sync::Barrier::new(8);
+            std::thread::scope(|scope| {
+                for _ in 0..8 {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        store.increment(&key, 1, None).expect("Should increment");
+                    });
+                }
+            });
+
+            assert_eq!(store.get(&key).as_int(), Ok(8));
+        }
+    }
+
+    #[test]
+    fn should_not_increment_values_that_are_not_integers() {
+        let store = RhaiStore::default();
+
+        let err = run_rhai_script::<INT>(
+            &store,
+            r#"fn test() { Store::set("name", "abc"); Store::increment("name") }"#,
+        )
+        .expect_err("Should error");
+
+        assert!(
+            err.to_string()
+                .contains("Store value of 'name' is not an integer")
+        );
+    }
+
+    #[test]
+    fn should_delete_values() {
+        let store = RhaiStore::default();
+
+        let result = run_rhai_script::<rhai::Array>(
+            &store,
+            r#"fn test() {
+                Store::set("key", 1);
+                let deleted = Store::delete("key");
+                [deleted, Store::delete("key"), Store::get("key")]
+            }"#,
+        )
+        .expect("Should not error");
+
+        assert_eq!(result[0].as_bool(), Ok(true));
+        assert_eq!(result[1].as_bool(), Ok(false));
+        assert!(result[2].is_unit());
+    }
+
+    #[test]
+    fn should_reject_values_past_the_memory_cap() {
+        let store = RhaiStore::new(16);
+
+        let err = run_rhai_script::<()>(
+            &store,
+            r#"fn test() { Store::set("a", "0123456789"); Store::set("b", "0123456789"); }"#,
+        )
+        .expect_err("Should error");
+
+        assert!(err.to_string().contains("Store is full, could not set 'b'"));
+        // Replacing a value only counts the new size against the cap
+        store
+            .set("a", Dynamic::from("abcdefghij"), None)
+            .expect("Should replace the value");
+    }
+
+    #[test]
+    fn should_make_room_by_removing_expired_values() {
+        let store = RhaiStore::new(16);
+        store
+            .set(
+                "a",
+                Dynamic::from("0123456789"),
+                Some(Duration::from_millis(10)),
+            )
+            .expect("Should set");
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        store
+            .set("b", Dynamic::from("0123456789"), None)
+            .expect("Should set after the first value expired");
+    }
+
+    #[test]
+    fn should_reject_ttls_that_are_not_positive() {
+        let store = RhaiStore::default();
+
+        let err = run_rhai_script::<()>(&store, r#"fn test() { Store::set("key", 1, 0); }"#)
+            .expect_err("Should error");
+
+        assert!(
+            err.to_string()
+                .contains("Store TTL must be a positive number of seconds, got 0")
+        );
+    }
+}
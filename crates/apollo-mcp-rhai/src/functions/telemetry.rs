@@ -0,0 +1,377 @@
+use std::sync::Arc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, FLOAT, INT, ImmutableString, Map, Module};
+
+/// The prefix of the names of metrics recorded by scripts
+pub const METRIC_PREFIX: &str = "apollo.mcp.rhai.";
+
+/// The longest metric name scripts can use, without the prefix
+const MAX_METRIC_NAME_LENGTH: usize = 200;
+
+/// The value of a metric or span attribute set by a script
+#[derive(Clone, Debug, PartialEq)]
+pub enum TelemetryValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<Dynamic> for TelemetryValue {
+    fn from(value: Dynamic) -> Self {
+        if let Ok(value) = value.as_bool() {
+            Self::Bool(value)
+        } else if let Ok(value) = value.as_int() {
+            Self::Int(value)
+        } else if let Ok(value) = value.as_float() {
+            Self::Float(value)
+        } else {
+            Self::String(value.to_string())
+        }
+    }
+}
+
+/// Records the metrics and span attributes that scripts emit
+pub trait ScriptTelemetry: Send + Sync {
+    /// Add `value` to the counter named `name`
+    fn increment(
+        &self,
+        name: &str,
+        value: u64,
+        attributes: &[(String, TelemetryValue)],
+    ) -> Result<(), String>;
+
+    /// Record `value` in the histogram named `name`
+    fn record(
+        &self,
+        name: &str,
+        value: f64,
+        attributes: &[(String, TelemetryValue)],
+    ) -> Result<(), String>;
+
+    /// Set an attribute on the current span
+    fn set_span_attribute(&self, key: &str, value: TelemetryValue);
+}
+
+/// The `Telemetry` module, which hands what scripts emit to a [`ScriptTelemetry`]. Scripts can
+/// still call it when no telemetry is set, in which case their calls do nothing.
+#[derive(Clone, Default)]
+pub struct RhaiTelemetry {
+    telemetry: Option<Arc<dyn ScriptTelemetry>>,
+}
+
+impl RhaiTelemetry {
+    pub fn new(telemetry: impl ScriptTelemetry + 'static) -> Self {
+        Self {
+            telemetry: Some(Arc::new(telemetry)),
+        }
+    }
+
+    pub(crate) fn register(&self, engine: &mut Engine) {
+        let mut module = Module::new();
+
+        let telemetry = self.clone();
+        module.set_native_fn("increment", move |name: ImmutableString| {
+            telemetry.increment(&name, 1, Map::new())
+        });
+
+        let telemetry = self.clone();
+        module.set_native_fn("increment", move |name: ImmutableString, value: INT| {
+            telemetry.increment(&name, value, Map::new())
+        });
+
+        let telemetry = self.clone();
+        module.set_native_fn(
+            "increment",
+            move |name: ImmutableString, value: INT, attributes: Map| {
+                telemetry.increment(&name, value, attributes)
+            },
+        );
+
+        let telemetry = self.clone();
+        module.set_native_fn("record", move |name: ImmutableString, value: FLOAT| {
+            telemetry.record(&name, value, Map::new())
+        });
+
+        let telemetry = self.clone();
+        module.set_native_fn("record", move |name: ImmutableString, value: INT| {
+            telemetry.record(&name, value as FLOAT, Map::new())
+        });
+
+        let telemetry = self.clone();
+        module.set_native_fn(
+            "record",
+            move |name: ImmutableString, value: FLOAT, attributes: Map| {
+                telemetry.record(&name, value, attributes)
+            },
+        );
+
+        let telemetry = self.clone();
+        module.set_native_fn(
+            "record",
+            move |name: ImmutableString, value: INT, attributes: Map| {
+                telemetry.record(&name, value as FLOAT, attributes)
+            },
+        );
+
+        let telemetry = self.clone();
+        module.set_native_fn(
+            "set_attribute",
+            move |key: ImmutableString, value: Dynamic| {
+                telemetry.set_attribute(&key, value);
+                Ok(())
+            },
+        );
+
+        engine.register_static_module("Telemetry", module.into());
+    }
+
+    fn increment(&self, name: &str, value: INT, attributes: Map) -> Result<(), Box<EvalAltResult>> {
+        let name = metric_name(name)?;
+        let value = u64::try_from(value)
+            .map_err(|_| format!("Telemetry counters can't be decremented, got {value}"))?;
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.increment(&name, value, &metric_attributes(attributes))?;
+        }
+        Ok(())
+    }
+
+    fn record(&self, name: &str, value: FLOAT, attributes: Map) -> Result<(), Box<EvalAltResult>> {
+        let name = metric_name(name)?;
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.record(&name, value, &metric_attributes(attributes))?;
+        }
+        Ok(())
+    }
+
+    fn set_attribute(&self, key: &str, value: Dynamic) {
+        if let Some(telemetry) = &self.telemetry {
+            telemetry.set_span_attribute(key, value.into());
+        }
+    }
+}
+
+/// Namespace the name a script gave a metric, checking that it's a valid instrument name
+fn metric_name(name: &str) -> Result<String, Box<EvalAltResult>> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.len() <= MAX_METRIC_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '/'));
+    if !valid {
+        return Err(format!(
+            "Invalid metric name '{name}': use up to {MAX_METRIC_NAME_LENGTH} letters, digits, '_', '.', '-' or '/', starting with a letter or digit"
+        )
+        .into());
+    }
+    Ok(format!("{METRIC_PREFIX}{name}"))
+}
+
+fn metric_attributes(attributes: Map) -> Vec<(String, TelemetryValue)> {
+    attributes
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+    use rhai::{Engine, EvalAltResult, Scope};
+
+    use super::{RhaiTelemetry, ScriptTelemetry, TelemetryValue};
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Emitted {
+        Increment(String, u64, Vec<(String, TelemetryValue)>),
+        Record(String, f64, Vec<(String, TelemetryValue)>),
+        SpanAttribute(String, TelemetryValue),
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingTelemetry(Arc<Mutex<Vec<Emitted>>>);
+
+    impl ScriptTelemetry for RecordingTelemetry {
+        fn increment(
+            &self,
+            name: &str,
+            value: u64,
+            attributes: &[(String, TelemetryValue)],
+        ) -> Result<(), String> {
+            if name == "apollo.mcp.rhai.reserved" {
+                return Err(format!("Metric name '{name}' is reserved"));
+            }
+            self.0.lock().push(Emitted::Increment(
+                name.to_string(),
+                value,
+                attributes.to_vec(),
+            ));
+            Ok(())
+        }
+
+        fn record(
+            &self,
+            name: &str,
+            value: f64,
+            attributes: &[(String, TelemetryValue)],
+        ) -> Result<(), String> {
+            self.0.lock().push(Emitted::Record(
+                name.to_string(),
+                value,
+                attributes.to_vec(),
+            ));
+            Ok(())
+        }
+
+        fn set_span_attribute(&self, key: &str, value: TelemetryValue) {
+            self.0
+                .lock()
+                .push(Emitted::SpanAttribute(key.to_string(), value));
+        }
+    }
+
+    fn run_rhai_script(script: &str) -> (Result<(), Box<EvalAltResult>>, Vec<Emitted>) {
+        let recorded = RecordingTelemetry::default();
+        let mut engine = Engine::new();
+        RhaiTelemetry::new(recorded.clone()).register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        let result = engine.call_fn::<()>(&mut Scope::new(), &ast, "test", ());
+        let emitted = recorded.0.lock().clone();
+        (result, emitted)
+    }
+
+    #[test]
+    fn should_increment_namespaced_counters() {
+        let (result, emitted) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::increment("cache.miss");
+                Telemetry::increment("cache.miss", 3, #{ tool: "GetUser", hit: false });
+            }"#,
+        );
+
+        result.expect("Should not error");
+        assert_eq!(
+            emitted,
+            vec![
+                Emitted::Increment("apollo.mcp.rhai.cache.miss".to_string(), 1, vec![]),
+                Emitted::Increment(
+                    "apollo.mcp.rhai.cache.miss".to_string(),
+                    3,
+                    vec![
+                        ("hit".to_string(), TelemetryValue::Bool(false)),
+                        (
+                            "tool".to_string(),
+                            TelemetryValue::String("GetUser".to_string())
+                        ),
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_record_histograms() {
+        let (result, emitted) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::record("lookup.duration", 12);
+                Telemetry::record("lookup.duration", 1.5, #{ source: "cache" });
+            }"#,
+        );
+
+        result.expect("Should not error");
+        assert_eq!(
+            emitted,
+            vec![
+                Emitted::Record("apollo.mcp.rhai.lookup.duration".to_string(), 12.0, vec![]),
+                Emitted::Record(
+                    "apollo.mcp.rhai.lookup.duration".to_string(),
+                    1.5,
+                    vec![(
+                        "source".to_string(),
+                        TelemetryValue::String("cache".to_string())
+                    )]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_set_span_attributes() {
+        let (result, emitted) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::set_attribute("tenant", "acme");
+                Telemetry::set_attribute("retries", 2);
+            }"#,
+        );
+
+        result.expect("Should not error");
+        assert_eq!(
+            emitted,
+            vec![
+                Emitted::SpanAttribute(
+                    "tenant".to_string(),
+                    TelemetryValue::String("acme".to_string())
+                ),
+                Emitted::SpanAttribute("retries".to_string(), TelemetryValue::Int(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_metric_names() {
+        let (result, emitted) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::increment("not a name");
+            }"#,
+        );
+
+        let err = result.expect_err("Should error");
+        assert!(err.to_string().contains("Invalid metric name 'not a name'"));
+        assert!(emitted.is_empty());
+    }
+
+    #[test]
+    fn should_reject_negative_increments() {
+        let (result, _) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::increment("count", -1);
+            }"#,
+        );
+
+        let err = result.expect_err("Should error");
+        assert!(
+            err.to_string()
+                .contains("Telemetry counters can't be decremented, got -1")
+        );
+    }
+
+    #[test]
+    fn should_throw_errors_from_the_telemetry() {
+        let (result, _) = run_rhai_script(
+            r#"fn test() {
+                Telemetry::increment("reserved");
+            }"#,
+        );
+
+        let err = result.expect_err("Should error");
+        assert!(
+            err.to_string()
+                .contains("Metric name 'apollo.mcp.rhai.reserved' is reserved")
+        );
+    }
+
+    #[test]
+    fn should_do_nothing_without_telemetry() {
+        let mut engine = Engine::new();
+        RhaiTelemetry::default().register(&mut engine);
+
+        let result = engine.eval::<()>(
+            r#"Telemetry::increment("count"); Telemetry::set_attribute("key", "value");"#,
+        );
+
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,127 @@
+use rhai::plugin::*;
+use rhai::{Engine, Module};
+use rhai::{export_module, exported_module};
+
+pub struct RhaiTime {}
+
+impl RhaiTime {
+    pub fn register(engine: &mut Engine) {
+        engine.register_static_module("Time", exported_module!(rhai_time_module).into());
+    }
+}
+
+// Rhai's #[export_module] macro generates code that uses unwrap internally
+#[allow(clippy::unwrap_used)]
+#[export_module]
+mod rhai_time_module {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use rhai::Dynamic;
+    use rhai::INT;
+    use rhai::ImmutableString;
+
+    /// The current time as an RFC 3339 timestamp in UTC
+    #[rhai_fn(volatile)]
+    pub(crate) fn now() -> String {
+        Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    /// The current time in seconds since the Unix epoch
+    #[rhai_fn(volatile)]
+    pub(crate) fn unix() -> INT {
+        Utc::now().timestamp()
+    }
+
+    /// Format seconds since the Unix epoch as an RFC 3339 timestamp in UTC
+    #[rhai_fn(return_raw)]
+    pub(crate) fn format(seconds: INT) -> Result<String, Box<EvalAltResult>> {
+        DateTime::from_timestamp(seconds, 0)
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .ok_or_else(|| format!("Timestamp {seconds} is out of range").into())
+    }
+
+    /// Parse an RFC 3339 timestamp into seconds since the Unix epoch
+    #[rhai_fn(pure, return_raw)]
+    pub(crate) fn parse(input: &mut ImmutableString) -> Result<INT, Box<EvalAltResult>> {
+        DateTime::parse_from_rfc3339(input)
+            .map(|time| time.timestamp())
+            .map_err(|e| format!("Invalid RFC 3339 timestamp: {e}").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::{Engine, EvalAltResult, FuncArgs, INT, Scope};
+
+    use crate::functions::RhaiTime;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        script: &str,
+        args: impl FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+
+        RhaiTime::register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .expect("Script should be able to run with AST");
+
+        engine.call_fn::<T>(&mut scope, &ast, "test", args)
+    }
+
+    #[test]
+    fn should_return_current_time() {
+        let before = chrono::Utc::now().timestamp();
+        let result = run_rhai_script::<rhai::Array>(
+            r#"fn test() {
+                return [Time::now(), Time::unix()];
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+        let after = chrono::Utc::now().timestamp();
+
+        let now = chrono::DateTime::parse_from_rfc3339(&result[0].to_string())
+            .expect("Should be an RFC 3339 timestamp");
+        assert!((before..=after).contains(&now.timestamp()));
+        assert!((before..=after).contains(&result[1].as_int().expect("Should be an integer")));
+    }
+
+    #[test]
+    fn should_format_and_parse_timestamps() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Time::format(1700000000);
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "2023-11-14T22:13:20Z");
+
+        let result = run_rhai_script::<INT>(
+            r#"fn test() {
+                return Time::parse("2023-11-14T23:13:20+01:00");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, 1700000000);
+    }
+
+    #[test]
+    fn should_error_for_invalid_timestamp() {
+        let err = run_rhai_script::<INT>(
+            r#"fn test() {
+                return Time::parse("yesterday");
+            }"#,
+            (),
+        )
+        .expect_err("Should error");
+
+        assert!(err.to_string().contains("Invalid RFC 3339 timestamp"));
+    }
+}
@@ -0,0 +1,225 @@
+use rhai::plugin::*;
+use rhai::{Engine, Module};
+use rhai::{export_module, exported_module};
+
+pub struct RhaiUrl {}
+
+impl RhaiUrl {
+    pub fn register(engine: &mut Engine) {
+        engine.register_static_module("Url", exported_module!(rhai_url_module).into());
+    }
+}
+
+/// Read an optional string property of the map passed to `Url::build`
+fn string_property(parts: &rhai::Map, name: &str) -> Result<Option<String>, Box<EvalAltResult>> {
+    match parts.get(name) {
+        None => Ok(None),
+        Some(value) if value.is_unit() => Ok(None),
+        Some(value) => value
+            .clone()
+            .into_string()
+            .map(Some)
+            .map_err(|_| format!("Url '{name}' must be a string").into()),
+    }
+}
+
+// Rhai's #[export_module] macro generates code that uses unwrap internally
+#[allow(clippy::unwrap_used)]
+#[export_module]
+mod rhai_url_module {
+    use rhai::Dynamic;
+    use rhai::ImmutableString;
+    use rhai::Map;
+    use url::Url;
+
+    use super::string_property;
+
+    /// Split a URL into a map of its parts
+    #[rhai_fn(pure, return_raw)]
+    pub(crate) fn parse(input: &mut ImmutableString) -> Result<Map, Box<EvalAltResult>> {
+        let url = Url::parse(input).map_err(|e| format!("Invalid URL: {e}"))?;
+
+        let mut query = Map::new();
+        for (name, value) in url.query_pairs() {
+            query.insert(name.as_ref().into(), value.into_owned().into());
+        }
+
+        let mut parts = Map::new();
+        parts.insert("scheme".into(), url.scheme().to_string().into());
+        parts.insert(
+            "host".into(),
+            url.host_str()
+                .map_or(Dynamic::UNIT, |host| host.to_string().into()),
+        );
+        parts.insert(
+            "port".into(),
+            url.port()
+                .map_or(Dynamic::UNIT, |port| (port as rhai::INT).into()),
+        );
+        parts.insert("path".into(), url.path().to_string().into());
+        parts.insert("query".into(), query.into());
+        parts.insert(
+            "fragment".into(),
+            url.fragment()
+                .map_or(Dynamic::UNIT, |fragment| fragment.to_string().into()),
+        );
+        Ok(parts)
+    }
+
+    /// Build a URL from a map of its parts, in the shape returned by `Url::parse`
+    #[rhai_fn(return_raw)]
+    pub(crate) fn build(parts: Map) -> Result<String, Box<EvalAltResult>> {
+        let scheme = string_property(&parts, "scheme")?.unwrap_or_else(|| "https".to_string());
+        let host = string_property(&parts, "host")?.ok_or("Url 'host' is required")?;
+        let mut url =
+            Url::parse(&format!("{scheme}://{host}")).map_err(|e| format!("Invalid URL: {e}"))?;
+
+        if let Some(port) = parts.get("port").filter(|port| !port.is_unit()) {
+            let port = port
+                .as_int()
+                .ok()
+                .and_then(|port| u16::try_from(port).ok())
+                .ok_or("Url 'port' must be a number between 0 and 65535")?;
+            url.set_port(Some(port))
+                .map_err(|_| format!("Url with scheme '{scheme}' can't have a port"))?;
+        }
+        if let Some(path) = string_property(&parts, "path")? {
+            url.set_path(&path);
+        }
+        if let Some(query) = parts.get("query").filter(|query| !query.is_unit()) {
+            let query = query
+                .read_lock::<Map>()
+                .ok_or("Url 'query' must be a map")?;
+            if !query.is_empty() {
+                let mut pairs = url.query_pairs_mut();
+                for (name, value) in query.iter() {
+                    pairs.append_pair(name, &value.to_string());
+                }
+            }
+        }
+        url.set_fragment(string_property(&parts, "fragment")?.as_deref());
+
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::{Engine, EvalAltResult, FuncArgs, Scope};
+
+    use crate::functions::RhaiUrl;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        script: &str,
+        args: impl FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+
+        RhaiUrl::register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .expect("Script should be able to run with AST");
+
+        engine.call_fn::<T>(&mut scope, &ast, "test", args)
+    }
+
+    #[test]
+    fn should_parse_url_into_parts() {
+        let result = run_rhai_script::<rhai::Map>(
+            r#"fn test() {
+                return Url::parse("https://example.com:8443/api/items?id=1&name=a%20b#top");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result["scheme"].to_string(), "https");
+        assert_eq!(result["host"].to_string(), "example.com");
+        assert_eq!(result["port"].as_int(), Ok(8443));
+        assert_eq!(result["path"].to_string(), "/api/items");
+        assert_eq!(result["fragment"].to_string(), "top");
+        let query = result["query"].clone().cast::<rhai::Map>();
+        assert_eq!(query["id"].to_string(), "1");
+        assert_eq!(query["name"].to_string(), "a b");
+    }
+
+    #[test]
+    fn should_return_unit_for_missing_parts() {
+        let result = run_rhai_script::<rhai::Map>(
+            r#"fn test() {
+                return Url::parse("https://example.com");
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert!(result["port"].is_unit());
+        assert!(result["fragment"].is_unit());
+        assert_eq!(result["path"].to_string(), "/");
+    }
+
+    #[test]
+    fn should_build_url_from_parts() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Url::build(#{
+                    host: "example.com",
+                    port: 8443,
+                    path: "/api/items",
+                    query: #{ name: "a b", limit: 10 }
+                });
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert!(result.starts_with("https://example.com:8443/api/items?"));
+        assert!(result.contains("name=a+b"));
+        assert!(result.contains("limit=10"));
+    }
+
+    #[test]
+    fn should_build_url_from_modified_parsed_url() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                let parts = Url::parse("http://example.com/old?keep=1");
+                parts.path = "/new";
+                parts.query.added = "yes";
+                return Url::build(parts);
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert_eq!(result, "http://example.com/new?added=yes&keep=1");
+    }
+
+    #[test]
+    fn should_error_for_invalid_url() {
+        let err = run_rhai_script::<rhai::Map>(
+            r#"fn test() {
+                return Url::parse("not a url");
+            }"#,
+            (),
+        )
+        .expect_err("Should error");
+
+        assert!(err.to_string().contains("Invalid URL"));
+    }
+
+    #[test]
+    fn should_error_when_building_without_host() {
+        let err = run_rhai_script::<String>(
+            r#"fn test() {
+                return Url::build(#{ path: "/" });
+            }"#,
+            (),
+        )
+        .expect_err("Should error");
+
+        assert!(err.to_string().contains("Url 'host' is required"));
+    }
+}
@@ -0,0 +1,76 @@
+use rhai::plugin::*;
+use rhai::{Engine, Module};
+use rhai::{export_module, exported_module};
+
+pub struct RhaiUuid {}
+
+impl RhaiUuid {
+    pub fn register(engine: &mut Engine) {
+        engine.register_static_module("Uuid", exported_module!(rhai_uuid_module).into());
+    }
+}
+
+// Rhai's #[export_module] macro generates code that uses unwrap internally
+#[allow(clippy::unwrap_used)]
+#[export_module]
+mod rhai_uuid_module {
+    use rhai::Dynamic;
+    use rhai::plugin::TypeId;
+
+    /// Generate a random (version 4) UUID
+    #[rhai_fn(volatile)]
+    pub(crate) fn v4() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhai::{Engine, EvalAltResult, FuncArgs, Scope};
+
+    use crate::functions::RhaiUuid;
+
+    fn run_rhai_script<T: Clone + Send + Sync + 'static>(
+        script: &str,
+        args: impl FuncArgs,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut engine = Engine::new();
+        let mut scope = Scope::new();
+
+        RhaiUuid::register(&mut engine);
+
+        let ast = engine.compile(script).expect("Script should have compiled");
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .expect("Script should be able to run with AST");
+
+        engine.call_fn::<T>(&mut scope, &ast, "test", args)
+    }
+
+    #[test]
+    fn should_return_v4_uuid() {
+        let result = run_rhai_script::<String>(
+            r#"fn test() {
+                return Uuid::v4();
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        let uuid = uuid::Uuid::parse_str(&result).expect("Should be a valid UUID");
+        assert_eq!(uuid.get_version_num(), 4);
+    }
+
+    #[test]
+    fn should_return_different_uuids() {
+        let result = run_rhai_script::<bool>(
+            r#"fn test() {
+                return Uuid::v4() != Uuid::v4();
+            }"#,
+            (),
+        )
+        .expect("Should not error");
+
+        assert!(result);
+    }
+}
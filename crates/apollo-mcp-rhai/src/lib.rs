@@ -1,7 +1,11 @@
 pub mod checkpoints;
 mod engine;
 mod functions;
+mod limits;
 mod shared_mut;
+pub mod tools;
 mod types;
 
-pub use engine::RhaiEngine;
+pub use engine::{DEFAULT_POOL_SIZE, RhaiEngine};
+pub use functions::{DEFAULT_MAX_STORE_SIZE, METRIC_PREFIX, ScriptTelemetry, TelemetryValue};
+pub use limits::Limits;
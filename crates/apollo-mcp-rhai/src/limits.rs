@@ -0,0 +1,261 @@
+//! Limits on the resources that Rhai scripts can use
+//!
+//! The timeout of a script is tracked per thread, because hooks run on the thread that calls
+//! them. Rhai checks it while the script runs, and promises check it while they wait.
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+use rhai::{Dynamic, Engine, EvalAltResult, Position};
+
+/// The value that scripts stopped by their timeout are terminated with
+const TIMEOUT_TOKEN: &str = "timeout";
+
+/// How many operations run between checks of the timeout
+const TIMEOUT_CHECK_INTERVAL: u64 = 256;
+
+thread_local! {
+    /// When the script running on this thread must stop
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Limits applied to scripts. Limits that aren't set keep the Rhai defaults.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    /// Maximum number of operations a script can run in one call
+    pub max_operations: Option<u64>,
+
+    /// Maximum depth of nested function calls
+    pub max_call_levels: Option<usize>,
+
+    /// Maximum length of strings, in bytes
+    pub max_string_size: Option<usize>,
+
+    /// Maximum number of items in arrays
+    pub max_array_size: Option<usize>,
+
+    /// Maximum number of properties in object maps
+    pub max_map_size: Option<usize>,
+
+    /// How long a call into the scripts can run, including time spent waiting for a free copy of
+    /// the global variables and for promises
+    pub timeout: Option<Duration>,
+}
+
+impl Limits {
+    pub(crate) fn apply(&self, engine: &mut Engine) {
+        if let Some(max_operations) = self.max_operations {
+            engine.set_max_operations(max_operations);
+        }
+        if let Some(max_call_levels) = self.max_call_levels {
+            engine.set_max_call_levels(max_call_levels);
+        }
+        if let Some(max_string_size) = self.max_string_size {
+            engine.set_max_string_size(max_string_size);
+        }
+        if let Some(max_array_size) = self.max_array_size {
+            engine.set_max_array_size(max_array_size);
+        }
+        if let Some(max_map_size) = self.max_map_size {
+            engine.set_max_map_size(max_map_size);
+        }
+
+        engine.on_progress(|operations| {
+            (operations % TIMEOUT_CHECK_INTERVAL == 0 && remaining_time() == Some(Duration::ZERO))
+                .then(|| Dynamic::from(TIMEOUT_TOKEN))
+        });
+    }
+
+    /// Run `f` with the timeout applied to the scripts it calls
+    pub(crate) fn with_timeout<T>(&self, f: impl FnOnce() -> T) -> T {
+        let Some(timeout) = self.timeout else {
+            return f();
+        };
+
+        let deadline = Instant::now() + timeout;
+        let previous = DEADLINE.get();
+        // A call nested in another keeps the earlier deadline
+        DEADLINE.set(Some(
+            previous.map_or(deadline, |previous| previous.min(deadline)),
+        ));
+        let _restore = RestoreDeadline(previous);
+        f()
+    }
+}
+
+/// Restores the deadline of the enclosing call when dropped
+struct RestoreDeadline(Option<Instant>);
+
+impl Drop for RestoreDeadline {
+    fn drop(&mut self) {
+        DEADLINE.set(self.0);
+    }
+}
+
+/// When the script running on this thread must stop
+pub(crate) fn deadline() -> Option<Instant> {
+    DEADLINE.get()
+}
+
+/// The time left before the script running on this thread must stop
+pub(crate) fn remaining_time() -> Option<Duration> {
+    DEADLINE
+        .get()
+        .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// The error that stops a script that ran past its timeout at `position`
+pub(crate) fn timed_out(position: Position) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorTerminated(Dynamic::from(TIMEOUT_TOKEN), position).into()
+}
+
+/// Describe an error raised because a script hit one of its limits, with the script position
+/// where it happened
+pub(crate) fn limit_exceeded(err: &EvalAltResult) -> Option<(String, Position)> {
+    let reason = match err.unwrap_inner() {
+        EvalAltResult::ErrorTerminated(..) => "ran longer than its timeout".to_string(),
+        EvalAltResult::ErrorTooManyOperations(..) => {
+            "ran more than the maximum number of operations".to_string()
+        }
+        EvalAltResult::ErrorStackOverflow(..) => {
+            "exceeded the maximum depth of function calls".to_string()
+        }
+        EvalAltResult::ErrorDataTooLarge(data, ..) => {
+            format!(
+                "exceeded the maximum size of data ({})",
+                data.to_lowercase()
+            )
+        }
+        _ => return None,
+    };
+
+    // Rhai moves these errors to the position of the call when they leave a function, so prefer
+    // the most specific position that is still known
+    let position = match err.unwrap_inner().position() {
+        Position::NONE => err.position(),
+        position => position,
+    };
+    Some((reason, position))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use rhai::EvalAltResult;
+
+    use super::{Limits, limit_exceeded};
+    use crate::engine::RhaiEngine;
+
+    fn run(limits: Limits, script: &str) -> Box<EvalAltResult> {
+        let engine = RhaiEngine::new("rhai").with_limits(limits);
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        engine
+            .execute_hook("my_hook", ())
+            .expect_err("Should hit a limit")
+    }
+
+    fn reason(err: &EvalAltResult) -> String {
+        limit_exceeded(err).expect("Should be a limit error").0
+    }
+
+    #[test]
+    fn should_stop_scripts_that_run_past_their_timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        let err = run(
+            limits,
+            "fn spin() {\n    let count = 0;\n    loop { count += 1; }\n}\nfn my_hook() {\n    spin();\n}",
+        );
+
+        assert_eq!(reason(&err), "ran longer than its timeout");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn should_stop_promises_that_wait_past_the_timeout() {
+        // Accepts connections but never responds
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let limits = Limits {
+            timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+
+        let err = run(
+            limits,
+            &format!(r#"fn my_hook() {{ Http::get("{url}").wait() }}"#),
+        );
+
+        assert_eq!(reason(&err), "ran longer than its timeout");
+    }
+
+    #[test]
+    fn should_limit_operations() {
+        let limits = Limits {
+            max_operations: Some(1000),
+            ..Default::default()
+        };
+
+        let err = run(
+            limits,
+            "fn my_hook() { let sum = 0; for i in 0..10000 { sum += i; } sum }",
+        );
+
+        assert_eq!(
+            reason(&err),
+            "ran more than the maximum number of operations"
+        );
+    }
+
+    #[test]
+    fn should_limit_call_levels() {
+        let limits = Limits {
+            max_call_levels: Some(4),
+            ..Default::default()
+        };
+
+        let err = run(
+            limits,
+            "fn recurse(n) { recurse(n + 1) }\nfn my_hook() { recurse(0) }",
+        );
+
+        assert_eq!(reason(&err), "exceeded the maximum depth of function calls");
+    }
+
+    #[test]
+    fn should_limit_data_sizes() {
+        let limits = Limits {
+            max_string_size: Some(10),
+            max_array_size: Some(5),
+            ..Default::default()
+        };
+
+        let string_err = run(limits.clone(), r#"fn my_hook() { "abcdef" + "ghijkl" }"#);
+        let array_err = run(
+            limits,
+            "fn my_hook() { let a = []; for i in 0..10 { a.push(i); } a }",
+        );
+
+        assert_eq!(
+            reason(&string_err),
+            "exceeded the maximum size of data (length of string)"
+        );
+        assert_eq!(
+            reason(&array_err),
+            "exceeded the maximum size of data (size of array/blob)"
+        );
+    }
+
+    #[test]
+    fn should_ignore_other_errors() {
+        let err = run(Limits::default(), r#"fn my_hook() { throw "oops"; }"#);
+
+        assert!(limit_exceeded(&err).is_none());
+    }
+}
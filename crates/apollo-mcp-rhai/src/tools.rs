@@ -0,0 +1,323 @@
+//! MCP tools defined by Rhai scripts
+//!
+//! A script defines tools by returning them from a `define_tools` function. Each tool has a
+//! handler function that is called with the tool arguments and a [`ToolApi`] for calling the
+//! server's GraphQL operations.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, ImmutableString, Map};
+use rmcp::model::{CallToolResult, Content, JsonObject, Tool, ToolAnnotations};
+use serde_json::Value;
+use tracing::error;
+
+use crate::checkpoints::{McpError, script_error};
+use crate::engine::RhaiEngine;
+use crate::types::Promise;
+
+/// The name of the function that scripts define tools with
+pub(crate) const DEFINE_TOOLS: &str = "define_tools";
+
+/// The result of executing a GraphQL operation: the response body, or why it couldn't be executed
+pub type OperationFuture = Pin<Box<dyn Future<Output = Result<Value, String>> + Send>>;
+
+/// Executes the server's GraphQL operations for the handlers of script tools
+pub trait OperationExecutor: Send + Sync {
+    /// Execute the operation of the tool called `operation` with the tool input `variables`
+    fn execute(&self, operation: String, variables: Value) -> OperationFuture;
+}
+
+/// A tool defined by the `define_tools` function of a script
+#[derive(Clone, Debug)]
+pub struct ScriptTool {
+    tool: Tool,
+    handler: FnPtr,
+}
+
+impl ScriptTool {
+    pub fn tool(&self) -> &Tool {
+        &self.tool
+    }
+
+    pub(crate) fn handler(&self) -> &FnPtr {
+        &self.handler
+    }
+
+    /// Read a tool definition returned by `define_tools`
+    pub(crate) fn from_definition(definition: Dynamic) -> Result<Self, Box<EvalAltResult>> {
+        let mut definition = definition
+            .try_cast::<Map>()
+            .ok_or("define_tools must return an array of maps")?;
+
+        let name = definition
+            .remove("name")
+            .and_then(|name| name.into_immutable_string().ok())
+            .filter(|name| !name.is_empty())
+            .ok_or("each tool needs a `name` string")?;
+        let handler = definition
+            .remove("handler")
+            .and_then(|handler| handler.try_cast::<FnPtr>())
+            .ok_or_else(|| format!("tool {name} needs a `handler` function pointer"))?;
+        let description = definition
+            .remove("description")
+            .map(|description| description.to_string())
+            .unwrap_or_default();
+        let input_schema = match definition.remove("input_schema") {
+            Some(schema) => rhai::serde::from_dynamic::<JsonObject>(&schema)
+                .map_err(|err| format!("the input schema of tool {name} is invalid: {err}"))?,
+            None => JsonObject::from_iter([("type".to_string(), Value::from("object"))]),
+        };
+        let annotations = definition
+            .remove("annotations")
+            .map(|annotations| rhai::serde::from_dynamic::<ToolAnnotations>(&annotations))
+            .transpose()
+            .map_err(|err| format!("the annotations of tool {name} are invalid: {err}"))?;
+        if let Some(field) = definition.keys().next() {
+            return Err(format!("tool {name} has an unknown field `{field}`").into());
+        }
+
+        let mut tool = Tool::new(name.to_string(), description, input_schema);
+        tool.annotations = annotations;
+        Ok(Self { tool, handler })
+    }
+}
+
+/// The API passed to the handler of a script tool
+#[derive(Clone)]
+pub struct ToolApi {
+    executor: Arc<dyn OperationExecutor>,
+}
+
+impl ToolApi {
+    pub(crate) fn register(engine: &mut Engine) {
+        engine
+            .register_type_with_name::<ToolApi>("ToolApi")
+            .register_fn("execute", ToolApi::execute_without_variables)
+            .register_fn("execute", ToolApi::execute);
+    }
+
+    fn execute_without_variables(api: &mut Self, operation: ImmutableString) -> Promise {
+        ToolApi::execute(api, operation, Map::new())
+    }
+
+    fn execute(api: &mut Self, operation: ImmutableString, variables: Map) -> Promise {
+        let variables = rhai::serde::from_dynamic::<Value>(&Dynamic::from_map(variables));
+        let execution =
+            variables.map(|variables| api.executor.execute(operation.to_string(), variables));
+
+        Promise::spawn(async move {
+            let body = execution.map_err(|err| err.to_string())?.await?;
+            rhai::serde::to_dynamic(body).map_err(|err| err.to_string())
+        })
+    }
+}
+
+/// Call the script tool named `name`, or return `None` when no script defines it.
+///
+/// Handlers can return a string, which becomes the text content of the result, or any other
+/// value, which becomes its structured content.
+pub fn call_script_tool(
+    engine: &Arc<RhaiEngine>,
+    name: &str,
+    arguments: Option<&JsonObject>,
+    executor: Arc<dyn OperationExecutor>,
+) -> Option<Result<CallToolResult, McpError>> {
+    let handler = engine
+        .tools()
+        .iter()
+        .find(|tool| tool.tool().name == name)?
+        .handler()
+        .clone();
+
+    let arguments = match rhai::serde::to_dynamic(arguments.cloned().unwrap_or_default()) {
+        Ok(arguments) => arguments,
+        Err(err) => {
+            return Some(Ok(CallToolResult::error(vec![Content::text(format!(
+                "Invalid input: {err}"
+            ))])));
+        }
+    };
+
+    let result = engine
+        .call_fn_ptr(&handler, (arguments, ToolApi { executor }))
+        .map_err(|err| script_error(&format!("{name} tool"), *err))
+        .and_then(|output| {
+            if output.is_unit() {
+                Ok(CallToolResult::success(vec![]))
+            } else if output.is_string() {
+                Ok(CallToolResult::success(vec![Content::text(
+                    output.to_string(),
+                )]))
+            } else {
+                rhai::serde::from_dynamic::<Value>(&output)
+                    .map(CallToolResult::structured)
+                    .map_err(|err| {
+                        error!(
+                            "Error when executing {name} tool: Error converting its result: {err}"
+                        );
+                        McpError::new(
+                            rmcp::model::ErrorCode::INTERNAL_ERROR,
+                            "Internal error",
+                            None,
+                        )
+                    })
+            }
+        });
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rmcp::model::ErrorCode;
+    use serde_json::{Value, json};
+
+    use super::{OperationExecutor, OperationFuture, call_script_tool};
+    use crate::engine::RhaiEngine;
+
+    struct EchoExecutor;
+
+    impl OperationExecutor for EchoExecutor {
+        fn execute(&self, operation: String, variables: Value) -> OperationFuture {
+            Box::pin(async move {
+                if operation == "Missing" {
+                    Err("Operation Missing was not found".to_string())
+                } else {
+                    Ok(json!({"data": {"operation": operation, "variables": variables}}))
+                }
+            })
+        }
+    }
+
+    fn create_engine(script: &str) -> Arc<RhaiEngine> {
+        let engine = RhaiEngine::new("rhai");
+        engine
+            .load_from_string(script)
+            .expect("Script should compile");
+        Arc::new(engine)
+    }
+
+    const TOOLS: &str = r#"
+        fn define_tools() {
+            let properties = #{ id: #{ type: "string" } };
+            let summary = #{
+                name: "GetUserSummary",
+                description: "Summarize a user",
+                input_schema: #{ type: "object", properties: properties, required: ["id"] },
+                annotations: #{ readOnlyHint: true },
+                handler: Fn("get_user_summary"),
+            };
+            [summary, #{ name: "Greet", handler: Fn("greet") }, #{ name: "Fail", handler: Fn("fail") }]
+        }
+
+        fn get_user_summary(args, api) {
+            let response = api.execute("GetUser", #{ id: args.id }).wait();
+            #{ summary: response.data.operation + " " + response.data.variables.id }
+        }
+
+        fn greet(args, api) {
+            "Hello"
+        }
+
+        fn fail(args, api) {
+            api.execute("Missing").wait();
+        }
+    "#;
+
+    #[test]
+    fn should_read_tool_definitions() {
+        let tools = create_engine(TOOLS).tools();
+
+        assert_eq!(tools.len(), 3);
+        let tool = tools[0].tool();
+        assert_eq!(tool.name, "GetUserSummary");
+        assert_eq!(tool.description.as_deref(), Some("Summarize a user"));
+        assert_eq!(tool.input_schema.get("required"), Some(&json!(["id"])));
+        assert_eq!(
+            tool.annotations.as_ref().and_then(|a| a.read_only_hint),
+            Some(true)
+        );
+        assert_eq!(
+            tools[1].tool().input_schema.get("type"),
+            Some(&json!("object"))
+        );
+    }
+
+    #[test]
+    fn should_reject_invalid_definitions() {
+        for (script, message) in [
+            (
+                r#"fn define_tools() { [#{ name: "A" }] }"#,
+                "tool A needs a `handler` function pointer",
+            ),
+            (
+                r#"fn define_tools() { [#{ name: "A", handler: Fn("a"), schema: #{} }] }"#,
+                "tool A has an unknown field `schema`",
+            ),
+            (
+                r#"fn define_tools() { [#{ name: "A", handler: Fn("a") }, #{ name: "A", handler: Fn("a") }] }"#,
+                "more than one tool is named A",
+            ),
+        ] {
+            let engine = RhaiEngine::new("rhai");
+            let err = engine.load_from_string(script).expect_err("Should error");
+
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn should_return_none_for_unknown_tools() {
+        let engine = create_engine(TOOLS);
+
+        assert!(call_script_tool(&engine, "Unknown", None, Arc::new(EchoExecutor)).is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn should_call_handler_with_operations() {
+        let engine = create_engine(TOOLS);
+        let arguments = json!({"id": "1"}).as_object().cloned();
+
+        let result = call_script_tool(
+            &engine,
+            "GetUserSummary",
+            arguments.as_ref(),
+            Arc::new(EchoExecutor),
+        )
+        .expect("Tool should exist")
+        .expect("Should not error");
+
+        assert_eq!(
+            result.structured_content,
+            Some(json!({"summary": "GetUser 1"}))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn should_return_text_results() {
+        let engine = create_engine(TOOLS);
+
+        let result = call_script_tool(&engine, "Greet", None, Arc::new(EchoExecutor))
+            .expect("Tool should exist")
+            .expect("Should not error");
+
+        assert_eq!(
+            result.content[0].as_text().map(|text| text.text.as_str()),
+            Some("Hello")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn should_return_internal_error_when_operation_fails() {
+        let engine = create_engine(TOOLS);
+
+        let err = call_script_tool(&engine, "Fail", None, Arc::new(EchoExecutor))
+            .expect("Tool should exist")
+            .expect_err("Should error");
+
+        assert_eq!(err.code, ErrorCode::INTERNAL_ERROR);
+    }
+}
@@ -3,9 +3,11 @@ mod header_map;
 mod http_parts;
 mod promise;
 mod response;
+mod tool;
 
 pub use error_code::*;
 pub use header_map::*;
 pub use http_parts::*;
 pub use promise::*;
 pub use response::*;
+pub use tool::*;
@@ -1,9 +1,11 @@
 use std::sync::Arc;
 
 use parking_lot::Mutex;
-use rhai::{CustomType, Dynamic, Engine, EvalAltResult, Position, TypeBuilder};
+use rhai::{CustomType, Dynamic, Engine, EvalAltResult, NativeCallContext, Position, TypeBuilder};
 use tokio::sync::oneshot;
 
+use crate::limits;
+
 #[derive(Clone, Debug)]
 pub enum PromiseState {
     Pending,
@@ -21,6 +23,23 @@ pub struct Promise {
 }
 
 impl Promise {
+    /// Run `future` in the background, resolving the promise with its output
+    pub(crate) fn spawn(
+        future: impl Future<Output = Result<Dynamic, String>> + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let _ = tx.send(future.await);
+        });
+
+        Self {
+            state: PromiseState::Pending,
+            resolved_value: None,
+            receiver: Arc::new(Mutex::new(Some(rx))),
+        }
+    }
+
     pub fn register(engine: &mut Engine) {
         engine
             .register_type::<Promise>()
@@ -29,7 +48,10 @@ impl Promise {
             .register_fn("wait", Promise::resolve);
     }
 
-    pub fn resolve(promise: &mut Self) -> Result<Dynamic, Box<EvalAltResult>> {
+    pub fn resolve(
+        context: NativeCallContext,
+        promise: &mut Self,
+    ) -> Result<Dynamic, Box<EvalAltResult>> {
         match (&promise.state, &promise.resolved_value) {
             (PromiseState::Resolved, Some(value)) => return Ok(value.clone()),
             (PromiseState::Rejected, Some(value)) => {
@@ -51,8 +73,16 @@ impl Promise {
                 "Unexpected state: Promise was pending but no async task was found".into()
             })?;
 
-        let result =
-            tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(receiver));
+        // Stop waiting when the script runs out of time
+        let result = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                match limits::remaining_time() {
+                    Some(remaining) => tokio::time::timeout(remaining, receiver).await.ok(),
+                    None => Some(receiver.await),
+                }
+            })
+        })
+        .ok_or_else(|| limits::timed_out(context.call_position()))?;
 
         match result {
             Ok(Ok(value)) => {
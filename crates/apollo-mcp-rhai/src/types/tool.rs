@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use rhai::{Dynamic, Engine, EvalAltResult, Map};
+use rmcp::model::{JsonObject, Tool, ToolAnnotations};
+
+/// An MCP tool as seen by the `on_list_tools` hook
+#[derive(Clone, Debug)]
+pub struct RhaiTool {
+    original_name: String,
+    tool: Tool,
+}
+
+impl From<Tool> for RhaiTool {
+    fn from(tool: Tool) -> Self {
+        Self {
+            original_name: tool.name.to_string(),
+            tool,
+        }
+    }
+}
+
+impl RhaiTool {
+    pub fn register(engine: &mut Engine) {
+        engine
+            .register_type_with_name::<RhaiTool>("Tool")
+            .register_get("original_name", RhaiTool::get_original_name)
+            .register_get_set("name", RhaiTool::get_name, RhaiTool::set_name)
+            .register_get_set("title", RhaiTool::get_title, RhaiTool::set_title)
+            .register_get_set(
+                "description",
+                RhaiTool::get_description,
+                RhaiTool::set_description,
+            )
+            .register_get_set(
+                "annotations",
+                RhaiTool::get_annotations,
+                RhaiTool::set_annotations,
+            )
+            .register_get_set(
+                "input_schema",
+                RhaiTool::get_input_schema,
+                RhaiTool::set_input_schema,
+            );
+    }
+
+    /// The name the tool had before any script renamed it
+    pub fn original_name(&self) -> &str {
+        &self.original_name
+    }
+
+    pub fn into_tool(self) -> Tool {
+        self.tool
+    }
+
+    fn get_original_name(&mut self) -> String {
+        self.original_name.clone()
+    }
+
+    fn get_name(&mut self) -> String {
+        self.tool.name.to_string()
+    }
+
+    fn set_name(&mut self, name: String) {
+        self.tool.name = name.into();
+    }
+
+    fn get_title(&mut self) -> String {
+        self.tool.title.clone().unwrap_or_default()
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.tool.title = Some(title).filter(|title| !title.is_empty());
+    }
+
+    fn get_description(&mut self) -> String {
+        self.tool
+            .description
+            .as_deref()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn set_description(&mut self, description: String) {
+        self.tool.description = Some(description)
+            .filter(|description| !description.is_empty())
+            .map(Into::into);
+    }
+
+    fn get_annotations(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        match &self.tool.annotations {
+            Some(annotations) => rhai::serde::to_dynamic(annotations),
+            None => Ok(Dynamic::from_map(Map::new())),
+        }
+    }
+
+    fn set_annotations(&mut self, annotations: Dynamic) -> Result<(), Box<EvalAltResult>> {
+        let annotations = rhai::serde::from_dynamic::<ToolAnnotations>(&annotations)?;
+        self.tool.annotations = Some(annotations).filter(|a| *a != ToolAnnotations::default());
+        Ok(())
+    }
+
+    fn get_input_schema(&mut self) -> Result<Dynamic, Box<EvalAltResult>> {
+        rhai::serde::to_dynamic(self.tool.input_schema.as_ref())
+    }
+
+    fn set_input_schema(&mut self, input_schema: Dynamic) -> Result<(), Box<EvalAltResult>> {
+        let input_schema = rhai::serde::from_dynamic::<JsonObject>(&input_schema)?;
+        self.tool.input_schema = Arc::new(input_schema);
+        Ok(())
+    }
+}
@@ -117,6 +117,10 @@ fn main() {
     let attribute_enum_as_str_matches =
         generate_enum_as_str_matches(&telemetry_attribute_data, attribute_enum_name.clone());
 
+    let metric_keys_len = telemetry_metrics_data.len();
+    let all_metric_enum_values = telemetry_metrics_data
+        .iter()
+        .map(|t| quote::format_ident!("{}", t.name));
     let metric_enum_name = quote::format_ident!("{}", "TelemetryMetric");
     let metric_enum_keys = generate_enum(&telemetry_metrics_data);
     let metric_enum_as_str_matches =
@@ -141,6 +145,9 @@ fn main() {
             }
         }
 
+        /// All TelemetryMetric values
+        pub const ALL_METRICS: &[TelemetryMetric; #metric_keys_len] = &[#(TelemetryMetric::#all_metric_enum_values),*];
+
         /// Supported telemetry metrics
         #[derive(Debug, ::serde::Deserialize, ::schemars::JsonSchema, Clone, Eq, PartialEq, Hash, Copy)]
         pub enum #metric_enum_name {
@@ -6,17 +6,18 @@ use http::HeaderMap;
 use http::request::Parts;
 use opentelemetry::Context;
 use opentelemetry::trace::FutureExt;
-use parking_lot::Mutex;
 use rmcp::model::{CallToolResult, Content, JsonObject, Meta, Tool};
 use serde_json::{Map, Value, json};
 use url::Url;
 
+use apollo_compiler::Schema;
+use apollo_compiler::validation::Valid;
+
 use crate::apps::app::{AppTarget, AppTool};
 use crate::errors::McpError;
-use crate::graphql::{self, Executable};
-use crate::operations::Operation;
-use crate::server::states::telemetry::current_trace_id;
-use apollo_mcp_rhai::{RhaiEngine, checkpoints};
+use crate::graphql::{self, Executable, Upstream};
+use crate::operations::{Operation, prepare_operation};
+use apollo_mcp_rhai::RhaiEngine;
 
 use super::App;
 
@@ -28,7 +29,9 @@ pub(crate) async fn find_and_execute_app_tool(
     headers: &HeaderMap,
     arguments: Option<&JsonObject>,
     endpoint: &Url,
-    rhai_engine: &Arc<Mutex<RhaiEngine>>,
+    upstream: &Upstream,
+    schema: &Valid<Schema>,
+    rhai_engine: &Arc<RhaiEngine>,
     axum_parts: Option<&Parts>,
 ) -> Option<Result<CallToolResult, McpError>> {
     let app = apps.iter().find(|app| app.name == app_name)?;
@@ -42,6 +45,8 @@ pub(crate) async fn find_and_execute_app_tool(
                     headers,
                     arguments,
                     endpoint,
+                    upstream,
+                    schema,
                     rhai_engine,
                     axum_parts,
                 )
@@ -52,32 +57,43 @@ pub(crate) async fn find_and_execute_app_tool(
     None
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn execute_app_tool(
     app: &App,
     tool: &AppTool,
     headers: &HeaderMap,
     arguments: Option<&JsonObject>,
     endpoint: &Url,
-    rhai_engine: &Arc<Mutex<RhaiEngine>>,
+    upstream: &Upstream,
+    schema: &Valid<Schema>,
+    rhai_engine: &Arc<RhaiEngine>,
     axum_parts: Option<&Parts>,
 ) -> Result<CallToolResult, McpError> {
-    let (endpoint, headers) = checkpoints::on_execute_graphql_operation(
-        rhai_engine,
+    let (endpoint, headers, operation) = prepare_operation(
+        tool.operation.as_ref(),
+        Value::from(filter_inputs_for_operation(arguments, &tool.operation)),
         endpoint,
         headers,
+        schema,
+        rhai_engine,
         axum_parts,
         &tool.tool.name,
-        current_trace_id,
     )?;
+    let response_hook = graphql::ResponseHook {
+        rhai_engine,
+        tool_name: &tool.tool.name,
+    };
 
+    // The prepared operation has already resolved the input
     let graphql_request = graphql::Request {
-        input: Value::from(filter_inputs_for_operation(arguments, &tool.operation)),
+        input: Value::Null,
         endpoint: &endpoint,
         headers: &headers,
+        upstream,
+        response_hook: Some(response_hook),
     };
 
-    let result = tool
-        .operation
+    let result = operation
         .execute(graphql_request)
         .with_context(Context::current())
         .await?;
@@ -93,6 +109,8 @@ async fn execute_app_tool(
             input: Value::from(filter_inputs_for_operation(arguments, &prefetch.operation)),
             endpoint: &endpoint,
             headers: &headers,
+            upstream,
+            response_hook: Some(response_hook),
         };
         prefetch_calls.push(
             prefetch
@@ -432,7 +450,9 @@ mod tests {
             &HeaderMap::new(),
             Some(&object!({"apples": 1, "oranges": 2, "bananas": 3})),
             &server.url().parse().unwrap(),
-            &Arc::new(Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await
@@ -538,7 +558,9 @@ mod tests {
             &HeaderMap::new(),
             None,
             &server.url().parse().unwrap(),
-            &Arc::new(Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await;
@@ -593,7 +615,9 @@ mod tests {
             &HeaderMap::new(),
             None,
             &server.url().parse().unwrap(),
-            &Arc::new(Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await;
@@ -647,7 +671,9 @@ mod tests {
             &HeaderMap::new(),
             None,
             &server.url().parse().unwrap(),
-            &Arc::new(Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await;
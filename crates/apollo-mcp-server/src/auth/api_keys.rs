@@ -0,0 +1,295 @@
+//! Static API-key authentication
+//!
+//! For deployments without an OAuth server, clients can authenticate with an API key sent as a
+//! bearer token. Keys are listed by their SHA-256 hash in a file that is reloaded when it changes,
+//! so keys can be rotated without restarting the server.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::SystemTime,
+};
+
+use apollo_mcp_registry::files;
+use futures::StreamExt as _;
+use headers::{Authorization, authorization::Bearer};
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tracing::{error, info, warn};
+
+use super::ValidToken;
+
+/// API-key authentication configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeysConfig {
+    /// Path to the file listing the accepted API keys. The file is reloaded when it changes.
+    pub path: PathBuf,
+}
+
+/// Errors from loading the API key file
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeysError {
+    #[error("Failed to read API key file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse API key file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    #[error("API key {name:?} in {path} has an invalid hash: expected `sha256:` and 64 hex digits")]
+    InvalidHash { path: PathBuf, name: String },
+}
+
+/// The contents of the API key file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyFile {
+    keys: Vec<ApiKeyEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyEntry {
+    /// Name identifying the key, used as the subject of requests made with it
+    name: String,
+    /// The SHA-256 hash of the key, as `sha256:<hex>`
+    hash: String,
+    /// Scopes granted to requests made with the key
+    #[serde(default)]
+    scopes: Vec<String>,
+    /// When the key stops being accepted (RFC 3339)
+    #[serde(default, with = "humantime_serde")]
+    expires_at: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+struct ApiKey {
+    name: String,
+    scopes: Vec<String>,
+    expires_at: Option<SystemTime>,
+}
+
+/// The accepted API keys, keyed by the SHA-256 hash of the key
+#[derive(Debug, Clone)]
+pub(super) struct ApiKeys {
+    keys: Arc<RwLock<HashMap<[u8; 32], ApiKey>>>,
+}
+
+impl ApiKeys {
+    /// Load the keys from the configured file
+    pub(super) fn load(config: &ApiKeysConfig) -> Result<Self, ApiKeysError> {
+        Ok(Self {
+            keys: Arc::new(RwLock::new(read_keys(&config.path)?)),
+        })
+    }
+
+    /// Reload the keys whenever the file changes.
+    ///
+    /// A file that fails to load leaves the previous keys in place. Watching stops once every
+    /// copy of the keys has been dropped.
+    pub(super) fn watch(&self, config: &ApiKeysConfig) {
+        let path = config.path.clone();
+        let keys: Weak<RwLock<_>> = Arc::downgrade(&self.keys);
+        tokio::spawn(async move {
+            // Skip the initial event that files::watch always emits on startup
+            let mut changes = std::pin::pin!(files::watch(&path).skip(1));
+            while changes.next().await.is_some() {
+                let Some(keys) = keys.upgrade() else {
+                    break;
+                };
+                match read_keys(&path) {
+                    Ok(reloaded) => {
+                        info!(path = %path.display(), count = reloaded.len(), "Reloaded API keys");
+                        *keys.write() = reloaded;
+                    }
+                    Err(e) => error!("{e}, keeping the previously loaded API keys"),
+                }
+            }
+        });
+    }
+
+    /// Validate a bearer token as an API key
+    pub(super) fn validate(&self, token: &Authorization<Bearer>) -> Option<ValidToken> {
+        let hash: [u8; 32] = Sha256::digest(token.token().as_bytes()).into();
+        let keys = self.keys.read();
+        let key = keys.get(&hash)?;
+        if key
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
+        {
+            warn!(name = %key.name, "API key has expired");
+            return None;
+        }
+
+        let mut claims = Map::new();
+        claims.insert("sub".to_string(), Value::String(key.name.clone()));
+        if !key.scopes.is_empty() {
+            claims.insert("scope".to_string(), Value::String(key.scopes.join(" ")));
+        }
+        Some(ValidToken {
+            token: token.clone(),
+            subject: key.name.clone(),
+            scopes: key.scopes.clone(),
+            claims,
+            api_key: true,
+        })
+    }
+}
+
+fn read_keys(path: &Path) -> Result<HashMap<[u8; 32], ApiKey>, ApiKeysError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| ApiKeysError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let file: ApiKeyFile =
+        serde_yaml::from_str(&contents).map_err(|source| ApiKeysError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    file.keys
+        .into_iter()
+        .map(|entry| {
+            let hash = entry
+                .hash
+                .strip_prefix("sha256:")
+                .and_then(|hex| hex::decode(hex).ok())
+                .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+                .ok_or_else(|| ApiKeysError::InvalidHash {
+                    path: path.to_path_buf(),
+                    name: entry.name.clone(),
+                })?;
+            Ok((
+                hash,
+                ApiKey {
+                    name: entry.name,
+                    scopes: entry.scopes,
+                    expires_at: entry.expires_at,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    fn hash(key: &str) -> String {
+        format!("sha256:{}", hex::encode(Sha256::digest(key.as_bytes())))
+    }
+
+    fn key_file(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    fn bearer(key: &str) -> Authorization<Bearer> {
+        Authorization::bearer(key).unwrap()
+    }
+
+    #[test]
+    fn validates_known_key() {
+        let file = key_file(&format!(
+            r#"
+            keys:
+              - name: ci-bot
+                hash: {}
+                scopes: [read, write]
+                expires_at: 2999-01-01T00:00:00Z
+            "#,
+            hash("secret-key")
+        ));
+        let keys = ApiKeys::load(&ApiKeysConfig {
+            path: file.path().to_path_buf(),
+        })
+        .unwrap();
+
+        let valid = keys.validate(&bearer("secret-key")).unwrap();
+
+        assert_eq!(valid.subject, "ci-bot");
+        assert_eq!(valid.scopes, vec!["read".to_string(), "write".to_string()]);
+        assert_eq!(valid.claims.get("sub"), Some(&Value::from("ci-bot")));
+        assert!(valid.api_key);
+        assert!(keys.validate(&bearer("other-key")).is_none());
+    }
+
+    #[test]
+    fn rejects_expired_key() {
+        let file = key_file(&format!(
+            r#"
+            keys:
+              - name: old-bot
+                hash: {}
+                expires_at: 2000-01-01T00:00:00Z
+            "#,
+            hash("secret-key")
+        ));
+        let keys = ApiKeys::load(&ApiKeysConfig {
+            path: file.path().to_path_buf(),
+        })
+        .unwrap();
+
+        assert!(keys.validate(&bearer("secret-key")).is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_hash() {
+        let file = key_file(
+            r#"
+            keys:
+              - name: ci-bot
+                hash: secret-key
+            "#,
+        );
+
+        let result = ApiKeys::load(&ApiKeysConfig {
+            path: file.path().to_path_buf(),
+        });
+
+        assert!(matches!(result, Err(ApiKeysError::InvalidHash { name, .. }) if name == "ci-bot"));
+    }
+
+    #[tokio::test]
+    async fn reloads_keys_when_file_changes() {
+        let file = key_file(&format!(
+            "keys:\n  - name: first\n    hash: {}\n",
+            hash("first-key")
+        ));
+        let config = ApiKeysConfig {
+            path: file.path().to_path_buf(),
+        };
+        let keys = ApiKeys::load(&config).unwrap();
+        keys.watch(&config);
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+        std::fs::write(
+            file.path(),
+            format!(
+                "keys:\n  - name: second\n    hash: {}\n",
+                hash("second-key")
+            ),
+        )
+        .unwrap();
+
+        let reloaded = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            while keys.validate(&bearer("second-key")).is_none() {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert!(reloaded.is_ok());
+        assert!(keys.validate(&bearer("first-key")).is_none());
+    }
+}
@@ -0,0 +1,363 @@
+//! Headers derived from the claims of a validated token
+//!
+//! Upstream APIs often need the caller's identity without being handed the caller's token.
+//! Claims can be copied into plain headers, or into a short-lived JWT signed with a shared
+//! secret so that upstreams can verify the identity was asserted by the MCP server.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{EncodingKey, Header, encode};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use schemars::JsonSchema;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::warn;
+
+use super::ValidToken;
+
+/// Headers to set on upstream requests from the claims of the validated token
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimHeadersConfig {
+    /// Headers to set from claims, keyed by header name
+    #[serde(default, deserialize_with = "deserialize_claim_mappings")]
+    #[schemars(with = "HashMap<String, ClaimMapping>")]
+    pub headers: Vec<(HeaderName, ClaimMapping)>,
+
+    /// A header carrying the caller's identity as a JWT signed by the MCP server
+    pub identity: Option<IdentityHeaderConfig>,
+}
+
+/// The claim a header is set from
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ClaimMapping {
+    /// A claim path, such as `sub` or `org.tenant_id`
+    Path(String),
+
+    /// A claim path, with the separator used to join array claims
+    Detailed {
+        /// A claim path, such as `sub` or `org.tenant_id`
+        claim: String,
+
+        /// Separator used to join the elements of an array claim (default: `,`)
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+}
+
+fn default_separator() -> String {
+    ",".to_string()
+}
+
+impl ClaimMapping {
+    fn claim(&self) -> &str {
+        match self {
+            ClaimMapping::Path(claim) | ClaimMapping::Detailed { claim, .. } => claim,
+        }
+    }
+
+    fn separator(&self) -> &str {
+        match self {
+            ClaimMapping::Path(_) => ",",
+            ClaimMapping::Detailed { separator, .. } => separator,
+        }
+    }
+}
+
+/// Signed identity header configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct IdentityHeaderConfig {
+    /// The header to send the identity token in (default: `x-mcp-identity`)
+    #[serde(
+        default = "default_identity_header",
+        deserialize_with = "deserialize_header_name"
+    )]
+    #[schemars(with = "String")]
+    pub header: HeaderName,
+
+    /// Shared secret used to sign the identity token (HS256)
+    #[schemars(with = "String")]
+    pub secret: SecretString,
+
+    /// How long the identity token is valid (default: 60s)
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_identity_ttl"
+    )]
+    #[schemars(with = "String")]
+    pub ttl: Duration,
+
+    /// The `iss` claim of the identity token (default: `apollo-mcp-server`)
+    #[serde(default = "default_identity_issuer")]
+    pub issuer: String,
+
+    /// The `aud` claim of the identity token
+    pub audience: Option<String>,
+
+    /// Claim paths to copy into the identity token, in addition to `sub` and `scope`
+    #[serde(default)]
+    pub claims: Vec<String>,
+}
+
+fn default_identity_header() -> HeaderName {
+    HeaderName::from_static("x-mcp-identity")
+}
+
+fn default_identity_ttl() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_identity_issuer() -> String {
+    "apollo-mcp-server".to_string()
+}
+
+fn deserialize_header_name<'de, D>(deserializer: D) -> Result<HeaderName, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    HeaderName::from_str(&name).map_err(|e| serde::de::Error::custom(format!("{name:?}: {e}")))
+}
+
+fn deserialize_claim_mappings<'de, D>(
+    deserializer: D,
+) -> Result<Vec<(HeaderName, ClaimMapping)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    HashMap::<String, ClaimMapping>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, mapping)| {
+            HeaderName::from_str(&name)
+                .map(|name| (name, mapping))
+                .map_err(|e| serde::de::Error::custom(format!("{name:?}: {e}")))
+        })
+        .collect()
+}
+
+impl ClaimHeadersConfig {
+    /// Set the configured headers from the claims of `token`.
+    ///
+    /// Configured headers are always removed first, so a header forwarded from the client
+    /// cannot stand in for a claim the token doesn't have.
+    pub(crate) fn apply(&self, token: Option<&ValidToken>, headers: &mut HeaderMap) {
+        for (name, mapping) in &self.headers {
+            headers.remove(name);
+            let Some(token) = token else {
+                continue;
+            };
+            let Some(value) = lookup_claim(&token.claims, mapping.claim())
+                .and_then(|v| render(v, mapping.separator()))
+            else {
+                continue;
+            };
+            match HeaderValue::from_str(&value) {
+                Ok(value) => {
+                    headers.insert(name.clone(), value);
+                }
+                Err(_) => {
+                    warn!(header = %name, claim = mapping.claim(), "Claim is not a valid header value")
+                }
+            }
+        }
+
+        if let Some(identity) = &self.identity {
+            headers.remove(&identity.header);
+            if let Some(token) = token {
+                match identity.sign(token) {
+                    Ok(value) => {
+                        headers.insert(identity.header.clone(), value);
+                    }
+                    Err(e) => warn!(error = %e, "Failed to sign identity header"),
+                }
+            }
+        }
+    }
+}
+
+impl IdentityHeaderConfig {
+    fn sign(&self, token: &ValidToken) -> Result<HeaderValue, Box<dyn std::error::Error>> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let mut claims = Map::new();
+        for claim in &self.claims {
+            if let Some(value) = lookup_claim(&token.claims, claim) {
+                claims.insert(claim.clone(), value.clone());
+            }
+        }
+        claims.insert("iss".to_string(), self.issuer.clone().into());
+        if let Some(audience) = &self.audience {
+            claims.insert("aud".to_string(), audience.clone().into());
+        }
+        claims.insert("sub".to_string(), token.subject.clone().into());
+        if !token.scopes.is_empty() {
+            claims.insert("scope".to_string(), token.scopes.join(" ").into());
+        }
+        claims.insert("iat".to_string(), now.into());
+        claims.insert("exp".to_string(), (now + self.ttl.as_secs()).into());
+
+        let jwt = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.secret.expose_secret().as_bytes()),
+        )?;
+        Ok(HeaderValue::from_str(&jwt)?)
+    }
+}
+
+/// Look up a claim by path.
+///
+/// A claim whose name contains dots, such as a namespaced `https://example.com/tenant` claim,
+/// is matched by its full name before the path is split into nested segments.
+pub(crate) fn lookup_claim<'a>(claims: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = claims.get(path) {
+        return Some(value);
+    }
+    let mut segments = path.split('.');
+    let first = claims.get(segments.next()?)?;
+    segments.try_fold(first, |value, segment| value.as_object()?.get(segment))
+}
+
+/// Render a claim as a header value, joining arrays of scalars with `separator`
+fn render(value: &Value, separator: &str) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Array(_) | Value::Object(_) | Value::Null => None,
+                    scalar => render(scalar, separator),
+                })
+                .collect::<Vec<_>>()
+                .join(separator),
+        ),
+        Value::Object(_) | Value::Null => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::Authorization;
+    use jsonwebtoken::{DecodingKey, Validation, decode};
+    use serde_json::json;
+
+    use super::*;
+
+    fn token(claims: Value) -> ValidToken {
+        let Value::Object(claims) = claims else {
+            panic!("claims must be an object");
+        };
+        ValidToken {
+            token: Authorization::bearer("client-token").unwrap(),
+            subject: "alice".to_string(),
+            scopes: vec!["read".to_string()],
+            claims,
+            api_key: false,
+        }
+    }
+
+    fn config(yaml: &str) -> ClaimHeadersConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn maps_claims_to_headers() {
+        let config = config(
+            r#"
+            headers:
+              x-user-id: sub
+              x-tenant-id: org.tenant.id
+              x-roles:
+                claim: realm_access.roles
+                separator: " "
+              x-groups: groups
+              x-namespaced: https://example.com/tenant
+            "#,
+        );
+        let token = token(json!({
+            "sub": "alice",
+            "org": { "tenant": { "id": 42 } },
+            "realm_access": { "roles": ["admin", "editor"] },
+            "groups": ["a", "b"],
+            "https://example.com/tenant": "acme",
+        }));
+
+        let mut headers = HeaderMap::new();
+        config.apply(Some(&token), &mut headers);
+
+        assert_eq!(headers.get("x-user-id").unwrap(), "alice");
+        assert_eq!(headers.get("x-tenant-id").unwrap(), "42");
+        assert_eq!(headers.get("x-roles").unwrap(), "admin editor");
+        assert_eq!(headers.get("x-groups").unwrap(), "a,b");
+        assert_eq!(headers.get("x-namespaced").unwrap(), "acme");
+    }
+
+    #[test]
+    fn removes_mapped_headers_when_claim_is_missing() {
+        let config = config(
+            r#"
+            headers:
+              x-tenant-id: tenant
+            "#,
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert("x-tenant-id", HeaderValue::from_static("spoofed"));
+
+        config.apply(Some(&token(json!({ "sub": "alice" }))), &mut headers);
+
+        assert!(headers.get("x-tenant-id").is_none());
+    }
+
+    #[test]
+    fn signs_identity_header() {
+        let config = config(
+            r#"
+            identity:
+              secret: shared-secret
+              audience: subgraphs
+              claims:
+                - org.tenant
+            "#,
+        );
+        let token = token(json!({ "sub": "alice", "org": { "tenant": "acme" } }));
+
+        let mut headers = HeaderMap::new();
+        config.apply(Some(&token), &mut headers);
+
+        let jwt = headers.get("x-mcp-identity").unwrap().to_str().unwrap();
+        let mut validation = Validation::default();
+        validation.set_audience(&["subgraphs"]);
+        validation.set_issuer(&["apollo-mcp-server"]);
+        let decoded = decode::<Value>(
+            jwt,
+            &DecodingKey::from_secret(b"shared-secret"),
+            &validation,
+        )
+        .unwrap();
+        assert_eq!(decoded.claims["sub"], "alice");
+        assert_eq!(decoded.claims["scope"], "read");
+        assert_eq!(decoded.claims["org.tenant"], "acme");
+    }
+
+    #[test]
+    fn rejects_invalid_header_names() {
+        let result = serde_yaml::from_str::<ClaimHeadersConfig>(
+            r#"
+            headers:
+              "x tenant": tenant
+            "#,
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Signing keys for an auth server, read from local configuration instead of discovered
+//!
+//! Air-gapped deployments and tests can't reach an authorization server's discovery and JWKS
+//! endpoints. For those, an auth server's public keys can be given inline as PEM or in a local
+//! JWKS file, which is reloaded when it changes so that keys can be rotated without a restart.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+};
+
+use apollo_mcp_registry::files;
+use futures::StreamExt as _;
+use jsonwebtoken::{DecodingKey, jwk::JwkSet, jwk::KeyAlgorithm};
+use jwks::{Jwk, JwkEntry, JwkError};
+use parking_lot::RwLock;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tracing::{error, info};
+use url::Url;
+
+use super::valid_token::KeyResolver;
+
+/// Local signing keys for one auth server
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LocalKeysConfig {
+    /// Path to a JWKS file with the server's public keys. The file is reloaded when it changes.
+    pub jwks_file: Option<PathBuf>,
+
+    /// PEM-encoded public keys
+    #[serde(default)]
+    pub keys: Vec<PemKeyConfig>,
+
+    /// The issuer of tokens signed by these keys, which the `iss` claim must match when
+    /// `issuers` are configured (default: the server URL without a trailing slash)
+    pub issuer: Option<String>,
+}
+
+/// A PEM-encoded public key
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PemKeyConfig {
+    /// The key ID, matched against the `kid` header of tokens
+    pub kid: String,
+
+    /// The signing algorithm, such as `RS256`, `ES256`, or `EdDSA`
+    #[schemars(with = "String")]
+    pub algorithm: KeyAlgorithm,
+
+    /// The public key, in PEM format
+    pub public_key: String,
+}
+
+/// Errors from loading local signing keys
+#[derive(Debug, thiserror::Error)]
+pub enum LocalKeysError {
+    #[error("Failed to read JWKS file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse JWKS file {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("Invalid key in JWKS file {path}: {source}")]
+    InvalidJwk { path: PathBuf, source: JwkError },
+    #[error("Invalid PEM public key {kid:?}: {source}")]
+    InvalidPem {
+        kid: String,
+        source: jsonwebtoken::errors::Error,
+    },
+    #[error("PEM public key {kid:?} has algorithm {algorithm:?}, which has no public key")]
+    UnsupportedAlgorithm {
+        kid: String,
+        algorithm: KeyAlgorithm,
+    },
+    #[error("Local keys for {server} configure neither `jwks_file` nor `keys`")]
+    NoKeys { server: String },
+}
+
+/// The signing keys of one auth server, keyed by key ID
+#[derive(Clone)]
+pub(super) struct LocalKeys {
+    issuer: String,
+    pem: Arc<HashMap<String, Jwk>>,
+    jwks: Arc<RwLock<HashMap<String, Jwk>>>,
+}
+
+impl LocalKeys {
+    /// Load the configured keys of `server`
+    pub(super) fn load(server: &str, config: &LocalKeysConfig) -> Result<Self, LocalKeysError> {
+        if config.jwks_file.is_none() && config.keys.is_empty() {
+            return Err(LocalKeysError::NoKeys {
+                server: server.to_string(),
+            });
+        }
+
+        let pem = config
+            .keys
+            .iter()
+            .map(|key| Ok((key.kid.clone(), decode_pem(key)?)))
+            .collect::<Result<_, LocalKeysError>>()?;
+        let jwks = config
+            .jwks_file
+            .as_deref()
+            .map(read_jwks)
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            issuer: config
+                .issuer
+                .clone()
+                .unwrap_or_else(|| server.trim_end_matches('/').to_string()),
+            pem: Arc::new(pem),
+            jwks: Arc::new(RwLock::new(jwks)),
+        })
+    }
+
+    /// Reload the JWKS file whenever it changes.
+    ///
+    /// A file that fails to load leaves the previous keys in place. Watching stops once every
+    /// copy of the keys has been dropped.
+    pub(super) fn watch(&self, config: &LocalKeysConfig) {
+        let Some(path) = config.jwks_file.clone() else {
+            return;
+        };
+        let jwks: Weak<RwLock<_>> = Arc::downgrade(&self.jwks);
+        tokio::spawn(async move {
+            // Skip the initial event that files::watch always emits on startup
+            let mut changes = std::pin::pin!(files::watch(&path).skip(1));
+            while changes.next().await.is_some() {
+                let Some(jwks) = jwks.upgrade() else {
+                    break;
+                };
+                match read_jwks(&path) {
+                    Ok(reloaded) => {
+                        info!(path = %path.display(), count = reloaded.len(), "Reloaded JWKS file");
+                        *jwks.write() = reloaded;
+                    }
+                    Err(e) => error!("{e}, keeping the previously loaded keys"),
+                }
+            }
+        });
+    }
+
+    fn get(&self, key_id: &str) -> Option<Jwk> {
+        self.pem
+            .get(key_id)
+            .cloned()
+            .or_else(|| self.jwks.read().get(key_id).cloned())
+    }
+}
+
+fn decode_pem(key: &PemKeyConfig) -> Result<Jwk, LocalKeysError> {
+    let pem = key.public_key.as_bytes();
+    let decoding_key = match key.algorithm {
+        KeyAlgorithm::RS256
+        | KeyAlgorithm::RS384
+        | KeyAlgorithm::RS512
+        | KeyAlgorithm::PS256
+        | KeyAlgorithm::PS384
+        | KeyAlgorithm::PS512 => DecodingKey::from_rsa_pem(pem),
+        KeyAlgorithm::ES256 | KeyAlgorithm::ES384 => DecodingKey::from_ec_pem(pem),
+        KeyAlgorithm::EdDSA => DecodingKey::from_ed_pem(pem),
+        algorithm => {
+            return Err(LocalKeysError::UnsupportedAlgorithm {
+                kid: key.kid.clone(),
+                algorithm,
+            });
+        }
+    }
+    .map_err(|source| LocalKeysError::InvalidPem {
+        kid: key.kid.clone(),
+        source,
+    })?;
+
+    Ok(Jwk {
+        alg: Some(key.algorithm),
+        decoding_key,
+    })
+}
+
+fn read_jwks(path: &Path) -> Result<HashMap<String, Jwk>, LocalKeysError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| LocalKeysError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let set: JwkSet = serde_json::from_str(&contents).map_err(|source| LocalKeysError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    set.keys
+        .into_iter()
+        .map(|jwk| {
+            let JwkEntry { kid, jwk } =
+                jwk.try_into()
+                    .map_err(|source| LocalKeysError::InvalidJwk {
+                        path: path.to_path_buf(),
+                        source,
+                    })?;
+            Ok((kid, jwk))
+        })
+        .collect()
+}
+
+/// [`KeyResolver`] that serves the keys of servers configured with local keys, and defers to
+/// `fallback` for every other server.
+pub(super) struct LocalKeyResolver<'a, R: KeyResolver> {
+    keys: &'a HashMap<Url, LocalKeys>,
+    fallback: R,
+}
+
+impl<'a, R: KeyResolver> LocalKeyResolver<'a, R> {
+    pub fn new(keys: &'a HashMap<Url, LocalKeys>, fallback: R) -> Self {
+        Self { keys, fallback }
+    }
+}
+
+impl<R: KeyResolver> KeyResolver for LocalKeyResolver<'_, R> {
+    async fn resolve_key(&self, server: &Url, key_id: &str) -> Option<(Jwk, String)> {
+        match self.keys.get(server) {
+            Some(local) => local.get(key_id).map(|jwk| (jwk, local.issuer.clone())),
+            None => self.fallback.resolve_key(server, key_id).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    // P-256 public key generated for these tests only
+    const EC_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE34u9jquYSBJCxflNBOjiVTb/g1F7
+e91dEL8qGk6O9d1QF87dDJzvT4/VS558idCb62E0IzjtEj41CcGw1plHFw==
+-----END PUBLIC KEY-----";
+
+    fn jwks_file(kid: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{"keys":[{{"kty":"oct","kid":"{kid}","alg":"HS256","k":"c2VjcmV0"}}]}}"#
+        )
+        .unwrap();
+        file
+    }
+
+    /// Resolver standing in for discovery, which must not be reached for local servers
+    struct Unreachable;
+
+    impl KeyResolver for Unreachable {
+        async fn resolve_key(&self, _server: &Url, _key_id: &str) -> Option<(Jwk, String)> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn resolves_pem_and_jwks_keys_with_default_issuer() {
+        let file = jwks_file("file-key");
+        let config: LocalKeysConfig = serde_yaml::from_str(&format!(
+            r#"
+            jwks_file: {}
+            keys:
+              - kid: pem-key
+                algorithm: ES256
+                public_key: |
+                  {}
+            "#,
+            file.path().display(),
+            EC_PUBLIC_KEY.replace('\n', "\n                  ")
+        ))
+        .unwrap();
+        let server = Url::parse("https://auth.internal/").unwrap();
+        let keys = HashMap::from([(
+            server.clone(),
+            LocalKeys::load("https://auth.internal/", &config).unwrap(),
+        )]);
+        let resolver = LocalKeyResolver::new(&keys, Unreachable);
+
+        let (pem, issuer) = resolver.resolve_key(&server, "pem-key").await.unwrap();
+        let (jwk, _) = resolver.resolve_key(&server, "file-key").await.unwrap();
+
+        assert_eq!(pem.alg, Some(KeyAlgorithm::ES256));
+        assert_eq!(jwk.alg, Some(KeyAlgorithm::HS256));
+        assert_eq!(issuer, "https://auth.internal");
+        assert!(resolver.resolve_key(&server, "unknown").await.is_none());
+    }
+
+    #[test]
+    fn rejects_config_without_keys() {
+        let config: LocalKeysConfig =
+            serde_yaml::from_str("issuer: https://auth.internal").unwrap();
+
+        let result = LocalKeys::load("https://auth.internal", &config);
+
+        assert!(matches!(result, Err(LocalKeysError::NoKeys { .. })));
+    }
+
+    #[test]
+    fn rejects_symmetric_pem_keys() {
+        let config = LocalKeysConfig {
+            jwks_file: None,
+            keys: vec![PemKeyConfig {
+                kid: "shared".to_string(),
+                algorithm: KeyAlgorithm::HS256,
+                public_key: EC_PUBLIC_KEY.to_string(),
+            }],
+            issuer: None,
+        };
+
+        let result = LocalKeys::load("https://auth.internal", &config);
+
+        assert!(matches!(
+            result,
+            Err(LocalKeysError::UnsupportedAlgorithm { kid, .. }) if kid == "shared"
+        ));
+    }
+
+    #[tokio::test]
+    async fn reloads_jwks_file_when_it_changes() {
+        let file = jwks_file("first-key");
+        let config = LocalKeysConfig {
+            jwks_file: Some(file.path().to_path_buf()),
+            keys: vec![],
+            issuer: None,
+        };
+        let keys = LocalKeys::load("https://auth.internal", &config).unwrap();
+        keys.watch(&config);
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+        std::fs::write(
+            file.path(),
+            r#"{"keys":[{"kty":"oct","kid":"second-key","alg":"HS256","k":"c2VjcmV0"}]}"#,
+        )
+        .unwrap();
+
+        let reloaded = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            while keys.get("second-key").is_none() {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        assert!(reloaded.is_ok());
+        assert!(keys.get("first-key").is_none());
+    }
+}
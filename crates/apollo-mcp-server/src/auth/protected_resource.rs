@@ -1,13 +1,14 @@
 use serde::Serialize;
 use url::Url;
 
-use super::Config;
+use super::{Config, auth_proxy};
 
 /// OAuth 2.1 Protected Resource Response
 #[derive(Serialize)]
 pub(super) struct ProtectedResource {
     /// The URL of the resource
-    resource: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource: Option<Url>,
 
     /// List of authorization servers protecting this resource.
     authorization_servers: Vec<String>,
@@ -25,9 +26,19 @@ pub(super) struct ProtectedResource {
 
 impl From<Config> for ProtectedResource {
     fn from(value: Config) -> Self {
+        // With the built-in proxy, clients log in through this server instead
+        let authorization_servers = match (&value.proxy, &value.resource) {
+            (Some(_), Some(resource)) => vec![
+                auth_proxy::issuer(resource)
+                    .as_str()
+                    .trim_end_matches('/')
+                    .to_string(),
+            ],
+            _ => value.servers,
+        };
         Self {
             resource: value.resource,
-            authorization_servers: value.servers,
+            authorization_servers,
             bearer_methods_supported: vec!["header".to_string()], // The spec only supports header auth
             scopes_supported: value.scopes,
             resource_documentation: value.resource_documentation,
@@ -92,4 +103,27 @@ mod tests {
             ])
         );
     }
+    #[test]
+    fn proxy_advertises_this_server_as_authorization_server() {
+        let yaml = r#"
+            servers:
+              - https://idp.example.com
+            resource: https://mcp.example.com/mcp
+            proxy:
+              authorization_endpoint: https://idp.example.com/authorize
+              token_endpoint: https://idp.example.com/token
+              client_id: apollo-mcp
+              allowed_redirect_uris:
+                - http://localhost
+        "#;
+
+        let config: Config = serde_yaml::from_str(yaml).expect("config parses");
+        let metadata = ProtectedResource::from(config);
+        let json = serde_json::to_value(&metadata).expect("metadata serializes");
+
+        assert_eq!(
+            json["authorization_servers"],
+            serde_json::json!(["https://mcp.example.com"])
+        );
+    }
 }
@@ -1,9 +1,12 @@
 use std::ops::Deref;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use headers::{Authorization, authorization::Bearer};
 use jsonwebtoken::{Algorithm, Validation, decode, decode_header, jwk};
 use jwks::Jwk;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use tracing::{info, warn};
 use url::Url;
 
@@ -14,7 +17,15 @@ use url::Url;
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct ValidToken {
     pub(crate) token: Authorization<Bearer>,
+    /// The `sub` claim of the token
+    pub(crate) subject: String,
     pub(crate) scopes: Vec<String>,
+    /// Every claim of the token. For introspected tokens, these are the members
+    /// of the introspection response.
+    pub(crate) claims: Map<String, Value>,
+    /// Whether the token is one of the server's own API keys, which are never
+    /// sent to upstream services
+    pub(crate) api_key: bool,
 }
 
 impl Deref for ValidToken {
@@ -28,7 +39,7 @@ impl Deref for ValidToken {
 /// Resolves a signing key for a `(server, key_id)` pair, returning it together
 /// with the issuer that server advertises in discovery.
 ///
-/// This is the single seam that production fetches over the network and tests
+/// This is the seam that production fetches over the network and tests
 /// substitute in memory. Everything else about validation lives in
 /// [`TokenValidator`], so adding a new validation input does not touch this
 /// trait or its implementations.
@@ -43,9 +54,25 @@ pub(super) trait KeyResolver {
     async fn resolve_key(&self, server: &Url, key_id: &str) -> Option<(Jwk, String)>;
 }
 
-/// Validates bearer JWTs against the configured audiences, issuers, and upstream
-/// servers, resolving signing keys through `keys`.
-pub(super) struct TokenValidator<'a, R: KeyResolver> {
+/// Introspects opaque tokens at the servers configured for introspection
+/// (RFC 7662).
+///
+/// Like [`KeyResolver`], this only covers the network call: the audience,
+/// issuer, and expiry checks on the response live in [`TokenValidator`].
+pub(super) trait Introspector {
+    /// Whether tokens issued by `server` are validated by introspection instead
+    /// of by verifying a JWT signature.
+    fn introspects(&self, server: &Url) -> bool;
+
+    /// Introspect `token` at `server`. Returns `None` when the introspection
+    /// request fails.
+    async fn introspect(&self, server: &Url, token: &str) -> Option<Introspection>;
+}
+
+/// Validates bearer tokens against the configured audiences, issuers, and upstream
+/// servers, resolving signing keys through `keys` and introspecting opaque
+/// tokens through `introspector`.
+pub(super) struct TokenValidator<'a, R: KeyResolver, I: Introspector> {
     /// Accepted audiences. Ignored when `allow_any_audience` is set.
     pub(super) audiences: &'a [String],
     /// Accepted issuers (empty = skip issuer validation).
@@ -56,16 +83,48 @@ pub(super) struct TokenValidator<'a, R: KeyResolver> {
     pub(super) servers: &'a [Url],
     /// Resolves signing keys (the network seam).
     pub(super) keys: R,
+    /// Introspects opaque tokens (the network seam for introspection servers).
+    pub(super) introspector: I,
 }
 
-impl<R: KeyResolver> TokenValidator<'_, R> {
+impl<R: KeyResolver, I: Introspector> TokenValidator<'_, R, I> {
     /// Attempt to validate a token against the configured rules.
     pub(super) async fn validate(&self, token: Authorization<Bearer>) -> Option<ValidToken> {
         let jwt = token.token();
-        let header = decode_header(jwt).ok()?;
-        let key_id = header.kid.as_ref()?;
+        // Opaque tokens have no JWT header, so only introspection servers can
+        // validate them.
+        let header = decode_header(jwt).ok();
+        let key_id = header.as_ref().and_then(|header| header.kid.clone());
+        // The claimed issuer of a JWT, before its signature is checked. It is
+        // only used to decide where the token may be sent for introspection.
+        let claimed_issuer = header.as_ref().and_then(|_| unverified_issuer(jwt));
 
         for server in self.servers {
+            if self.introspector.introspects(server) {
+                // Only send a token to an introspection server that could have
+                // issued it, so JWTs from other servers never leak to it.
+                if header.is_some()
+                    && !claimed_issuer
+                        .as_deref()
+                        .is_some_and(|issuer| is_issued_by(issuer, server))
+                {
+                    continue;
+                }
+                let Some(introspection) = self.introspector.introspect(server, jwt).await else {
+                    continue;
+                };
+                if !introspection.active {
+                    continue;
+                }
+                match self.validate_introspection(&token, introspection) {
+                    Some(valid_token) => return Some(valid_token),
+                    None => break,
+                }
+            }
+
+            let Some(key_id) = key_id.as_deref() else {
+                continue;
+            };
             let Some((jwk, discovered_issuer)) = self.keys.resolve_key(server, key_id).await else {
                 continue;
             };
@@ -93,13 +152,21 @@ impl<R: KeyResolver> TokenValidator<'_, R> {
                 val
             };
 
-            match decode::<Claims>(jwt, &jwk.decoding_key, &validation) {
-                Ok(token_data) => {
+            // Decode every claim, then the subset validation depends on
+            let decoded = decode::<Map<String, Value>>(jwt, &jwk.decoding_key, &validation)
+                .map_err(|e| e.to_string())
+                .and_then(|token_data| {
+                    serde_json::from_value::<Claims>(Value::Object(token_data.claims.clone()))
+                        .map(|claims| (claims, token_data.claims))
+                        .map_err(|e| e.to_string())
+                });
+            match decoded {
+                Ok((claims, raw_claims)) => {
                     // When audience validation is enabled, explicitly reject tokens
                     // with a missing `aud` claim. The `jsonwebtoken` crate skips its
                     // own audience check when the claim is absent from the raw JWT,
                     // so we enforce it here.
-                    if !self.allow_any_audience && token_data.claims.aud.is_empty() {
+                    if !self.allow_any_audience && claims.aud.is_empty() {
                         warn!("Token is missing the required `aud` claim");
                         break;
                     }
@@ -108,7 +175,7 @@ impl<R: KeyResolver> TokenValidator<'_, R> {
                         // with a missing `iss` claim. The `jsonwebtoken` crate skips its
                         // own issuer check when the claim is absent from the raw JWT,
                         // so we enforce it here.
-                        let Some(token_issuer) = token_data.claims.iss.as_deref() else {
+                        let Some(token_issuer) = claims.iss.as_deref() else {
                             warn!("Token is missing the required `iss` claim");
                             break;
                         };
@@ -129,7 +196,10 @@ impl<R: KeyResolver> TokenValidator<'_, R> {
                     }
                     return Some(ValidToken {
                         token,
-                        scopes: token_data.claims.scopes(),
+                        subject: claims.sub.clone(),
+                        scopes: claims.scopes(),
+                        claims: raw_claims,
+                        api_key: false,
                     });
                 }
                 Err(e) => warn!("Token failed validation with error: {e}"),
@@ -139,6 +209,119 @@ impl<R: KeyResolver> TokenValidator<'_, R> {
         info!("Token did not pass validation");
         None
     }
+
+    /// Apply the audience, issuer, and expiry checks to an active introspection
+    /// response, mapping it to a [`ValidToken`].
+    fn validate_introspection(
+        &self,
+        token: &Authorization<Bearer>,
+        introspection: Introspection,
+    ) -> Option<ValidToken> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        if introspection.exp.is_some_and(|exp| exp <= now) {
+            warn!("Introspected token has expired");
+            return None;
+        }
+
+        // A response without `aud` is rejected, matching the explicit
+        // missing-claim check for JWTs.
+        if !self.allow_any_audience
+            && !introspection
+                .aud
+                .iter()
+                .any(|aud| self.audiences.contains(aud))
+        {
+            warn!(audiences = ?introspection.aud, "Introspected token has no accepted audience");
+            return None;
+        }
+
+        if !self.issuers.is_empty()
+            && !introspection
+                .iss
+                .as_ref()
+                .is_some_and(|iss| self.issuers.contains(iss))
+        {
+            warn!(issuer = ?introspection.iss, "Introspected token has no accepted issuer");
+            return None;
+        }
+
+        // Tokens issued to a client rather than a user (client credentials) have
+        // no `sub`, so fall back to the client they were issued to.
+        let Some(subject) = introspection.sub.or(introspection.client_id) else {
+            warn!("Introspected token has neither a `sub` nor a `client_id`");
+            return None;
+        };
+
+        Some(ValidToken {
+            token: token.clone(),
+            subject,
+            scopes: introspection
+                .scope
+                .map(|scope| scope.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            claims: introspection.claims,
+            api_key: false,
+        })
+    }
+}
+
+/// The `iss` claim of a JWT, read without verifying its signature
+fn unverified_issuer(jwt: &str) -> Option<String> {
+    let payload = jwt.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload).ok()?;
+    serde_json::from_slice::<Map<String, Value>>(&payload)
+        .ok()?
+        .get("iss")?
+        .as_str()
+        .map(String::from)
+}
+
+/// Whether `issuer` identifies the authorization server at `server`, ignoring
+/// a trailing slash
+fn is_issued_by(issuer: &str, server: &Url) -> bool {
+    issuer.trim_end_matches('/') == server.as_str().trim_end_matches('/')
+}
+
+/// The members of an introspection response (RFC 7662 Section 2.2) that map
+/// onto token validation.
+///
+/// See: https://datatracker.ietf.org/doc/html/rfc7662#section-2.2
+#[derive(Clone, Debug, Default, Deserialize)]
+pub(super) struct Introspection {
+    /// Whether the token is currently active. Every other member is only
+    /// meaningful for active tokens.
+    pub(super) active: bool,
+
+    /// Space-separated scopes granted by the token
+    #[serde(default)]
+    pub(super) scope: Option<String>,
+
+    /// The client the token was issued to
+    #[serde(default)]
+    pub(super) client_id: Option<String>,
+
+    /// The subject the token was issued for
+    #[serde(default)]
+    pub(super) sub: Option<String>,
+
+    /// The intended audience of the token, a single string or an array
+    #[serde(default, deserialize_with = "deserialize_audience")]
+    pub(super) aud: Vec<String>,
+
+    /// The issuer of the token
+    #[serde(default)]
+    pub(super) iss: Option<String>,
+
+    /// When the token expires, in seconds since the Unix epoch
+    #[serde(default)]
+    pub(super) exp: Option<u64>,
+
+    /// Every member of the response
+    #[serde(skip)]
+    pub(super) claims: Map<String, Value>,
 }
 
 /// Claims which must be present in the JWT (and must match validation) in order
@@ -247,7 +430,9 @@ mod test {
     use tracing_test::traced_test;
     use url::Url;
 
-    use super::{Claims, KeyResolver, TokenValidator, ValidToken, jwt_algorithm};
+    use super::{
+        Claims, Introspection, Introspector, KeyResolver, TokenValidator, ValidToken, jwt_algorithm,
+    };
 
     /// A single upstream server in the stub resolver: its URL, the one
     /// `(kid, jwk)` it serves, and the issuer it advertises in discovery.
@@ -275,6 +460,24 @@ mod test {
         }
     }
 
+    /// In-memory [`Introspector`] returning a canned response per server.
+    struct StubIntrospector<'a> {
+        introspections: &'a [(Url, Introspection)],
+    }
+
+    impl Introspector for StubIntrospector<'_> {
+        fn introspects(&self, server: &Url) -> bool {
+            self.introspections.iter().any(|(url, _)| url == server)
+        }
+
+        async fn introspect(&self, server: &Url, _token: &str) -> Option<Introspection> {
+            self.introspections
+                .iter()
+                .find(|(url, _)| url == server)
+                .map(|(_, introspection)| introspection.clone())
+        }
+    }
+
     /// Thin test harness that owns the validation inputs and delegates to the
     /// real [`TokenValidator`] through a [`StubKeyResolver`]. Keeps the test
     /// call sites concise while exercising the production validation path.
@@ -285,6 +488,8 @@ mod test {
         servers: Vec<TestServer>,
         /// Parsed server URLs, kept index-aligned with `servers`.
         server_urls: Vec<Url>,
+        /// Servers validated by introspection, with their canned responses.
+        introspections: Vec<(Url, Introspection)>,
     }
 
     impl TestTokenValidator {
@@ -301,6 +506,25 @@ mod test {
                 allow_any_audience,
                 servers,
                 server_urls,
+                introspections: Vec::new(),
+            }
+        }
+
+        /// A harness whose only server validates tokens by introspection,
+        /// answering every request with `introspection`.
+        fn introspecting(
+            audiences: Vec<String>,
+            issuers: Vec<String>,
+            introspection: Introspection,
+        ) -> Self {
+            let server = Url::from_str("https://auth.example.com").expect("valid server URL");
+            Self {
+                audiences,
+                issuers,
+                allow_any_audience: false,
+                servers: Vec::new(),
+                server_urls: vec![server.clone()],
+                introspections: vec![(server, introspection)],
             }
         }
 
@@ -337,6 +561,9 @@ mod test {
                 keys: StubKeyResolver {
                     servers: &self.servers,
                 },
+                introspector: StubIntrospector {
+                    introspections: &self.introspections,
+                },
             }
             .validate(token)
             .await
@@ -566,6 +793,47 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn it_keeps_all_claims_of_valid_jwt() {
+        use serde_json::json;
+
+        let key_id = "some-example-id".to_string();
+        let (encode_key, decode_key) = create_key("DEADBEEF");
+        let jwk = Jwk {
+            alg: Some(KeyAlgorithm::HS512),
+            decoding_key: decode_key,
+        };
+
+        let header = {
+            let mut h = Header::new(Algorithm::HS512);
+            h.kid = Some(key_id.clone());
+            h
+        };
+        let claims = json!({
+            "aud": "test-audience",
+            "exp": chrono::Utc::now().timestamp() + 1000,
+            "sub": "test user",
+            "org": { "tenant": "acme" }
+        });
+        let token = encode(&header, &claims, &encode_key).expect("encode JWT");
+        let jwt = Authorization::bearer(&token).expect("create bearer token");
+
+        let server =
+            Url::from_str("https://auth.example.com").expect("should parse a valid example server");
+        let test_validator = TestTokenValidator::single(
+            vec!["test-audience".to_string()],
+            vec![],
+            false,
+            (key_id, jwk),
+            server,
+        );
+
+        let valid = test_validator.validate(jwt).await.expect("valid token");
+
+        assert_eq!(valid.claims.get("org"), Some(&json!({ "tenant": "acme" })));
+        assert_eq!(valid.claims.get("sub"), Some(&json!("test user")));
+    }
+
     #[tokio::test]
     async fn it_validates_jwt_with_allow_any_audience() {
         let key_id = "some-example-id".to_string();
@@ -1335,4 +1603,156 @@ mod test {
         // `RSA1_5` is a key-management algorithm, not a token-signing one.
         assert_eq!(jwt_algorithm(KeyAlgorithm::RSA1_5), None);
     }
+
+    fn active_introspection() -> Introspection {
+        Introspection {
+            active: true,
+            scope: Some("read write".to_string()),
+            client_id: Some("mcp-client".to_string()),
+            sub: Some("test user".to_string()),
+            aud: vec!["test-audience".to_string()],
+            iss: Some("https://auth.example.com".to_string()),
+            exp: Some(chrono::Utc::now().timestamp() as u64 + 1000),
+            claims: Default::default(),
+        }
+    }
+
+    fn opaque_token() -> Authorization<Bearer> {
+        Authorization::bearer("opaque-reference-token").expect("create bearer token")
+    }
+
+    #[tokio::test]
+    async fn it_validates_opaque_token_by_introspection() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec!["https://auth.example.com".to_string()],
+            active_introspection(),
+        );
+
+        let valid = test_validator
+            .validate(opaque_token())
+            .await
+            .expect("valid token");
+
+        assert_eq!(valid.token.token(), "opaque-reference-token");
+        assert_eq!(valid.subject, "test user");
+        assert_eq!(valid.scopes, vec!["read".to_string(), "write".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn introspection_subject_falls_back_to_client_id() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec![],
+            Introspection {
+                sub: None,
+                ..active_introspection()
+            },
+        );
+
+        let valid = test_validator
+            .validate(opaque_token())
+            .await
+            .expect("valid token");
+
+        assert_eq!(valid.subject, "mcp-client");
+    }
+
+    #[tokio::test]
+    async fn it_rejects_inactive_introspected_token() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec![],
+            Introspection {
+                active: false,
+                ..active_introspection()
+            },
+        );
+
+        assert_eq!(test_validator.validate(opaque_token()).await, None);
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn it_rejects_introspected_token_with_different_audience() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["other-audience".to_string()],
+            vec![],
+            active_introspection(),
+        );
+
+        assert_eq!(test_validator.validate(opaque_token()).await, None);
+        assert!(logs_contain("Introspected token has no accepted audience"));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn it_rejects_introspected_token_with_different_issuer() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec!["https://other.example.com".to_string()],
+            active_introspection(),
+        );
+
+        assert_eq!(test_validator.validate(opaque_token()).await, None);
+        assert!(logs_contain("Introspected token has no accepted issuer"));
+    }
+
+    #[traced_test]
+    #[tokio::test]
+    async fn it_rejects_expired_introspected_token() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec![],
+            Introspection {
+                exp: Some(chrono::Utc::now().timestamp() as u64 - 1000),
+                ..active_introspection()
+            },
+        );
+
+        assert_eq!(test_validator.validate(opaque_token()).await, None);
+        assert!(logs_contain("Introspected token has expired"));
+    }
+
+    /// An unsigned JWT with the given `iss` claim. Only its header and claims
+    /// are read before introspection, so the signature doesn't matter.
+    fn jwt_issued_by(issuer: &str) -> Authorization<Bearer> {
+        use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","kid":"other-key"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"iss":"{issuer}","sub":"test user"}}"#));
+        Authorization::bearer(&format!("{header}.{claims}.signature")).expect("create bearer token")
+    }
+
+    #[tokio::test]
+    async fn it_introspects_jwt_issued_by_introspection_server() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec![],
+            active_introspection(),
+        );
+
+        let valid = test_validator
+            .validate(jwt_issued_by("https://auth.example.com"))
+            .await
+            .expect("valid token");
+
+        assert_eq!(valid.subject, "test user");
+    }
+
+    #[tokio::test]
+    async fn it_does_not_introspect_jwt_from_another_issuer() {
+        let test_validator = TestTokenValidator::introspecting(
+            vec!["test-audience".to_string()],
+            vec![],
+            active_introspection(),
+        );
+
+        assert_eq!(
+            test_validator
+                .validate(jwt_issued_by("https://other.example.com"))
+                .await,
+            None
+        );
+    }
 }
@@ -7,15 +7,29 @@ use url::Url;
 
 use crate::auth::ScopeMode;
 
+use super::dpop::SUPPORTED_ALGS;
+
 pub(super) enum WwwAuthenticate {
     Bearer {
-        resource_metadata: Url,
+        /// Only present when OAuth is configured
+        resource_metadata: Option<Url>,
         scope: Option<String>,
         error: Option<BearerError>,
         scope_mode: Option<ScopeMode>,
+        /// Only present when DPoP is enabled
+        dpop: Option<DpopChallenge>,
     },
 }
 
+/// The `DPoP` challenge (RFC 9449 Section 7.1), sent alongside the Bearer challenge
+#[derive(Debug, Clone)]
+pub(super) struct DpopChallenge {
+    /// Only DPoP-bound tokens are accepted, so the Bearer challenge is left out
+    pub(super) required: bool,
+    /// The DPoP proof, rather than the token, was rejected
+    pub(super) invalid_proof: bool,
+}
+
 /// OAuth 2.0 Bearer Token error codes per RFC 6750 Section 3.1
 #[derive(Debug, Clone)]
 pub(super) enum BearerError {
@@ -38,43 +52,80 @@ impl Header for WwwAuthenticate {
     }
 
     fn encode<E: Extend<http::HeaderValue>>(&self, values: &mut E) {
-        let encoded = match &self {
+        let challenges = match &self {
             WwwAuthenticate::Bearer {
                 resource_metadata,
                 scope,
                 error,
                 scope_mode,
+                dpop,
             } => {
-                let mut header = format!(
-                    r#"Bearer resource_metadata="{}""#,
-                    resource_metadata.as_str()
-                );
-                // Error must come before scope per RFC 6750 examples
-                if let Some(err) = error {
-                    let error_str = match err {
-                        BearerError::InsufficientScope => "insufficient_scope",
-                    };
-                    header.push_str(&format!(r#", error="{}""#, error_str));
-                }
-                if let Some(scope) = scope {
-                    header.push_str(&format!(r#", scope="{}""#, scope));
-                }
-                if let Some(scope_mode) = scope_mode {
-                    header.push_str(&format!(r#", scope_mode="{}""#, scope_mode.as_str()));
+                let error = error.as_ref().map(|err| match err {
+                    BearerError::InsufficientScope => "insufficient_scope",
+                });
+                let params = |error: Option<&str>| {
+                    let mut params = Vec::new();
+                    if let Some(resource_metadata) = resource_metadata {
+                        params.push(format!(
+                            r#"resource_metadata="{}""#,
+                            resource_metadata.as_str()
+                        ));
+                    }
+                    // Error must come before scope per RFC 6750 examples
+                    if let Some(error) = error {
+                        params.push(format!(r#"error="{}""#, error));
+                    }
+                    if let Some(scope) = scope {
+                        params.push(format!(r#"scope="{}""#, scope));
+                    }
+                    if let Some(scope_mode) = scope_mode {
+                        params.push(format!(r#"scope_mode="{}""#, scope_mode.as_str()));
+                    }
+                    params
+                };
+
+                let mut challenges = Vec::new();
+                match dpop {
+                    None => challenges.push(challenge("Bearer", params(error))),
+                    Some(dpop) => {
+                        // A rejected proof is only reported in the DPoP challenge
+                        if !dpop.required {
+                            let error = if dpop.invalid_proof { None } else { error };
+                            challenges.push(challenge("Bearer", params(error)));
+                        }
+                        let error = if dpop.invalid_proof {
+                            Some("invalid_dpop_proof")
+                        } else {
+                            error
+                        };
+                        let mut params = params(error);
+                        params.push(format!(r#"algs="{SUPPORTED_ALGS}""#));
+                        challenges.push(challenge("DPoP", params));
+                    }
                 }
-                header
+                challenges
             }
         };
 
         // `from_str` is fallible even though our inputs are controlled, so log and skip
         // rather than panic if it ever rejects the constructed value.
-        match HeaderValue::from_str(&encoded) {
-            Ok(value) => values.extend(std::iter::once(value)),
-            Err(e) => warn!("could not construct WWW-AUTHENTICATE header: {e}"),
+        for encoded in challenges {
+            match HeaderValue::from_str(&encoded) {
+                Ok(value) => values.extend(std::iter::once(value)),
+                Err(e) => warn!("could not construct WWW-AUTHENTICATE header: {e}"),
+            }
         }
     }
 }
 
+fn challenge(scheme: &str, params: Vec<String>) -> String {
+    if params.is_empty() {
+        scheme.to_string()
+    } else {
+        format!("{scheme} {}", params.join(", "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,11 +143,13 @@ mod tests {
     #[test]
     fn encode_bearer_without_scope() {
         let header = WwwAuthenticate::Bearer {
-            resource_metadata: Url::parse("https://test.com/.well-known/oauth-protected-resource")
-                .unwrap(),
+            resource_metadata: Some(
+                Url::parse("https://test.com/.well-known/oauth-protected-resource").unwrap(),
+            ),
             scope: None,
             error: None,
             scope_mode: None,
+            dpop: None,
         };
 
         let encoded = encode_header(&header);
@@ -109,13 +162,13 @@ mod tests {
     #[test]
     fn encode_bearer_with_single_scope() {
         let header = WwwAuthenticate::Bearer {
-            resource_metadata: Url::parse(
-                "https://mcp.test.com/.well-known/oauth-protected-resource",
-            )
-            .unwrap(),
+            resource_metadata: Some(
+                Url::parse("https://mcp.test.com/.well-known/oauth-protected-resource").unwrap(),
+            ),
             scope: Some("read".to_string()),
             error: None,
             scope_mode: None,
+            dpop: None,
         };
 
         let encoded = encode_header(&header);
@@ -127,11 +180,13 @@ mod tests {
     #[test]
     fn encode_bearer_with_multiple_scopes() {
         let header = WwwAuthenticate::Bearer {
-            resource_metadata: Url::parse("https://test.com/.well-known/oauth-protected-resource")
-                .unwrap(),
+            resource_metadata: Some(
+                Url::parse("https://test.com/.well-known/oauth-protected-resource").unwrap(),
+            ),
             scope: Some("read write".to_string()),
             error: None,
             scope_mode: None,
+            dpop: None,
         };
 
         let encoded = encode_header(&header);
@@ -144,11 +199,13 @@ mod tests {
     #[test]
     fn encode_bearer_with_insufficient_scope_error() {
         let header = WwwAuthenticate::Bearer {
-            resource_metadata: Url::parse("https://test.com/.well-known/oauth-protected-resource")
-                .unwrap(),
+            resource_metadata: Some(
+                Url::parse("https://test.com/.well-known/oauth-protected-resource").unwrap(),
+            ),
             scope: Some("read write".to_string()),
             error: Some(BearerError::InsufficientScope),
             scope_mode: None,
+            dpop: None,
         };
 
         let encoded = encode_header(&header);
@@ -161,14 +218,82 @@ mod tests {
     #[test]
     fn encode_bearer_with_scope_mode() {
         let header = WwwAuthenticate::Bearer {
-            resource_metadata: Url::parse("https://test.com/.well-known/oauth-protected-resource")
-                .unwrap(),
+            resource_metadata: Some(
+                Url::parse("https://test.com/.well-known/oauth-protected-resource").unwrap(),
+            ),
             scope: Some("read write".to_string()),
             error: None,
             scope_mode: Some(ScopeMode::RequireAny),
+            dpop: None,
         };
 
         let encoded = encode_header(&header);
         assert!(encoded.contains(r#"scope_mode="require_any""#));
     }
+
+    #[test]
+    fn encode_bearer_without_resource_metadata() {
+        let header = WwwAuthenticate::Bearer {
+            resource_metadata: None,
+            scope: Some("read".to_string()),
+            error: None,
+            scope_mode: None,
+            dpop: None,
+        };
+
+        let encoded = encode_header(&header);
+        assert_eq!(encoded, r#"Bearer scope="read""#);
+    }
+
+    fn encode_all(header: &WwwAuthenticate) -> Vec<String> {
+        let mut values = Vec::new();
+        header.encode(&mut values);
+        values
+            .iter()
+            .map(|v| v.to_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn encode_dpop_alongside_bearer() {
+        let header = WwwAuthenticate::Bearer {
+            resource_metadata: None,
+            scope: Some("read".to_string()),
+            error: None,
+            scope_mode: None,
+            dpop: Some(DpopChallenge {
+                required: false,
+                invalid_proof: false,
+            }),
+        };
+
+        assert_eq!(
+            encode_all(&header),
+            [
+                r#"Bearer scope="read""#.to_string(),
+                format!(r#"DPoP scope="read", algs="{SUPPORTED_ALGS}""#),
+            ]
+        );
+    }
+
+    #[test]
+    fn encode_required_dpop_with_invalid_proof() {
+        let header = WwwAuthenticate::Bearer {
+            resource_metadata: None,
+            scope: None,
+            error: None,
+            scope_mode: None,
+            dpop: Some(DpopChallenge {
+                required: true,
+                invalid_proof: true,
+            }),
+        };
+
+        assert_eq!(
+            encode_all(&header),
+            [format!(
+                r#"DPoP error="invalid_dpop_proof", algs="{SUPPORTED_ALGS}""#
+            )]
+        );
+    }
 }
@@ -0,0 +1,345 @@
+//! Circuit breaker for requests to the upstream GraphQL endpoint
+//!
+//! When the endpoint keeps failing, the breaker opens and tool calls fail fast instead of waiting
+//! for another connection error. After the open duration elapses, a limited number of probe
+//! requests are let through (half-open); if they succeed, the breaker closes again.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::generated::telemetry::TelemetryMetric;
+use crate::meter;
+
+/// Circuit breaker configuration for the upstream GraphQL endpoint
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    /// Set to true to enable the circuit breaker
+    pub enabled: bool,
+
+    /// How many consecutive failures open the breaker (default: 5)
+    pub failure_threshold: u32,
+
+    /// How long the breaker stays open before probing the endpoint again (default: 30s)
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[serde(serialize_with = "humantime_serde::serialize")]
+    #[schemars(with = "String")]
+    pub open_duration: Duration,
+
+    /// How many probe requests must succeed while half-open before the breaker closes (default: 1)
+    pub half_open_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            half_open_probes: 1,
+        }
+    }
+}
+
+/// The state of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// The value recorded for the circuit breaker state gauge
+    fn as_gauge(&self) -> u64 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Inner {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { in_flight: u32, successes: u32 },
+}
+
+/// A call was rejected because the breaker is open
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitOpen {
+    /// How long until the breaker lets probe requests through, if known
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The GraphQL endpoint is unavailable: the circuit breaker is open after repeated upstream failures."
+        )?;
+        match self.retry_after {
+            Some(retry_after) => write!(
+                f,
+                " Retry in {}s.",
+                retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
+            ),
+            None => write!(f, " Retry shortly."),
+        }
+    }
+}
+
+/// Circuit breaker shared by every request to the upstream GraphQL endpoint
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        record_state(CircuitState::Closed);
+        Self {
+            config,
+            inner: Arc::new(Mutex::new(Inner::Closed { failures: 0 })),
+        }
+    }
+
+    /// The current state of the breaker.
+    ///
+    /// An open breaker whose open duration has elapsed is reported as half-open, since the next
+    /// call will be let through as a probe.
+    pub fn state(&self) -> CircuitState {
+        match &*self.inner.lock() {
+            Inner::Closed { .. } => CircuitState::Closed,
+            Inner::Open { until } if Instant::now() < *until => CircuitState::Open,
+            Inner::Open { .. } | Inner::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Ask the breaker for permission to send a request upstream.
+    ///
+    /// The returned permit must be resolved with [`Permit::success`] or [`Permit::failure`]
+    /// once the outcome of the request is known.
+    pub fn try_acquire(&self) -> Result<Permit<'_>, CircuitOpen> {
+        let mut inner = self.inner.lock();
+        match &mut *inner {
+            Inner::Closed { .. } => Ok(Permit::new(self, false)),
+            Inner::Open { until } => {
+                let now = Instant::now();
+                if now < *until {
+                    record_rejected();
+                    return Err(CircuitOpen {
+                        retry_after: Some(*until - now),
+                    });
+                }
+                info!("Circuit breaker half-open, probing the GraphQL endpoint");
+                *inner = Inner::HalfOpen {
+                    in_flight: 1,
+                    successes: 0,
+                };
+                record_state(CircuitState::HalfOpen);
+                Ok(Permit::new(self, true))
+            }
+            Inner::HalfOpen { in_flight, .. } => {
+                if *in_flight >= self.config.half_open_probes.max(1) {
+                    record_rejected();
+                    return Err(CircuitOpen { retry_after: None });
+                }
+                *in_flight += 1;
+                Ok(Permit::new(self, true))
+            }
+        }
+    }
+
+    fn on_success(&self, probe: bool) {
+        let mut inner = self.inner.lock();
+        match &mut *inner {
+            Inner::Closed { failures } => *failures = 0,
+            Inner::HalfOpen {
+                in_flight,
+                successes,
+            } if probe => {
+                *in_flight = in_flight.saturating_sub(1);
+                *successes += 1;
+                if *successes >= self.config.half_open_probes.max(1) {
+                    info!("Circuit breaker closed, the GraphQL endpoint has recovered");
+                    *inner = Inner::Closed { failures: 0 };
+                    record_state(CircuitState::Closed);
+                }
+            }
+            // Requests started before the breaker opened do not affect it
+            Inner::HalfOpen { .. } | Inner::Open { .. } => {}
+        }
+    }
+
+    fn on_failure(&self, probe: bool) {
+        let mut inner = self.inner.lock();
+        let open = match &mut *inner {
+            Inner::Closed { failures } => {
+                *failures += 1;
+                *failures >= self.config.failure_threshold.max(1)
+            }
+            Inner::HalfOpen { .. } => probe,
+            Inner::Open { .. } => false,
+        };
+        if open {
+            warn!(
+                open_duration = ?self.config.open_duration,
+                "Circuit breaker opened after repeated GraphQL endpoint failures"
+            );
+            *inner = Inner::Open {
+                until: Instant::now() + self.config.open_duration,
+            };
+            record_state(CircuitState::Open);
+        }
+    }
+
+    fn on_abandoned(&self, probe: bool) {
+        if let Inner::HalfOpen { in_flight, .. } = &mut *self.inner.lock()
+            && probe
+        {
+            *in_flight = in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// Permission to send one request upstream
+#[must_use]
+pub struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    resolved: bool,
+}
+
+impl<'a> Permit<'a> {
+    fn new(breaker: &'a CircuitBreaker, probe: bool) -> Self {
+        Self {
+            breaker,
+            probe,
+            resolved: false,
+        }
+    }
+
+    /// The endpoint responded
+    pub fn success(mut self) {
+        self.resolved = true;
+        self.breaker.on_success(self.probe);
+    }
+
+    /// The endpoint could not be reached or responded with a server error
+    pub fn failure(mut self) {
+        self.resolved = true;
+        self.breaker.on_failure(self.probe);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        // A cancelled request frees its probe slot without counting either way
+        if !self.resolved {
+            self.breaker.on_abandoned(self.probe);
+        }
+    }
+}
+
+fn record_state(state: CircuitState) {
+    meter::METER
+        .u64_gauge(TelemetryMetric::CircuitBreakerState.as_str())
+        .build()
+        .record(state.as_gauge(), &[]);
+}
+
+fn record_rejected() {
+    meter::METER
+        .u64_counter(TelemetryMetric::CircuitBreakerRejectedCount.as_str())
+        .build()
+        .add(1, &[]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(failure_threshold: u32, open_duration: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold,
+            open_duration,
+            half_open_probes: 1,
+        })
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker(2, Duration::from_secs(60));
+
+        breaker.try_acquire().unwrap().failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.try_acquire().unwrap().failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let rejection = breaker.try_acquire().err().unwrap();
+        assert!(rejection.retry_after.is_some());
+        assert!(rejection.to_string().contains("circuit breaker is open"));
+    }
+
+    #[test]
+    fn success_resets_failure_count() {
+        let breaker = breaker(2, Duration::from_secs(60));
+
+        breaker.try_acquire().unwrap().failure();
+        breaker.try_acquire().unwrap().success();
+        breaker.try_acquire().unwrap().failure();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_probe_success_closes() {
+        let breaker = breaker(1, Duration::ZERO);
+        breaker.try_acquire().unwrap().failure();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        let probe = breaker.try_acquire().unwrap();
+        // Only one probe at a time
+        assert_eq!(
+            breaker.try_acquire().err(),
+            Some(CircuitOpen { retry_after: None })
+        );
+        probe.success();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_probe_failure_reopens() {
+        let breaker = breaker(1, Duration::from_millis(10));
+        breaker.try_acquire().unwrap().failure();
+        std::thread::sleep(Duration::from_millis(20));
+
+        breaker.try_acquire().unwrap().failure();
+
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[test]
+    fn dropped_probe_frees_its_slot() {
+        let breaker = breaker(1, Duration::ZERO);
+        breaker.try_acquire().unwrap().failure();
+
+        drop(breaker.try_acquire().unwrap());
+
+        assert!(breaker.try_acquire().is_ok());
+    }
+}
@@ -0,0 +1,467 @@
+//! Concurrency limits for tools that call the GraphQL endpoint
+//!
+//! Calls beyond the configured number of in-flight calls wait in a bounded queue until a slot
+//! frees up or the queue timeout elapses.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use opentelemetry::KeyValue;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
+use crate::meter;
+
+/// Concurrency limits for tool calls
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    /// Maximum number of tool calls in flight across all tools
+    pub max_in_flight: Option<NonZeroUsize>,
+
+    /// Maximum number of calls in flight for each tool
+    pub max_in_flight_per_tool: Option<NonZeroUsize>,
+
+    /// Maximum number of calls in flight for specific tools, overriding `max_in_flight_per_tool`
+    pub tools: HashMap<String, NonZeroUsize>,
+
+    /// Maximum number of calls waiting for a slot of each limit (default: 100)
+    pub queue_size: usize,
+
+    /// How long a call waits for a slot before failing (default: 30s)
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[serde(serialize_with = "humantime_serde::serialize")]
+    #[schemars(with = "String")]
+    pub queue_timeout: Duration,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            max_in_flight_per_tool: None,
+            tools: HashMap::new(),
+            queue_size: 100,
+            queue_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A tool call could not get a slot
+#[derive(Debug, Clone, PartialEq)]
+pub enum Saturated {
+    /// The wait queue is full
+    QueueFull,
+    /// The call waited longer than the queue timeout
+    Timeout(Duration),
+}
+
+impl fmt::Display for Saturated {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Saturated::QueueFull => write!(
+                f,
+                "The server is at capacity: too many calls are already waiting. Retry later."
+            ),
+            Saturated::Timeout(waited) => write!(
+                f,
+                "The server is at capacity: no slot became available within {}s. Retry later.",
+                waited.as_secs()
+            ),
+        }
+    }
+}
+
+/// Slots held by an in-flight tool call, released on drop
+#[derive(Debug)]
+pub struct ConcurrencyPermit {
+    _tool: Option<OwnedSemaphorePermit>,
+    _global: Option<OwnedSemaphorePermit>,
+}
+
+/// A limit on in-flight calls and the calls waiting for one of its slots
+#[derive(Debug)]
+struct Limit {
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl Limit {
+    fn new(max: NonZeroUsize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max.get())),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Take a slot if one is free and no call is waiting for one
+    fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if self.queued.load(Ordering::SeqCst) != 0 {
+            return None;
+        }
+        self.semaphore.clone().try_acquire_owned().ok()
+    }
+}
+
+/// Concurrency limiter shared by every session of the server
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimiter {
+    config: Arc<ConcurrencyConfig>,
+    global: Option<Arc<Limit>>,
+    tools: Arc<Mutex<HashMap<String, Arc<Limit>>>>,
+}
+
+impl ConcurrencyLimiter {
+    /// Create a concurrency limiter, or `None` if no limits are configured
+    pub fn new(config: ConcurrencyConfig) -> Option<Self> {
+        if config.max_in_flight.is_none()
+            && config.max_in_flight_per_tool.is_none()
+            && config.tools.is_empty()
+        {
+            return None;
+        }
+        Some(Self {
+            global: config.max_in_flight.map(|max| Arc::new(Limit::new(max))),
+            config: Arc::new(config),
+            tools: Default::default(),
+        })
+    }
+
+    /// Wait for a slot to call `tool_name`, which must be the name of an existing tool.
+    ///
+    /// Calls get slots of each limit in the order they arrive, and each limit has its own wait
+    /// queue, so a saturated tool doesn't hold up calls to other tools. The returned permit holds
+    /// the slots until it is dropped.
+    pub async fn acquire(&self, tool_name: &str) -> Result<ConcurrencyPermit, Saturated> {
+        self.acquire_slots(tool_name, self.global.as_ref()).await
+    }
+
+    /// Wait for a slot to call `tool_name` from within another tool call, such as an operation
+    /// executed by a script tool. The outer call already holds a global slot, so only the tool's
+    /// own limit applies; waiting for another global slot could wait on the outer call itself.
+    pub async fn acquire_nested(&self, tool_name: &str) -> Result<ConcurrencyPermit, Saturated> {
+        self.acquire_slots(tool_name, None).await
+    }
+
+    async fn acquire_slots(
+        &self,
+        tool_name: &str,
+        global: Option<&Arc<Limit>>,
+    ) -> Result<ConcurrencyPermit, Saturated> {
+        let tool = self.tool_limit(tool_name);
+
+        // Fast path: slots are free and no call is waiting for them, so the call skips the queues
+        if let Some(permit) = try_acquire(tool.as_deref(), global.map(Arc::as_ref)) {
+            return Ok(permit);
+        }
+
+        let attributes = [KeyValue::new(
+            TelemetryAttribute::ToolName.to_key(),
+            tool_name.to_string(),
+        )];
+
+        let start = Instant::now();
+        let permit = tokio::time::timeout(self.config.queue_timeout, async {
+            // Wait for the tool slot first so a queued call does not hold a global slot
+            let tool = match &tool {
+                Some(limit) => self.wait(limit, &attributes).await?,
+                None => None,
+            };
+            let global = match global {
+                Some(limit) => self.wait(limit, &attributes).await?,
+                None => None,
+            };
+            Ok(ConcurrencyPermit {
+                _tool: tool,
+                _global: global,
+            })
+        })
+        .await;
+
+        meter::METER
+            .f64_histogram(TelemetryMetric::ConcurrencyWaitDuration.as_str())
+            .build()
+            .record(start.elapsed().as_millis() as f64, &attributes);
+
+        permit.map_err(|_| Saturated::Timeout(self.config.queue_timeout))?
+    }
+
+    /// Wait in the queue of `limit` for one of its slots
+    async fn wait(
+        &self,
+        limit: &Limit,
+        attributes: &[KeyValue],
+    ) -> Result<Option<OwnedSemaphorePermit>, Saturated> {
+        if let Some(permit) = limit.try_acquire() {
+            return Ok(Some(permit));
+        }
+        let _slot = QueueSlot::enter(limit, self.config.queue_size, attributes)?;
+        Ok(limit.semaphore.clone().acquire_owned().await.ok())
+    }
+
+    fn tool_limit(&self, tool_name: &str) -> Option<Arc<Limit>> {
+        let max = self
+            .config
+            .tools
+            .get(tool_name)
+            .or(self.config.max_in_flight_per_tool.as_ref())?;
+        Some(
+            self.tools
+                .lock()
+                .entry(tool_name.to_string())
+                .or_insert_with(|| Arc::new(Limit::new(*max)))
+                .clone(),
+        )
+    }
+}
+
+fn try_acquire(tool: Option<&Limit>, global: Option<&Limit>) -> Option<ConcurrencyPermit> {
+    let tool = match tool {
+        Some(limit) => Some(limit.try_acquire()?),
+        None => None,
+    };
+    let global = match global {
+        Some(limit) => Some(limit.try_acquire()?),
+        None => None,
+    };
+    Some(ConcurrencyPermit {
+        _tool: tool,
+        _global: global,
+    })
+}
+
+/// A call's place in the wait queue, given up when it is dropped so that cancelled calls don't
+/// leak queue slots
+struct QueueSlot<'a> {
+    queued: &'a AtomicUsize,
+    attributes: &'a [KeyValue],
+}
+
+impl<'a> QueueSlot<'a> {
+    fn enter(
+        limit: &'a Limit,
+        queue_size: usize,
+        attributes: &'a [KeyValue],
+    ) -> Result<Self, Saturated> {
+        let queued = limit.queued.fetch_add(1, Ordering::SeqCst);
+        if queued >= queue_size {
+            limit.queued.fetch_sub(1, Ordering::SeqCst);
+            return Err(Saturated::QueueFull);
+        }
+        queue_depth().add(1, attributes);
+        Ok(Self {
+            queued: &limit.queued,
+            attributes,
+        })
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        queue_depth().add(-1, self.attributes);
+    }
+}
+
+fn queue_depth() -> opentelemetry::metrics::UpDownCounter<i64> {
+    meter::METER
+        .i64_up_down_counter(TelemetryMetric::ConcurrencyQueueDepth.as_str())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(config: ConcurrencyConfig) -> ConcurrencyLimiter {
+        ConcurrencyLimiter::new(config).unwrap()
+    }
+
+    fn max(max: usize) -> NonZeroUsize {
+        NonZeroUsize::new(max).unwrap()
+    }
+
+    #[test]
+    fn rejects_zero_limits() {
+        for yaml in [
+            "max_in_flight: 0",
+            "max_in_flight_per_tool: 0",
+            "tools: { ExportReport: 0 }",
+        ] {
+            assert!(serde_yaml::from_str::<ConcurrencyConfig>(yaml).is_err());
+        }
+    }
+
+    #[test]
+    fn no_limiter_without_limits() {
+        assert!(ConcurrencyLimiter::new(ConcurrencyConfig::default()).is_none());
+    }
+
+    #[tokio::test]
+    async fn limits_calls_per_tool() {
+        let limiter = limiter(ConcurrencyConfig {
+            tools: HashMap::from([("ExportReport".to_string(), max(1))]),
+            queue_timeout: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        let _held = limiter.acquire("ExportReport").await.unwrap();
+
+        assert_eq!(
+            limiter.acquire("ExportReport").await.unwrap_err(),
+            Saturated::Timeout(Duration::from_millis(20))
+        );
+        assert!(limiter.acquire("GetUser").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn limits_calls_globally() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight: Some(max(1)),
+            queue_timeout: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        let held = limiter.acquire("ExportReport").await.unwrap();
+        assert!(limiter.acquire("GetUser").await.is_err());
+
+        drop(held);
+        assert!(limiter.acquire("GetUser").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn nested_calls_only_take_tool_slots() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight: Some(max(1)),
+            tools: HashMap::from([("GetUser".to_string(), max(1))]),
+            queue_timeout: Duration::from_millis(20),
+            ..Default::default()
+        });
+
+        let _outer = limiter.acquire("ScriptTool").await.unwrap();
+        let _nested = limiter.acquire_nested("GetUser").await.unwrap();
+
+        assert!(limiter.acquire_nested("GetUser").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn queued_call_runs_when_slot_frees_up() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight_per_tool: Some(max(1)),
+            ..Default::default()
+        });
+
+        let held = limiter.acquire("ExportReport").await.unwrap();
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("ExportReport").await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(held);
+
+        assert!(waiting.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_calls_when_queue_is_full() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight: Some(max(1)),
+            queue_size: 0,
+            ..Default::default()
+        });
+
+        let _held = limiter.acquire("ExportReport").await.unwrap();
+
+        assert_eq!(
+            limiter.acquire("ExportReport").await.unwrap_err(),
+            Saturated::QueueFull
+        );
+    }
+
+    #[tokio::test]
+    async fn full_queue_of_one_tool_does_not_block_other_tools() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight_per_tool: Some(max(1)),
+            queue_size: 1,
+            ..Default::default()
+        });
+
+        let _held = limiter.acquire("ExportReport").await.unwrap();
+        let _waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("ExportReport").await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            limiter.acquire("ExportReport").await.unwrap_err(),
+            Saturated::QueueFull
+        );
+
+        let other =
+            tokio::time::timeout(Duration::from_millis(10), limiter.acquire("GetUser")).await;
+        assert!(other.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn cancelled_calls_leave_the_queue() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight: Some(max(1)),
+            queue_size: 1,
+            ..Default::default()
+        });
+
+        let held = limiter.acquire("ExportReport").await.unwrap();
+        let cancelled =
+            tokio::time::timeout(Duration::from_millis(10), limiter.acquire("ExportReport")).await;
+        assert!(cancelled.is_err());
+        assert_eq!(
+            limiter
+                .global
+                .as_ref()
+                .unwrap()
+                .queued
+                .load(Ordering::SeqCst),
+            0
+        );
+
+        drop(held);
+        assert!(limiter.acquire("ExportReport").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn new_calls_do_not_skip_the_queue() {
+        let limiter = limiter(ConcurrencyConfig {
+            max_in_flight: Some(max(1)),
+            ..Default::default()
+        });
+
+        let held = limiter.acquire("ExportReport").await.unwrap();
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("ExportReport").await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // The slot goes to the queued call, so a new call has to wait behind it
+        drop(held);
+        let queued = waiting.await.unwrap().unwrap();
+        let late =
+            tokio::time::timeout(Duration::from_millis(10), limiter.acquire("ExportReport")).await;
+        assert!(late.is_err());
+
+        drop(queued);
+        assert!(limiter.acquire("ExportReport").await.is_ok());
+    }
+}
@@ -0,0 +1,393 @@
+//! GraphQL error mapping
+//!
+//! Classifies the errors returned by the GraphQL endpoint by their `extensions.code` and by
+//! the HTTP status of the response, so that tool results can carry a concise, model-facing
+//! summary with a suggested next action instead of a raw `errors` array.
+
+use std::collections::{BTreeMap, HashMap};
+
+use reqwest::StatusCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+/// The class of a GraphQL error, used to pick a remediation hint
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The request is missing valid credentials
+    Unauthenticated,
+    /// The caller is not allowed to perform the request
+    Forbidden,
+    /// The request arguments or operation were rejected
+    BadUserInput,
+    /// The upstream does not know the persisted query
+    PersistedQueryNotFound,
+    /// The upstream is throttling requests
+    RateLimited,
+    /// The upstream failed while processing the request
+    Internal,
+    /// Any error that does not match a known class
+    Other,
+}
+
+impl ErrorClass {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ErrorClass::Unauthenticated => "unauthenticated",
+            ErrorClass::Forbidden => "forbidden",
+            ErrorClass::BadUserInput => "bad_user_input",
+            ErrorClass::PersistedQueryNotFound => "persisted_query_not_found",
+            ErrorClass::RateLimited => "rate_limited",
+            ErrorClass::Internal => "internal",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    const fn default_hint(&self) -> &'static str {
+        match self {
+            ErrorClass::Unauthenticated => {
+                "The request is not authenticated. Ask the user to sign in or refresh their credentials, then retry."
+            }
+            ErrorClass::Forbidden => {
+                "The caller is not allowed to access this data. Do not retry; tell the user which data was denied."
+            }
+            ErrorClass::BadUserInput => {
+                "The arguments were rejected. Fix them using the error messages and the tool's input schema, then retry."
+            }
+            ErrorClass::PersistedQueryNotFound => {
+                "The GraphQL endpoint does not recognize this operation. Do not retry until the operation list is refreshed."
+            }
+            ErrorClass::RateLimited => {
+                "The GraphQL endpoint is rate limiting requests. Wait before retrying and reduce the number of calls."
+            }
+            ErrorClass::Internal => {
+                "The GraphQL endpoint failed while processing the request. Retrying later may succeed."
+            }
+            ErrorClass::Other => "Review the error messages before retrying.",
+        }
+    }
+
+    fn from_default_code(code: &str) -> Option<Self> {
+        match code {
+            "UNAUTHENTICATED" => Some(ErrorClass::Unauthenticated),
+            "FORBIDDEN" => Some(ErrorClass::Forbidden),
+            "BAD_USER_INPUT" | "GRAPHQL_VALIDATION_FAILED" | "GRAPHQL_PARSE_FAILED" => {
+                Some(ErrorClass::BadUserInput)
+            }
+            "PERSISTED_QUERY_NOT_FOUND" | "PERSISTED_QUERY_NOT_SUPPORTED" => {
+                Some(ErrorClass::PersistedQueryNotFound)
+            }
+            "RATE_LIMITED" | "RATE_LIMIT_EXCEEDED" | "TOO_MANY_REQUESTS" | "THROTTLED" => {
+                Some(ErrorClass::RateLimited)
+            }
+            "INTERNAL_SERVER_ERROR" => Some(ErrorClass::Internal),
+            _ => None,
+        }
+    }
+
+    fn from_default_status(status: StatusCode) -> Option<Self> {
+        match status {
+            StatusCode::UNAUTHORIZED => Some(ErrorClass::Unauthenticated),
+            StatusCode::FORBIDDEN => Some(ErrorClass::Forbidden),
+            StatusCode::BAD_REQUEST => Some(ErrorClass::BadUserInput),
+            StatusCode::TOO_MANY_REQUESTS => Some(ErrorClass::RateLimited),
+            status if status.is_server_error() => Some(ErrorClass::Internal),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for classifying GraphQL errors.
+///
+/// The built-in mappings cover the common Apollo error codes and HTTP statuses; entries
+/// here are consulted first, so they can both add new mappings and replace built-in ones.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct ErrorMappingConfig {
+    /// Map from GraphQL error `extensions.code` values to error classes
+    pub codes: HashMap<String, ErrorClass>,
+
+    /// Map from HTTP status codes of the GraphQL response to error classes
+    pub statuses: HashMap<u16, ErrorClass>,
+
+    /// Remediation hints that replace the built-in hint for an error class
+    pub hints: HashMap<ErrorClass, String>,
+}
+
+impl ErrorMappingConfig {
+    fn class_for_code(&self, code: &str) -> Option<ErrorClass> {
+        self.codes
+            .get(code)
+            .copied()
+            .or_else(|| ErrorClass::from_default_code(code))
+    }
+
+    fn class_for_status(&self, status: StatusCode) -> Option<ErrorClass> {
+        self.statuses
+            .get(&status.as_u16())
+            .copied()
+            .or_else(|| ErrorClass::from_default_status(status))
+    }
+
+    fn hint(&self, class: ErrorClass) -> &str {
+        self.hints
+            .get(&class)
+            .map(String::as_str)
+            .unwrap_or_else(|| class.default_hint())
+    }
+
+    /// Classify the errors in a GraphQL response.
+    ///
+    /// Returns `None` when the response has no errors and the HTTP status is a success.
+    pub(crate) fn classify(&self, response: &Value, status: StatusCode) -> Option<ErrorSummary> {
+        let errors = response
+            .get("errors")
+            .and_then(Value::as_array)
+            .filter(|errors| !errors.is_empty());
+        let status_class = (!status.is_success())
+            .then(|| self.class_for_status(status))
+            .flatten();
+
+        let mut classes = BTreeMap::new();
+        match errors {
+            Some(errors) => {
+                for error in errors {
+                    let class = error
+                        .get("extensions")
+                        .and_then(|extensions| extensions.get("code"))
+                        .and_then(Value::as_str)
+                        .and_then(|code| self.class_for_code(code))
+                        .or(status_class)
+                        .unwrap_or(ErrorClass::Other);
+                    *classes.entry(class).or_insert(0) += 1;
+                }
+            }
+            None if !status.is_success() => {
+                classes.insert(status_class.unwrap_or(ErrorClass::Other), 1);
+            }
+            None => return None,
+        }
+
+        let has_data = response
+            .get("data")
+            .is_some_and(|data| data.as_object().is_some_and(|data| !data.is_empty()));
+
+        Some(ErrorSummary {
+            outcome: if has_data {
+                ErrorOutcome::PartialData
+            } else {
+                ErrorOutcome::Failure
+            },
+            hints: classes
+                .keys()
+                .map(|class| (*class, self.hint(*class).to_string()))
+                .collect(),
+            classes,
+        })
+    }
+}
+
+/// Whether any data was returned alongside the errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ErrorOutcome {
+    /// Some data was returned alongside the errors
+    PartialData,
+    /// No data was returned
+    Failure,
+}
+
+impl ErrorOutcome {
+    pub(crate) const fn as_str(&self) -> &'static str {
+        match self {
+            ErrorOutcome::PartialData => "partial_data",
+            ErrorOutcome::Failure => "failure",
+        }
+    }
+}
+
+/// The classified errors of a single GraphQL response
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ErrorSummary {
+    pub(crate) outcome: ErrorOutcome,
+    pub(crate) classes: BTreeMap<ErrorClass, usize>,
+    hints: BTreeMap<ErrorClass, String>,
+}
+
+impl ErrorSummary {
+    /// The class with the most errors, preferring the more specific class on a tie
+    pub(crate) fn primary_class(&self) -> Option<ErrorClass> {
+        self.classes
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map(|(class, _)| *class)
+    }
+
+    /// A concise, model-facing description of the errors and what to do next
+    pub(crate) fn message(&self) -> String {
+        let total: usize = self.classes.values().sum();
+        let counts = self
+            .classes
+            .iter()
+            .map(|(class, count)| format!("{count} {}", class.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let plural = if total == 1 { "" } else { "s" };
+        let headline = match self.outcome {
+            ErrorOutcome::PartialData => {
+                format!(
+                    "The operation returned partial data with {total} error{plural} ({counts})."
+                )
+            }
+            ErrorOutcome::Failure => {
+                format!("The operation failed with {total} error{plural} ({counts}).")
+            }
+        };
+
+        std::iter::once(headline)
+            .chain(
+                self.hints
+                    .iter()
+                    .map(|(class, hint)| format!("- {}: {hint}", class.as_str())),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A machine-readable form of the summary, for the tool result metadata
+    pub(crate) fn to_meta(&self) -> Value {
+        json!({
+            "outcome": self.outcome,
+            "classes": self
+                .classes
+                .iter()
+                .map(|(class, count)| (class.as_str().to_string(), Value::from(*count)))
+                .collect::<serde_json::Map<_, _>>(),
+            "message": self.message(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors_by_extension_code() {
+        let response = json!({
+            "data": null,
+            "errors": [
+                { "message": "no", "extensions": { "code": "UNAUTHENTICATED" } },
+                { "message": "bad", "extensions": { "code": "BAD_USER_INPUT" } },
+                { "message": "bad", "extensions": { "code": "BAD_USER_INPUT" } },
+                { "message": "?" }
+            ]
+        });
+
+        let summary = ErrorMappingConfig::default()
+            .classify(&response, StatusCode::OK)
+            .unwrap();
+
+        assert_eq!(summary.outcome, ErrorOutcome::Failure);
+        assert_eq!(
+            summary.classes,
+            BTreeMap::from([
+                (ErrorClass::Unauthenticated, 1),
+                (ErrorClass::BadUserInput, 2),
+                (ErrorClass::Other, 1),
+            ])
+        );
+        assert_eq!(summary.primary_class(), Some(ErrorClass::BadUserInput));
+    }
+
+    #[test]
+    fn partial_data_is_distinguished_from_failure() {
+        let response = json!({
+            "data": { "user": null },
+            "errors": [{ "message": "no", "extensions": { "code": "FORBIDDEN" } }]
+        });
+
+        let summary = ErrorMappingConfig::default()
+            .classify(&response, StatusCode::OK)
+            .unwrap();
+
+        assert_eq!(summary.outcome, ErrorOutcome::PartialData);
+        assert!(
+            summary
+                .message()
+                .starts_with("The operation returned partial data with 1 error (1 forbidden).")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_http_status() {
+        let response = json!({ "errors": [{ "message": "slow down" }] });
+
+        let summary = ErrorMappingConfig::default()
+            .classify(&response, StatusCode::TOO_MANY_REQUESTS)
+            .unwrap();
+
+        assert_eq!(
+            summary.classes,
+            BTreeMap::from([(ErrorClass::RateLimited, 1)])
+        );
+    }
+
+    #[test]
+    fn classifies_error_status_without_errors() {
+        let summary = ErrorMappingConfig::default()
+            .classify(&Value::Null, StatusCode::BAD_GATEWAY)
+            .unwrap();
+
+        assert_eq!(summary.classes, BTreeMap::from([(ErrorClass::Internal, 1)]));
+        assert_eq!(summary.outcome, ErrorOutcome::Failure);
+    }
+
+    #[test]
+    fn no_summary_for_successful_responses() {
+        let response = json!({ "data": { "user": { "id": "1" } } });
+
+        assert_eq!(
+            ErrorMappingConfig::default().classify(&response, StatusCode::OK),
+            None
+        );
+    }
+
+    #[test]
+    fn configured_mappings_take_precedence() {
+        let config: ErrorMappingConfig = serde_json::from_value(json!({
+            "codes": { "UNAUTHENTICATED": "forbidden", "QUOTA_EXCEEDED": "rate_limited" },
+            "statuses": { "418": "internal" },
+            "hints": { "rate_limited": "Try again tomorrow." }
+        }))
+        .unwrap();
+        let response = json!({
+            "errors": [
+                { "message": "no", "extensions": { "code": "UNAUTHENTICATED" } },
+                { "message": "quota", "extensions": { "code": "QUOTA_EXCEEDED" } },
+                { "message": "teapot" }
+            ]
+        });
+
+        let summary = config.classify(&response, StatusCode::IM_A_TEAPOT).unwrap();
+
+        assert_eq!(
+            summary.classes,
+            BTreeMap::from([
+                (ErrorClass::Forbidden, 1),
+                (ErrorClass::RateLimited, 1),
+                (ErrorClass::Internal, 1),
+            ])
+        );
+        assert_eq!(summary.primary_class(), Some(ErrorClass::Forbidden));
+        assert!(
+            summary
+                .message()
+                .contains("- rate_limited: Try again tomorrow.")
+        );
+    }
+}
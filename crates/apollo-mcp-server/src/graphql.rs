@@ -1,11 +1,16 @@
 //! Execute GraphQL operations from an MCP tool
 
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock};
 
+use crate::auth::{TokenExchanger, UpstreamAuth};
+use crate::circuit_breaker::CircuitBreaker;
+use crate::error_mapping::ErrorMappingConfig;
 use crate::errors::McpError;
 use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
 use crate::meter;
 use crate::operations::private_fields::{PrivateFieldTree, filter_private_fields};
+use apollo_mcp_rhai::RhaiEngine;
+use apollo_mcp_rhai::checkpoints::{self, GraphqlResponse};
 use opentelemetry::KeyValue;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware, Extension};
@@ -19,9 +24,35 @@ pub struct Request<'a> {
     pub input: Value,
     pub endpoint: &'a Url,
     pub headers: &'a HeaderMap,
+    pub upstream: &'a Upstream,
+    pub response_hook: Option<ResponseHook<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Runs the `on_graphql_response` Rhai hook on the response to a tool's operation
+#[derive(Clone, Copy)]
+pub struct ResponseHook<'a> {
+    pub rhai_engine: &'a Arc<RhaiEngine>,
+    pub tool_name: &'a str,
+}
+
+impl std::fmt::Debug for ResponseHook<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseHook")
+            .field("tool_name", &self.tool_name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Server-wide policies applied to every request sent to the GraphQL endpoint
+#[derive(Debug, Clone, Default)]
+pub struct Upstream {
+    pub error_mapping: ErrorMappingConfig,
+    pub circuit_breaker: Option<CircuitBreaker>,
+    pub token_exchange: Option<TokenExchanger>,
+    pub upstream_auth: Option<UpstreamAuth>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct OperationDetails {
     pub query: String,
     pub operation_name: Option<String>,
@@ -40,7 +71,7 @@ static GRAPHQL_CLIENT: LazyLock<ClientWithMiddleware> = LazyLock::new(|| {
         .build()
 });
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ValidationError(pub String);
 
 /// Able to be executed as a GraphQL operation
@@ -98,27 +129,78 @@ pub trait Executable {
             request_body.insert(String::from("operationName"), Value::String(op_name));
         }
 
+        // Fail fast while the endpoint is known to be unavailable
+        let permit = match request
+            .upstream
+            .circuit_breaker
+            .as_ref()
+            .map(CircuitBreaker::try_acquire)
+            .transpose()
+        {
+            Ok(permit) => permit,
+            Err(open) => {
+                return Ok(CallToolResult::error(vec![Content::text(open.to_string())]));
+            }
+        };
+
+        let request_body = Value::Object(request_body);
         let response = match GRAPHQL_CLIENT
             .post(request.endpoint.as_str())
             .headers(self.headers(request.headers))
-            .body(Value::Object(request_body).to_string())
+            .body(request_body.to_string())
             .send()
             .await
         {
             Ok(resp) => resp,
             Err(e) => {
+                if let Some(permit) = permit {
+                    permit.failure();
+                }
                 return Ok(CallToolResult::error(vec![Content::text(format!(
                     "Failed to send GraphQL request: {e}"
                 ))]));
             }
         };
 
-        let result = match response.json::<Value>().await {
-            Ok(json) => {
+        let status = response.status();
+        if let Some(permit) = permit {
+            if status.is_server_error() {
+                permit.failure();
+            } else {
+                permit.success();
+            }
+        }
+        let response_headers = response.headers().clone();
+        // Let scripts transform the response before it is classified and filtered
+        let body = response
+            .json::<Value>()
+            .await
+            .map(|json| match request.response_hook {
+                Some(hook) => checkpoints::on_graphql_response(
+                    hook.rhai_engine,
+                    hook.tool_name,
+                    request_body.get("variables").unwrap_or(&Value::Null),
+                    status,
+                    &response_headers,
+                    json,
+                ),
+                None => Ok(GraphqlResponse::from(json)),
+            });
+        let (result, error_summary) = match body {
+            Ok(Err(hook_error)) => (Err(hook_error), None),
+            Ok(Ok(GraphqlResponse {
+                body: json,
+                content: script_content,
+                is_error: script_error,
+            })) => {
+                let error_summary = request.upstream.error_mapping.classify(&json, status);
                 let is_error = Some(
-                    json.get("errors")
-                        .filter(|value| !matches!(value, Value::Null))
-                        .is_some(),
+                    script_error
+                        || error_summary.is_some()
+                        || json
+                            .get("errors")
+                            .filter(|value| !matches!(value, Value::Null))
+                            .is_some(),
                 );
 
                 // When the operation has @private fields, split the response:
@@ -138,30 +220,67 @@ pub trait Executable {
                     tracing::Span::current().record("apollo.mcp.graphql_response", s.as_str());
                 }
 
-                let result = if is_error == Some(true) {
+                let mut result = if is_error == Some(true) {
                     CallToolResult::structured_error(structured_content)
                 } else {
                     CallToolResult::structured(structured_content)
                 };
-                Ok(result.with_meta(meta))
+                result
+                    .content
+                    .extend(script_content.into_iter().map(Content::text));
+
+                // Give the model a short interpretation of the errors with a next step
+                let meta = match error_summary.as_ref() {
+                    Some(summary) => {
+                        result.content.push(Content::text(summary.message()));
+                        let mut meta = meta.unwrap_or_default();
+                        meta.insert("graphqlErrors".into(), summary.to_meta());
+                        Some(meta)
+                    }
+                    None => meta,
+                };
+                (Ok(result.with_meta(meta)), error_summary)
+            }
+            Err(e) => {
+                let error_summary = request
+                    .upstream
+                    .error_mapping
+                    .classify(&Value::Null, status);
+                let mut content = vec![Content::text(format!(
+                    "Failed to read GraphQL response body: {e}"
+                ))];
+                if let Some(summary) = error_summary.as_ref() {
+                    content.push(Content::text(summary.message()));
+                }
+                (Ok(CallToolResult::error(content)), error_summary)
             }
-            Err(e) => Ok(CallToolResult::error(vec![Content::text(format!(
-                "Failed to read GraphQL response body: {e}"
-            ))])),
         };
 
         // Record response metrics
-        let attributes = vec![
+        let operation_id = KeyValue::new(
+            TelemetryAttribute::OperationId.to_key(),
+            op_id.unwrap_or_default(),
+        );
+        let mut attributes = vec![
             KeyValue::new(
                 TelemetryAttribute::Success.to_key(),
                 result.as_ref().is_ok_and(|r| r.is_error != Some(true)),
             ),
-            KeyValue::new(
-                TelemetryAttribute::OperationId.to_key(),
-                op_id.unwrap_or_default(),
-            ),
+            operation_id.clone(),
             KeyValue::new(TelemetryAttribute::OperationSource.to_key(), "operation"),
         ];
+        if let Some(summary) = error_summary.as_ref() {
+            attributes.push(KeyValue::new(
+                TelemetryAttribute::GraphqlOutcome.to_key(),
+                summary.outcome.as_str(),
+            ));
+            if let Some(class) = summary.primary_class() {
+                attributes.push(KeyValue::new(
+                    TelemetryAttribute::GraphqlErrorClass.to_key(),
+                    class.as_str(),
+                ));
+            }
+        }
         meter
             .f64_histogram(TelemetryMetric::OperationDuration.as_str())
             .build()
@@ -170,6 +289,24 @@ pub trait Executable {
             .u64_counter(TelemetryMetric::OperationCount.as_str())
             .build()
             .add(1, &attributes);
+        // Count errors per class with a bounded attribute rather than one series per count
+        if let Some(summary) = error_summary.as_ref() {
+            let errors = meter
+                .u64_counter(TelemetryMetric::OperationErrorsCount.as_str())
+                .build();
+            for (class, count) in &summary.classes {
+                errors.add(
+                    *count as u64,
+                    &[
+                        operation_id.clone(),
+                        KeyValue::new(
+                            TelemetryAttribute::GraphqlErrorClass.to_key(),
+                            class.as_str(),
+                        ),
+                    ],
+                );
+            }
+        }
 
         result
     }
@@ -177,8 +314,11 @@ pub trait Executable {
 
 #[cfg(test)]
 mod test {
+    use crate::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig};
     use crate::generated::telemetry::TelemetryMetric;
-    use crate::graphql::{Executable, OperationDetails, Request, ValidationError};
+    use crate::graphql::{
+        Executable, OperationDetails, Request, ResponseHook, Upstream, ValidationError,
+    };
     use crate::operations::private_fields::process_private_directives;
     use http::{HeaderMap, HeaderValue};
     use opentelemetry::global;
@@ -260,6 +400,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
         let expected_request_body = json!({
             "variables": { "arg1": "foobar" },
@@ -302,6 +444,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
 
         // when
@@ -318,6 +462,61 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn fails_fast_while_circuit_breaker_is_open() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let upstream = Upstream {
+            circuit_breaker: Some(CircuitBreaker::new(CircuitBreakerConfig {
+                enabled: true,
+                failure_threshold: 2,
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        let mock = server
+            .mock("POST", "/")
+            .with_status(503)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "data": null }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        // when
+        for _ in 0..2 {
+            TestExecutable {}
+                .execute(Request {
+                    input: json!({}),
+                    endpoint: &url,
+                    headers: &HeaderMap::new(),
+                    upstream: &upstream,
+                    response_hook: None,
+                })
+                .await
+                .unwrap();
+        }
+        let result = TestExecutable {}
+            .execute(Request {
+                input: json!({}),
+                endpoint: &url,
+                headers: &HeaderMap::new(),
+                upstream: &upstream,
+                response_hook: None,
+            })
+            .await
+            .unwrap();
+
+        // then
+        mock.assert(); // the third call never reached the endpoint
+        assert_eq!(result.is_error, Some(true));
+        let RawContent::Text(text) = &result.content[0].raw else {
+            panic!("Expected text content");
+        };
+        assert!(text.text.contains("circuit breaker is open"));
+    }
+
     #[tokio::test]
     async fn returns_tool_error_when_json_body_cannot_be_parsed() {
         // given
@@ -327,6 +526,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
 
         server
@@ -364,6 +565,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
 
         server
@@ -384,6 +587,62 @@ mod test {
         assert!(result.is_error.unwrap());
     }
 
+    #[tokio::test]
+    async fn response_hook_transforms_result() {
+        // given
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r#"fn on_graphql_response(ctx) {
+                ctx.body.data.mockOp.email = "[redacted]";
+                ctx.content.push(`${ctx.tool_name} returned ${ctx.status} for ${ctx.variables.arg1}`);
+                ctx.is_error = true;
+            }"#,
+        )
+        .unwrap();
+        let engine = apollo_mcp_rhai::RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+        let engine = Arc::new(engine);
+
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: Some(ResponseHook {
+                rhai_engine: &engine,
+                tool_name: "MockOp",
+            }),
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "data": { "mockOp": { "email": "ada@example.com" } } }).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let result = TestExecutable.execute(mock_request).await.unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.structured_content,
+            Some(json!({ "data": { "mockOp": { "email": "[redacted]" } } }))
+        );
+        let texts: Vec<_> = result
+            .content
+            .iter()
+            .filter_map(|content| content.as_text().map(|text| text.text.as_str()))
+            .collect();
+        assert!(texts.contains(&"MockOp returned 200 for foobar"));
+    }
+
     #[tokio::test]
     async fn gql_response_with_errors_and_partial_data_is_flagged_as_error() {
         // given
@@ -393,6 +652,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
 
         // Partial success: resolver failed but `data` is a non-null object.
@@ -417,6 +678,92 @@ mod test {
         assert!(result.is_error == Some(true));
     }
 
+    #[tokio::test]
+    async fn gql_response_errors_are_summarized_with_remediation_hints() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "data": { "user": { "id": "1", "email": null } },
+                    "errors": [{ "message": "denied", "extensions": { "code": "FORBIDDEN" } }]
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutable {};
+        let result = test_executable.execute(mock_request).await.unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(true));
+        let summary = result
+            .content
+            .iter()
+            .filter_map(|content| content.raw.as_text())
+            .map(|text| text.text.as_str())
+            .find(|text| text.starts_with("The operation returned partial data"))
+            .expect("summary should be part of the content");
+        assert!(summary.contains("1 forbidden"));
+
+        let meta = result.meta.expect("meta should be set");
+        assert_eq!(
+            meta.get("graphqlErrors")
+                .and_then(|summary| summary.get("outcome")),
+            Some(&json!("partial_data"))
+        );
+    }
+
+    #[tokio::test]
+    async fn http_error_status_without_json_body_is_summarized() {
+        // given
+        let mut server = mockito::Server::new_async().await;
+        let url = Url::parse(server.url().as_str()).unwrap();
+        let mock_request = Request {
+            input: json!({}),
+            endpoint: &url,
+            headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
+        };
+
+        server
+            .mock("POST", "/")
+            .with_status(429)
+            .with_body("Too Many Requests")
+            .expect(1)
+            .create_async()
+            .await;
+
+        // when
+        let test_executable = TestExecutable {};
+        let result = test_executable.execute(mock_request).await.unwrap();
+
+        // then
+        assert_eq!(result.is_error, Some(true));
+        assert!(result.content.iter().any(|content| {
+            content
+                .raw
+                .as_text()
+                .is_some_and(|text| text.text.contains("1 rate_limited"))
+        }));
+    }
+
     #[tokio::test]
     async fn span_does_not_record_private_fields_in_graphql_response() {
         use crate::operations::private_fields::PrivateFieldTree;
@@ -460,6 +807,8 @@ mod test {
                 input: json!({}),
                 endpoint: &url,
                 headers: &HeaderMap::new(),
+                upstream: &Upstream::default(),
+                response_hook: None,
             })
             .await
             .unwrap();
@@ -489,6 +838,8 @@ mod test {
             input: json!({}),
             endpoint: &url,
             headers: &HeaderMap::new(),
+            upstream: &Upstream::default(),
+            response_hook: None,
         };
 
         server
@@ -6,7 +6,7 @@ use http::Extensions;
 use reqwest::header::{HeaderMap, HeaderName};
 use tracing::warn;
 
-use crate::auth::ValidToken;
+use crate::auth::{ClaimHeadersConfig, ValidToken};
 
 /// List of header names to forward from MCP clients to GraphQL API
 pub type ForwardHeaders = Vec<String>;
@@ -18,6 +18,7 @@ pub fn build_request_headers(
     incoming_headers: &HeaderMap,
     extensions: &Extensions,
     disable_auth_token_passthrough: bool,
+    claim_headers: &ClaimHeadersConfig,
 ) -> HeaderMap {
     // Starts with static headers
     let mut headers = static_headers.clone();
@@ -25,11 +26,19 @@ pub fn build_request_headers(
     // Forward headers dynamically
     forward_headers(forward_header_names, incoming_headers, &mut headers);
 
-    // Optionally extract the validated token and propagate it to upstream servers if present
-    if !disable_auth_token_passthrough && let Some(token) = extensions.get::<ValidToken>() {
+    // Optionally extract the validated token and propagate it to upstream servers if present.
+    // API keys authenticate clients to this server only, so they are never passed through.
+    let token = extensions.get::<ValidToken>();
+    if !disable_auth_token_passthrough
+        && let Some(token) = token
+        && !token.api_key
+    {
         headers.typed_insert(token.deref().clone());
     }
 
+    // Set headers derived from the token's claims, replacing any forwarded from the client
+    claim_headers.apply(token, &mut headers);
+
     // Forward the mcp-session-id header if present
     if let Some(session_id) = incoming_headers.get("mcp-session-id") {
         headers.insert("mcp-session-id", session_id.clone());
@@ -101,6 +110,7 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 false,
+                &ClaimHeadersConfig::default(),
             );
 
             assert_eq!(result.get("x-api-key").unwrap(), "static-key");
@@ -125,6 +135,7 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 false,
+                &ClaimHeadersConfig::default(),
             );
 
             assert_eq!(result.get("x-tenant-id").unwrap(), "tenant-123");
@@ -141,7 +152,10 @@ mod tests {
             let mut extensions = Extensions::new();
             let token = ValidToken {
                 token: Authorization::bearer("test-token").unwrap(),
+                subject: "test-user".to_string(),
                 scopes: vec![],
+                claims: Default::default(),
+                api_key: false,
             };
             extensions.insert(token);
 
@@ -151,6 +165,7 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 false,
+                &ClaimHeadersConfig::default(),
             );
 
             assert!(result.get("authorization").is_some());
@@ -166,7 +181,10 @@ mod tests {
             let mut extensions = Extensions::new();
             let token = ValidToken {
                 token: Authorization::bearer("test-token").unwrap(),
+                subject: "test-user".to_string(),
                 scopes: vec![],
+                claims: Default::default(),
+                api_key: false,
             };
             extensions.insert(token);
 
@@ -176,6 +194,30 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 true,
+                &ClaimHeadersConfig::default(),
+            );
+
+            assert!(result.get("authorization").is_none());
+        }
+
+        #[test]
+        fn never_passes_api_key_through() {
+            let mut extensions = Extensions::new();
+            extensions.insert(ValidToken {
+                token: Authorization::bearer("server-api-key").unwrap(),
+                subject: "ci".to_string(),
+                scopes: vec![],
+                claims: Default::default(),
+                api_key: true,
+            });
+
+            let result = super::super::build_request_headers(
+                &HeaderMap::new(),
+                &vec![],
+                &HeaderMap::new(),
+                &extensions,
+                false,
+                &ClaimHeadersConfig::default(),
             );
 
             assert!(result.get("authorization").is_none());
@@ -197,6 +239,7 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 false,
+                &ClaimHeadersConfig::default(),
             );
 
             assert_eq!(result.get("mcp-session-id").unwrap(), "session-123");
@@ -219,7 +262,10 @@ mod tests {
             let mut extensions = Extensions::new();
             let token = ValidToken {
                 token: Authorization::bearer("oauth-token").unwrap(),
+                subject: "test-user".to_string(),
                 scopes: vec![],
+                claims: Default::default(),
+                api_key: false,
             };
             extensions.insert(token);
 
@@ -229,6 +275,7 @@ mod tests {
                 &incoming_headers,
                 &extensions,
                 false,
+                &ClaimHeadersConfig::default(),
             )
         }
 
@@ -26,6 +26,8 @@ use serde_json::json;
 use tokio::time::Instant;
 use tracing::{debug, trace};
 
+use crate::circuit_breaker::{CircuitBreaker, CircuitState};
+
 /// Health status enumeration
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
@@ -121,6 +123,7 @@ pub struct HealthCheck {
     live: Arc<AtomicBool>,
     ready: Arc<AtomicBool>,
     rejected: Arc<AtomicUsize>,
+    circuit_breaker: Option<CircuitBreaker>,
     ticker: Arc<tokio::task::JoinHandle<()>>,
 }
 
@@ -165,10 +168,25 @@ impl HealthCheck {
             live,
             ready,
             rejected,
+            circuit_breaker: None,
             ticker: Arc::new(ticker),
         }
     }
 
+    /// Report unready while the upstream circuit breaker is open
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Option<CircuitBreaker>) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+            && self
+                .circuit_breaker
+                .as_ref()
+                .is_none_or(|breaker| breaker.state() != CircuitState::Open)
+    }
+
     pub fn record_rejection(&self) {
         self.rejected.fetch_add(1, Ordering::Relaxed);
     }
@@ -184,7 +202,7 @@ impl HealthCheck {
             let query_upper = query.to_ascii_uppercase();
 
             if query_upper.starts_with("READY") {
-                let status = if self.ready.load(Ordering::SeqCst) {
+                let status = if self.is_ready() {
                     HealthStatus::Up
                 } else {
                     status_code = StatusCode::SERVICE_UNAVAILABLE;
@@ -287,6 +305,7 @@ mod tests {
 
     mod enable_router {
         use super::*;
+        use crate::circuit_breaker::CircuitBreakerConfig;
         use axum::{body::Body, http::Request};
         use http_body_util::BodyExt;
         use tower::ServiceExt;
@@ -368,6 +387,30 @@ mod tests {
             assert_eq!(json["status"], "DOWN");
         }
 
+        #[tokio::test]
+        async fn ready_query_returns_down_when_circuit_breaker_is_open() {
+            let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+                enabled: true,
+                failure_threshold: 1,
+                ..Default::default()
+            });
+            breaker.try_acquire().unwrap().failure();
+
+            let health_check =
+                HealthCheck::new(HealthCheckConfig::default()).with_circuit_breaker(Some(breaker));
+            let app = health_check.enable_router(Router::new());
+            let req = Request::builder()
+                .uri("/health?ready")
+                .body(Body::empty())
+                .unwrap();
+            let res = app.oneshot(req).await.unwrap();
+
+            assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["status"], "DOWN");
+        }
+
         #[tokio::test]
         async fn uses_configured_path() {
             let config = HealthCheckConfig {
@@ -1,15 +1,20 @@
 use crate::operations::private_fields::process_private_directives;
+use crate::operations::typename::inject_typename_into_query;
 use crate::operations::{MutationMode, operation_defs, operation_name};
 use crate::{
     graphql::{self, OperationDetails, ValidationError},
     schema_from_type,
 };
+use apollo_compiler::Schema;
+use apollo_compiler::validation::Valid;
 use reqwest::header::{HeaderMap, HeaderValue};
 use rmcp::model::Tool;
 use rmcp::schemars::JsonSchema;
 use rmcp::serde_json::Value;
 use rmcp::{schemars, serde_json};
 use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
 use super::description::append_description_hint;
 
@@ -19,6 +24,7 @@ pub const EXECUTE_TOOL_NAME: &str = "execute";
 #[derive(Clone)]
 pub struct Execute {
     pub tool: Tool,
+    schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     mutation_mode: MutationMode,
 }
 
@@ -34,34 +40,67 @@ pub struct Input {
 }
 
 impl Execute {
-    pub fn new(mutation_mode: MutationMode, description_hint: Option<&str>) -> Self {
+    pub fn new(
+        schema: Arc<RwLock<Arc<Valid<Schema>>>>,
+        mutation_mode: MutationMode,
+        description_hint: Option<&str>,
+    ) -> Self {
         let description = append_description_hint(
             "Execute a GraphQL operation. Use the `introspect` tool to get information about the GraphQL schema. Always use the schema to create operations - do not try arbitrary operations. If available, first use the `validate` tool to validate operations. DO NOT try to execute introspection queries.",
             description_hint,
         );
         Self {
+            schema,
             mutation_mode,
             tool: Tool::new(EXECUTE_TOOL_NAME, description, schema_from_type!(Input)),
         }
     }
+
+    /// Bind the tool to the current schema for the duration of a call
+    pub async fn with_current_schema(&self) -> ExecuteWithSchema<'_> {
+        ExecuteWithSchema {
+            execute: self,
+            schema: self.schema.read().await.clone(),
+        }
+    }
 }
 
-impl graphql::Executable for Execute {
+/// The execute tool with a snapshot of the schema, so that a call sees a single schema even
+/// if it is replaced while the call is in flight
+pub struct ExecuteWithSchema<'a> {
+    execute: &'a Execute,
+    schema: Arc<Valid<Schema>>,
+}
+
+impl ExecuteWithSchema<'_> {
+    /// The schema the call is bound to
+    pub fn schema(&self) -> &Valid<Schema> {
+        &self.schema
+    }
+}
+
+impl graphql::Executable for ExecuteWithSchema<'_> {
     fn operation(&self, input: Value) -> Result<OperationDetails, ValidationError> {
         let input = serde_json::from_value::<Input>(input)
             .map_err(|e| ValidationError(format!("Invalid input: {e}")))?;
 
-        let (_, operation_def, source_path) =
-            operation_defs(&input.query, self.mutation_mode == MutationMode::All, None)
-                .map_err(|e| ValidationError(e.to_string()))?
-                .ok_or_else(|| ValidationError("Invalid operation type".into()))?;
+        let (_, operation_def, source_path) = operation_defs(
+            &input.query,
+            self.execute.mutation_mode == MutationMode::All,
+            None,
+        )
+        .map_err(|e| ValidationError(e.to_string()))?
+        .ok_or_else(|| ValidationError("Invalid operation type".into()))?;
 
         let op_name = operation_name(&operation_def, source_path).ok();
 
+        // Select __typename on interfaces and unions so the response can be discriminated
+        let query = inject_typename_into_query(&input.query, &self.schema).unwrap_or(input.query);
+
         // Check for @private directives and strip them from the query sent downstream
-        let (query, private_fields) = match process_private_directives(&input.query) {
+        let (query, private_fields) = match process_private_directives(&query) {
             Some((stripped_query, tree)) => (stripped_query, Some(tree)),
-            None => (input.query, None),
+            None => (query, None),
         };
 
         Ok(OperationDetails {
@@ -96,11 +135,41 @@ mod tests {
     use crate::graphql::{Executable, OperationDetails, ValidationError};
     use crate::introspection::tools::execute::Execute;
     use crate::operations::MutationMode;
+    use apollo_compiler::Schema;
+    use apollo_compiler::validation::Valid;
     use rmcp::serde_json::{Value, json};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn test_schema() -> Arc<RwLock<Arc<Valid<Schema>>>> {
+        Arc::new(RwLock::new(Arc::new(
+            Schema::parse_and_validate(
+                r#"
+                type Query {
+                    user(id: ID!): User
+                    node(id: ID!): Node
+                }
+                type Mutation {
+                    id: ID
+                }
+                interface Node {
+                    id: ID!
+                }
+                type User implements Node {
+                    id: ID!
+                    name: String
+                }
+                "#,
+                "schema.graphql",
+            )
+            .unwrap(),
+        )))
+    }
 
-    #[test]
-    fn execute_query_with_variables_as_string() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_with_variables_as_string() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "query GetUser($id: ID!) { user(id: $id) { id name } }";
         let variables = json!({ "id": "123" });
@@ -121,9 +190,10 @@ mod tests {
         assert_eq!(Executable::variables(&execute, input), Ok(variables));
     }
 
-    #[test]
-    fn execute_query_with_variables_as_json() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_with_variables_as_json() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "query GetUser($id: ID!) { user(id: $id) { id name } }";
         let variables = json!({ "id": "123" });
@@ -144,9 +214,10 @@ mod tests {
         assert_eq!(Executable::variables(&execute, input), Ok(variables));
     }
 
-    #[test]
-    fn execute_query_without_variables() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_without_variables() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "query GetUser($id: ID!) { user(id: $id) { id name } }";
 
@@ -165,9 +236,10 @@ mod tests {
         assert_eq!(Executable::variables(&execute, input), Ok(Value::Null));
     }
 
-    #[test]
-    fn execute_query_anonymous_operation() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_anonymous_operation() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "{ user(id: \"123\") { id name } }";
         let input = json!({
@@ -184,9 +256,10 @@ mod tests {
         );
     }
 
-    #[test]
-    fn execute_query_err_with_mutation_when_mutation_mode_is_none() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_err_with_mutation_when_mutation_mode_is_none() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "mutation MutationName { id }".to_string();
         let input = json!({
@@ -197,9 +270,10 @@ mod tests {
         assert!(matches!(result, Err(ValidationError(msg)) if msg == "Invalid operation type"));
     }
 
-    #[test]
-    fn execute_query_ok_with_mutation_when_mutation_mode_is_all() {
-        let execute = Execute::new(MutationMode::All, None);
+    #[tokio::test]
+    async fn execute_query_ok_with_mutation_when_mutation_mode_is_all() {
+        let execute = Execute::new(test_schema(), MutationMode::All, None);
+        let execute = execute.with_current_schema().await;
 
         let query = "mutation MutationName { id }".to_string();
         let input = json!({
@@ -216,14 +290,15 @@ mod tests {
         );
     }
 
-    #[test]
-    fn execute_query_err_with_subscription_regardless_of_mutation_mode() {
+    #[tokio::test]
+    async fn execute_query_err_with_subscription_regardless_of_mutation_mode() {
         for mutation_mode in [
             MutationMode::None,
             MutationMode::Explicit,
             MutationMode::All,
         ] {
-            let execute = Execute::new(mutation_mode, None);
+            let execute = Execute::new(test_schema(), mutation_mode, None);
+            let execute = execute.with_current_schema().await;
 
             let input = json!({
                 "query": "subscription SubscriptionName { id }",
@@ -234,9 +309,10 @@ mod tests {
         }
     }
 
-    #[test]
-    fn execute_query_invalid_input() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_invalid_input() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let input = json!({
             "nonsense": "whatever",
@@ -249,9 +325,10 @@ mod tests {
         assert!(matches!(var_result, Err(ValidationError(msg)) if msg.contains("Invalid input")));
     }
 
-    #[test]
-    fn execute_query_invalid_variables() {
-        let execute = Execute::new(MutationMode::None, None);
+    #[tokio::test]
+    async fn execute_query_invalid_variables() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
 
         let input = json!({
             "query": "query GetUser($id: ID!) { user(id: $id) { id name } }",
@@ -261,4 +338,23 @@ mod tests {
         let result = Executable::variables(&execute, input);
         assert!(matches!(result, Err(ValidationError(msg)) if msg.contains("Invalid variables")));
     }
+
+    #[tokio::test]
+    async fn execute_query_injects_typename_into_abstract_selections() {
+        let execute = Execute::new(test_schema(), MutationMode::None, None);
+        let execute = execute.with_current_schema().await;
+
+        let input = json!({
+            "query": "query GetNode($id: ID!) { node(id: $id) { id } }",
+        });
+
+        assert_eq!(
+            Executable::operation(&execute, input),
+            Ok(OperationDetails {
+                query: "query GetNode($id: ID!) { node(id: $id) { __typename id } }".to_string(),
+                operation_name: Some("GetNode".to_string()),
+                private_fields: None,
+            })
+        );
+    }
 }
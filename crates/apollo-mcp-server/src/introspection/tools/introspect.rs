@@ -22,7 +22,7 @@ pub const INTROSPECT_TOOL_NAME: &str = "introspect";
 /// A tool to get detailed information about specific types from the GraphQL schema.
 #[derive(Clone)]
 pub struct Introspect {
-    schema: Arc<RwLock<Valid<Schema>>>,
+    schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     allow_mutations: bool,
     minify: bool,
     pub tool: Tool,
@@ -40,7 +40,7 @@ pub struct Input {
 
 impl Introspect {
     pub fn new(
-        schema: Arc<RwLock<Valid<Schema>>>,
+        schema: Arc<RwLock<Arc<Valid<Schema>>>>,
         root_query_type: Option<String>,
         root_mutation_type: Option<String>,
         minify: bool,
@@ -149,18 +149,18 @@ mod tests {
     const TEST_SCHEMA: &str = include_str!("testdata/schema.graphql");
 
     #[fixture]
-    fn schema() -> Arc<RwLock<Valid<Schema>>> {
-        Arc::new(RwLock::new(
+    fn schema() -> Arc<RwLock<Arc<Valid<Schema>>>> {
+        Arc::new(RwLock::new(Arc::new(
             Schema::parse(TEST_SCHEMA, "schema.graphql")
                 .expect("Failed to parse test schema")
                 .validate()
                 .expect("Failed to validate test schema"),
-        ))
+        )))
     }
 
     #[rstest]
     #[tokio::test]
-    async fn introspect_tool_description_is_not_minified(schema: Arc<RwLock<Valid<Schema>>>) {
+    async fn introspect_tool_description_is_not_minified(schema: Arc<RwLock<Arc<Valid<Schema>>>>) {
         let introspect = Introspect::new(schema, None, None, false, None);
 
         let description = introspect.tool.description.unwrap();
@@ -179,7 +179,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn introspect_tool_description_is_minified_with_an_appropriate_legend(
-        schema: Arc<RwLock<Valid<Schema>>>,
+        schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     ) {
         let introspect = Introspect::new(schema, None, None, true, None);
 
@@ -192,7 +192,7 @@ mod tests {
 
     #[rstest]
     #[tokio::test]
-    async fn introspect_query_depth_1_returns_fields(schema: Arc<RwLock<Valid<Schema>>>) {
+    async fn introspect_query_depth_1_returns_fields(schema: Arc<RwLock<Arc<Valid<Schema>>>>) {
         let introspect = Introspect::new(
             schema,
             Some("Query".to_string()),
@@ -231,7 +231,7 @@ mod tests {
 
     #[rstest]
     #[tokio::test]
-    async fn introspect_mutation_depth_1_returns_fields(schema: Arc<RwLock<Valid<Schema>>>) {
+    async fn introspect_mutation_depth_1_returns_fields(schema: Arc<RwLock<Arc<Valid<Schema>>>>) {
         let introspect = Introspect::new(
             schema,
             Some("Query".to_string()),
@@ -277,7 +277,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn introspect_mutation_depth_1_with_mutations_disabled(
-        schema: Arc<RwLock<Valid<Schema>>>,
+        schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     ) {
         // This test verifies the fix: when mutations are not allowed, mutation introspection should still work
         let introspect = Introspect::new(schema, Some("Query".to_string()), None, false, None);
@@ -29,7 +29,7 @@ const MAX_SEARCH_RESULTS: usize = 5;
 /// A tool to search a GraphQL schema.
 #[derive(Clone)]
 pub struct Search {
-    schema: Arc<RwLock<Valid<Schema>>>,
+    schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     index: SchemaIndex,
     allow_mutations: bool,
     leaf_depth: usize,
@@ -56,7 +56,7 @@ pub enum IndexingError {
 
 impl Search {
     pub fn new(
-        schema: Arc<RwLock<Valid<Schema>>>,
+        schema: Arc<RwLock<Arc<Valid<Schema>>>>,
         allow_mutations: bool,
         leaf_depth: usize,
         index_memory_bytes: usize,
@@ -208,7 +208,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn search_tool(schema: Valid<Schema>) {
-        let schema = Arc::new(RwLock::new(schema));
+        let schema = Arc::new(RwLock::new(Arc::new(schema)));
         let search = Search::new(schema.clone(), false, 1, 15_000_000, false, None)
             .expect("Failed to create search tool");
 
@@ -226,7 +226,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn referencing_types_are_collected(schema: Valid<Schema>) {
-        let schema = Arc::new(RwLock::new(schema));
+        let schema = Arc::new(RwLock::new(Arc::new(schema)));
         let search = Search::new(schema.clone(), true, 1, 15_000_000, false, None)
             .expect("Failed to create search tool");
 
@@ -248,7 +248,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn search_tool_description_is_not_minified(schema: Valid<Schema>) {
-        let schema = Arc::new(RwLock::new(schema));
+        let schema = Arc::new(RwLock::new(Arc::new(schema)));
         let search = Search::new(schema.clone(), false, 1, 15_000_000, false, None)
             .expect("Failed to create search tool");
 
@@ -267,7 +267,7 @@ mod tests {
     #[rstest]
     #[tokio::test]
     async fn tool_description_minified(schema: Valid<Schema>) {
-        let schema = Arc::new(RwLock::new(schema));
+        let schema = Arc::new(RwLock::new(Arc::new(schema)));
         let search = Search::new(schema.clone(), false, 1, 15_000_000, true, None)
             .expect("Failed to create search tool");
 
@@ -21,7 +21,7 @@ pub const VALIDATE_TOOL_NAME: &str = "validate";
 #[derive(Clone)]
 pub struct Validate {
     pub tool: Tool,
-    schema: Arc<RwLock<Valid<Schema>>>,
+    schema: Arc<RwLock<Arc<Valid<Schema>>>>,
 }
 
 /// Input for the validate tool
@@ -32,7 +32,7 @@ pub struct Input {
 }
 
 impl Validate {
-    pub fn new(schema: Arc<RwLock<Valid<Schema>>>, description_hint: Option<&str>) -> Self {
+    pub fn new(schema: Arc<RwLock<Arc<Valid<Schema>>>>, description_hint: Option<&str>) -> Self {
         let default_description = "Validates a GraphQL operation against the schema. \
                 Use the `introspect` tool first to get information about the GraphQL schema. \
                 Operations should be validated prior to calling the `execute` tool.";
@@ -81,15 +81,15 @@ mod tests {
     use serde_json::json;
 
     use super::*;
-    static SCHEMA: std::sync::LazyLock<Arc<RwLock<Valid<Schema>>>> =
+    static SCHEMA: std::sync::LazyLock<Arc<RwLock<Arc<Valid<Schema>>>>> =
         std::sync::LazyLock::new(|| {
-            Arc::new(RwLock::new(
+            Arc::new(RwLock::new(Arc::new(
                 Schema::parse_and_validate(
                     "type Query { id: ID! hello(name: String!): String! }",
                     "schema.graphql",
                 )
                 .unwrap(),
-            ))
+            )))
         });
 
     #[tokio::test]
@@ -2,9 +2,12 @@
 
 pub(crate) mod apps;
 pub(crate) mod auth;
+pub mod circuit_breaker;
+pub mod concurrency;
 pub mod cors;
 pub mod custom_scalar_map;
 pub mod env_expansion;
+pub mod error_mapping;
 pub mod errors;
 pub(crate) mod event;
 mod explorer;
@@ -16,7 +19,10 @@ mod introspection;
 pub(crate) mod json_schema;
 pub(crate) mod meter;
 pub mod operations;
+pub mod policy;
 pub(crate) mod prompts;
+pub mod rate_limit;
+pub mod rhai;
 pub(crate) mod schema_tree_shake;
 pub mod server;
 pub mod server_info;
@@ -154,6 +154,12 @@ fn build_server(config_path: Option<&std::path::Path>) -> anyhow::Result<Server>
         &config.transport,
         Transport::StreamableHttp { auth: Some(auth), .. } if auth.disable_auth_token_passthrough
     );
+    let claim_headers = match &config.transport {
+        Transport::StreamableHttp {
+            auth: Some(auth), ..
+        } => auth.claim_headers.clone(),
+        _ => Default::default(),
+    };
 
     Ok(Server::builder()
         .maybe_config_path(config_path.map(Path::to_path_buf))
@@ -182,6 +188,7 @@ fn build_server(config_path: Option<&std::path::Path>) -> anyhow::Result<Server>
         .annotations(config.overrides.annotations)
         .required_scopes(config.overrides.required_scopes)
         .disable_auth_token_passthrough(disable_auth_token_passthrough)
+        .claim_headers(claim_headers)
         .custom_scalar_map(
             config
                 .custom_scalars
@@ -193,6 +200,13 @@ fn build_server(config_path: Option<&std::path::Path>) -> anyhow::Result<Server>
         .health_check(config.health_check)
         .cors(config.cors)
         .server_info(config.server_info)
+        .policies(config.policies)
+        .maybe_upstream_auth(config.upstream_auth)
+        .concurrency(config.concurrency)
+        .rate_limits(config.rate_limits)
+        .circuit_breaker(config.circuit_breaker)
+        .error_mapping(config.error_mapping)
+        .rhai(config.rhai)
         .maybe_config_validator(config_validator)
         .maybe_instructions(config.instructions)
         .build())
@@ -11,9 +11,12 @@ mod operation_source;
 pub(crate) mod private_fields;
 mod raw_operation;
 mod schema_walker;
+pub(crate) mod typename;
 
 pub use annotation_overrides::AnnotationOverrides;
-pub(crate) use execution::{execute_operation, find_and_execute_operation};
+pub(crate) use execution::{
+    ScriptOperations, execute_operation, find_and_execute_operation, prepare_operation,
+};
 pub use mutation_mode::MutationMode;
 pub(crate) use operation::{Operation, operation_defs, operation_name};
 pub use operation_source::OperationSource;
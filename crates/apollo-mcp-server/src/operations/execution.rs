@@ -1,28 +1,41 @@
 use std::sync::Arc;
 
+use apollo_compiler::Schema;
+use apollo_compiler::parser::Parser;
+use apollo_compiler::validation::Valid;
 use http::HeaderMap;
 use http::request::Parts;
 use opentelemetry::Context;
 use opentelemetry::trace::FutureExt;
-use parking_lot::Mutex;
 use rmcp::model::{CallToolResult, JsonObject};
 use serde_json::Value;
+use tokio::sync::RwLock;
+use tracing::warn;
 use url::Url;
 
+use crate::auth::ValidToken;
+use crate::concurrency::ConcurrencyLimiter;
 use crate::errors::McpError;
-use crate::graphql::{self, Executable};
+use crate::graphql::{self, Executable, OperationDetails, Upstream, ValidationError};
+use crate::policy::{Policies, PolicyTarget};
+use crate::rate_limit::RateLimiter;
 use crate::server::states::telemetry::current_trace_id;
+use apollo_mcp_rhai::checkpoints::{GraphqlOperation, TokenClaims};
+use apollo_mcp_rhai::tools::{OperationExecutor, OperationFuture};
 use apollo_mcp_rhai::{RhaiEngine, checkpoints};
 
-use super::Operation;
+use super::{Operation, operation_defs};
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn find_and_execute_operation(
     operations: &[Operation],
     tool_name: &str,
     headers: &HeaderMap,
     arguments: Option<&JsonObject>,
     endpoint: &Url,
-    rhai_engine: &Arc<Mutex<RhaiEngine>>,
+    upstream: &Upstream,
+    schema: &Valid<Schema>,
+    rhai_engine: &Arc<RhaiEngine>,
     axum_parts: Option<&Parts>,
 ) -> Option<Result<CallToolResult, McpError>> {
     let operation = operations.iter().find(|op| op.as_ref().name == tool_name)?;
@@ -32,6 +45,8 @@ pub(crate) async fn find_and_execute_operation(
             headers,
             arguments,
             endpoint,
+            upstream,
+            schema,
             rhai_engine,
             axum_parts,
             tool_name,
@@ -40,36 +55,283 @@ pub(crate) async fn find_and_execute_operation(
     )
 }
 
+/// The claims of the validated access token of a request, for Rhai hooks
+fn token_claims(axum_parts: Option<&Parts>) -> Option<TokenClaims<'_>> {
+    axum_parts
+        .and_then(|parts| parts.extensions.get::<ValidToken>())
+        .map(|token| TokenClaims {
+            claims: &token.claims,
+            scopes: &token.scopes,
+        })
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn execute_operation(
     executable: &impl Executable,
     headers: &HeaderMap,
     arguments: Option<&JsonObject>,
     endpoint: &Url,
-    rhai_engine: &Arc<Mutex<RhaiEngine>>,
+    upstream: &Upstream,
+    schema: &Valid<Schema>,
+    rhai_engine: &Arc<RhaiEngine>,
     axum_parts: Option<&Parts>,
     tool_name: &str,
 ) -> Result<CallToolResult, McpError> {
-    let (endpoint, headers) = checkpoints::on_execute_graphql_operation(
-        rhai_engine,
+    let (endpoint, headers, operation) = prepare_operation(
+        executable,
+        Value::from(arguments.cloned()),
         endpoint,
         headers,
+        schema,
+        rhai_engine,
         axum_parts,
         tool_name,
-        current_trace_id,
     )?;
+    let response_hook = graphql::ResponseHook {
+        rhai_engine,
+        tool_name,
+    };
 
+    // The prepared operation has already resolved the input
     let graphql_request = graphql::Request {
-        input: Value::from(arguments.cloned()),
+        input: Value::Null,
         endpoint: &endpoint,
         headers: &headers,
+        upstream,
+        response_hook: Some(response_hook),
     };
 
-    executable
+    operation
         .execute(graphql_request)
         .with_context(Context::current())
         .await
 }
 
+/// Executes operations for the handlers of tools defined by Rhai scripts.
+///
+/// The handler holds a copy of the scripts' global variables while it waits for these operations,
+/// so they run without the Rhai hooks, which could otherwise wait for a copy that never frees up.
+/// Each operation is still rate limited, authorized, and concurrency limited as if the caller had
+/// called its tool directly.
+pub(crate) struct ScriptOperations {
+    pub(crate) operations: Arc<RwLock<Vec<super::Operation>>>,
+    pub(crate) endpoint: Url,
+    pub(crate) headers: HeaderMap,
+    pub(crate) upstream: Upstream,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) policies: Option<Policies>,
+    pub(crate) concurrency_limiter: Option<ConcurrencyLimiter>,
+    /// The request of the script tool call
+    pub(crate) axum_parts: Option<Parts>,
+}
+
+impl OperationExecutor for ScriptOperations {
+    fn execute(&self, operation: String, variables: Value) -> OperationFuture {
+        let operations = self.operations.clone();
+        let endpoint = self.endpoint.clone();
+        let headers = self.headers.clone();
+        let upstream = self.upstream.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let policies = self.policies.clone();
+        let concurrency_limiter = self.concurrency_limiter.clone();
+        let axum_parts = self.axum_parts.clone();
+
+        Box::pin(async move {
+            let operation = operations
+                .read()
+                .await
+                .iter()
+                .find(|op| op.as_ref().name == operation)
+                .cloned()
+                .ok_or_else(|| format!("Operation {operation} was not found"))?;
+
+            let tool_name = operation.tool.name.as_ref();
+            if let Some(policies) = &policies {
+                let token = axum_parts
+                    .as_ref()
+                    .and_then(|parts| parts.extensions.get::<ValidToken>());
+                policies
+                    .check_call(
+                        &PolicyTarget {
+                            tool: tool_name,
+                            annotations: operation.tool.annotations.as_ref(),
+                            operation_type: Some(operation.operation_type),
+                        },
+                        token,
+                    )
+                    .map_err(|denied| denied.to_string())?;
+            }
+            if let Some(rate_limiter) = &rate_limiter {
+                rate_limiter
+                    .check(tool_name, axum_parts.as_ref())
+                    .map_err(|throttled| throttled.to_string())?;
+            }
+            let _permit = match &concurrency_limiter {
+                Some(limiter) => Some(
+                    limiter
+                        .acquire_nested(tool_name)
+                        .await
+                        .map_err(|saturated| saturated.to_string())?,
+                ),
+                None => None,
+            };
+
+            let result = operation
+                .execute(graphql::Request {
+                    input: variables,
+                    endpoint: &endpoint,
+                    headers: &headers,
+                    upstream: &upstream,
+                    response_hook: None,
+                })
+                .await
+                .map_err(|err| err.message.to_string())?;
+
+            // Results without structured content describe why the operation wasn't executed
+            result.structured_content.ok_or_else(|| {
+                result
+                    .content
+                    .iter()
+                    .filter_map(|content| content.as_text())
+                    .map(|text| text.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+        })
+    }
+}
+
+/// An operation whose query and variables were resolved before execution, so that the
+/// `on_execute_graphql_operation` hook could inspect and rewrite them
+pub(crate) struct PreparedOperation<'a, E> {
+    executable: &'a E,
+    details: Result<OperationDetails, ValidationError>,
+    variables: Result<Value, ValidationError>,
+}
+
+impl<E: Executable> Executable for PreparedOperation<'_, E> {
+    fn operation(&self, _input: Value) -> Result<OperationDetails, ValidationError> {
+        self.details.clone()
+    }
+
+    fn variables(&self, _input: Value) -> Result<Value, ValidationError> {
+        self.variables.clone()
+    }
+
+    fn headers(&self, default_headers: &HeaderMap) -> HeaderMap {
+        self.executable.headers(default_headers)
+    }
+}
+
+/// Run the `on_execute_graphql_operation` hook, returning the endpoint and headers to use and the
+/// operation to execute.
+///
+/// The hook is skipped for invalid input, which fails when the operation is executed.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_operation<'a, E: Executable>(
+    executable: &'a E,
+    input: Value,
+    endpoint: &Url,
+    headers: &HeaderMap,
+    schema: &Valid<Schema>,
+    rhai_engine: &Arc<RhaiEngine>,
+    axum_parts: Option<&Parts>,
+    tool_name: &str,
+) -> Result<(Url, HeaderMap, PreparedOperation<'a, E>), McpError> {
+    let details = executable.operation(input.clone());
+    let variables = executable.variables(input);
+    let (Ok(details), Ok(variables)) = (details.clone(), variables.clone()) else {
+        let operation = PreparedOperation {
+            executable,
+            details,
+            variables,
+        };
+        return Ok((endpoint.clone(), headers.clone(), operation));
+    };
+
+    let mut operation = GraphqlOperation {
+        query: details.query.clone(),
+        operation_name: details.operation_name.clone(),
+        variables,
+    };
+    let (endpoint, headers) = checkpoints::on_execute_graphql_operation(
+        rhai_engine,
+        endpoint,
+        headers,
+        &mut operation,
+        axum_parts,
+        tool_name,
+        token_claims(axum_parts),
+        current_trace_id,
+    )?;
+
+    let rewritten = if operation.query == details.query {
+        Ok(())
+    } else {
+        check_rewritten_query(
+            &details.query,
+            &operation.query,
+            operation.operation_name.as_deref(),
+            schema,
+        )
+        .map_err(|err| {
+            warn!("The on_execute_graphql_operation hook rewrote {tool_name} into an invalid query: {err}");
+            ValidationError(format!(
+                "The on_execute_graphql_operation hook rewrote the operation into an invalid query: {err}"
+            ))
+        })
+    };
+
+    let operation = PreparedOperation {
+        executable,
+        details: rewritten.map(|()| OperationDetails {
+            query: operation.query,
+            operation_name: operation.operation_name,
+            // The response is still filtered by the `@private` fields of the original query
+            private_fields: details.private_fields,
+        }),
+        variables: Ok(operation.variables),
+    };
+    Ok((endpoint, headers, operation))
+}
+
+/// Check that a query rewritten by a script is still a single operation of the same type, so that
+/// a script can't turn a query into a mutation that the mutation mode would have rejected, and
+/// that it is still valid against the schema
+fn check_rewritten_query(
+    original: &str,
+    rewritten: &str,
+    operation_name: Option<&str>,
+    schema: &Valid<Schema>,
+) -> Result<(), String> {
+    let definition = |query| match operation_defs(query, true, None) {
+        Ok(Some((_, definition, _))) => Ok(definition),
+        Ok(None) => Err("the rewritten query is a subscription".to_string()),
+        Err(err) => Err(format!("the rewritten query is invalid: {err}")),
+    };
+    let original = definition(original)?;
+    let rewritten_definition = definition(rewritten)?;
+
+    if rewritten_definition.operation_type != original.operation_type {
+        return Err(format!(
+            "the rewritten query is a {} instead of a {}",
+            rewritten_definition.operation_type, original.operation_type
+        ));
+    }
+    if let Some(operation_name) = operation_name
+        && rewritten_definition.name.as_deref() != Some(operation_name)
+    {
+        return Err(format!(
+            "the rewritten query has no operation named {operation_name}"
+        ));
+    }
+    Parser::new()
+        .parse_executable(schema, rewritten, "operation.graphql")
+        .and_then(|document| document.validate(schema))
+        .map_err(|err| format!("the rewritten query is invalid against the schema: {err}"))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -106,7 +368,9 @@ mod tests {
             &HeaderMap::new(),
             None,
             &"http://localhost:4000".parse().unwrap(),
-            &Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await;
@@ -163,7 +427,9 @@ mod tests {
             &HeaderMap::new(),
             None,
             &server.url().parse().unwrap(),
-            &Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(RhaiEngine::new("rhai")),
             None,
         )
         .await;
@@ -173,4 +439,107 @@ mod tests {
         let call_result = result.unwrap().unwrap();
         assert!(call_result.is_error != Some(true));
     }
+
+    #[tokio::test]
+    async fn applies_operation_rewritten_by_script() {
+        let schema = Schema::parse(
+            "type Query { users(first: Int): [String] }",
+            "schema.graphql",
+        )
+        .unwrap()
+        .validate()
+        .unwrap();
+        let operation = RawOperation::from((
+            "query ListUsers($first: Int) { users(first: $first) }".to_string(),
+            None,
+        ))
+        .into_operation(
+            &schema,
+            None,
+            MutationMode::All,
+            true,
+            true,
+            true,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap()
+        .unwrap();
+
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r##"fn on_execute_graphql_operation(ctx) {
+                if ctx.variables.first > 50 {
+                    ctx.variables.first = 50;
+                }
+                ctx.query = "# " + ctx.tool_name + "\n" + ctx.query;
+            }"##,
+        )
+        .unwrap();
+        let engine = RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "query": "# ListUsers\nquery ListUsers($first: Int) { users(first: $first) }",
+                "variables": { "first": 50 },
+            })))
+            .with_body(r#"{"data": {"users": []}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let result = find_and_execute_operation(
+            &[operation],
+            "ListUsers",
+            &HeaderMap::new(),
+            serde_json::json!({ "first": 1000 }).as_object(),
+            &server.url().parse().unwrap(),
+            &Upstream::default(),
+            &schema,
+            &Arc::new(engine),
+            None,
+        )
+        .await;
+
+        mock.assert();
+        assert!(result.unwrap().is_ok());
+    }
+
+    #[test]
+    fn rewritten_query_must_keep_operation_type_and_name() {
+        let schema = Schema::parse_and_validate(
+            "type Query { user: User } type Mutation { deleteUser: Boolean } type User { id: ID }",
+            "schema.graphql",
+        )
+        .unwrap();
+        let original = "query GetUser { user { id } }";
+        let check = |rewritten, operation_name| {
+            check_rewritten_query(original, rewritten, operation_name, &schema)
+        };
+
+        assert!(check("query GetUser { user { __typename id } }", Some("GetUser")).is_ok());
+        assert!(check("{ user { id } }", None).is_ok());
+        assert!(check("mutation GetUser { deleteUser }", Some("GetUser")).is_err());
+        assert!(check("query Other { user { id } }", Some("GetUser")).is_err());
+        assert!(check("query GetUser {", Some("GetUser")).is_err());
+    }
+
+    #[test]
+    fn rewritten_query_must_be_valid_against_the_schema() {
+        let schema =
+            Schema::parse_and_validate("type Query { user: String }", "schema.graphql").unwrap();
+
+        let result = check_rewritten_query(
+            "query GetUser { user }",
+            "query GetUser { user password }",
+            Some("GetUser"),
+            &schema,
+        );
+
+        assert!(result.unwrap_err().contains("password"));
+    }
 }
@@ -27,6 +27,7 @@ use super::{
         PrivateFieldTree, collect_named_fragments, collect_private_fields, strip_private_directives,
     },
     schema_walker,
+    typename::inject_typename,
 };
 
 /// A valid GraphQL operation
@@ -35,13 +36,17 @@ pub struct Operation {
     pub(crate) tool: Tool,
     pub(crate) inner: RawOperation,
     operation_name: String,
-    /// Query text with `@private` directives stripped, sent downstream instead of `source_text`.
-    /// `None` when the operation has no `@private` directives.
-    stripped_source_text: Option<String>,
+    /// Query text sent downstream instead of `source_text`, with `@private` directives stripped
+    /// and `__typename` injected into abstract selection sets.
+    /// `None` when the operation needs neither rewrite.
+    rewritten_source_text: Option<String>,
     /// Tree of field paths marked `@private`, used for response filtering.
     /// `None` when the operation has no `@private` directives.
     #[serde(skip)]
     pub(crate) private_fields: Option<PrivateFieldTree>,
+    /// Whether the operation is a query, mutation, or subscription
+    #[serde(skip)]
+    pub(crate) operation_type: OperationType,
 }
 
 impl AsRef<Tool> for Operation {
@@ -133,6 +138,24 @@ impl Operation {
                 ));
             };
 
+            // Select __typename on interfaces and unions so the response can be discriminated
+            let injected_document = inject_typename(&document, graphql_schema);
+            let typename_injected = injected_document.is_some();
+            let (document, operation) = match injected_document {
+                Some(injected) => {
+                    let injected_operation = injected
+                        .definitions
+                        .iter()
+                        .find_map(|def| match def {
+                            Definition::OperationDefinition(op) => Some(op.clone()),
+                            _ => None,
+                        })
+                        .unwrap_or(operation);
+                    (injected, injected_operation)
+                }
+                None => (document, operation),
+            };
+
             // Collect named fragments for use by output schema and @private detection
             let named_fragments = collect_named_fragments(&document);
 
@@ -173,12 +196,14 @@ impl Operation {
                 None
             };
 
-            let (stripped_source_text, private_fields) = if has_private_fields {
+            let (rewritten_source_text, private_fields) = if has_private_fields {
                 let stripped_doc = strip_private_directives(&document);
                 (
                     Some(stripped_doc.serialize().no_indent().to_string()),
                     Some(private_tree),
                 )
+            } else if typename_injected {
+                (Some(document.serialize().no_indent().to_string()), None)
             } else {
                 (None, None)
             };
@@ -216,8 +241,9 @@ impl Operation {
                 tool,
                 inner: raw_operation,
                 operation_name,
-                stripped_source_text,
+                rewritten_source_text,
                 private_fields,
+                operation_type: operation.operation_type,
             }))
         } else {
             Ok(None)
@@ -361,7 +387,7 @@ impl graphql::Executable for Operation {
     fn operation(&self, _input: Value) -> Result<OperationDetails, ValidationError> {
         Ok(OperationDetails {
             query: self
-                .stripped_source_text
+                .rewritten_source_text
                 .clone()
                 .unwrap_or_else(|| self.inner.source_text.clone()),
             operation_name: Some(self.operation_name.clone()),
@@ -4500,8 +4526,9 @@ mod tests {
                 source_path: None,
             },
             operation_name: "MutationName",
-            stripped_source_text: None,
+            rewritten_source_text: None,
             private_fields: None,
+            operation_type: Mutation,
         }
         "#);
     }
@@ -4634,8 +4661,9 @@ mod tests {
                 source_path: None,
             },
             operation_name: "MutationName",
-            stripped_source_text: None,
+            rewritten_source_text: None,
             private_fields: None,
+            operation_type: Mutation,
         }
         "#);
     }
@@ -4987,7 +5015,7 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        assert!(operation.stripped_source_text.is_none());
+        assert!(operation.rewritten_source_text.is_none());
         assert!(operation.private_fields.is_none());
     }
 
@@ -5018,7 +5046,7 @@ mod tests {
         .unwrap()
         .unwrap();
 
-        assert!(operation.stripped_source_text.is_some());
+        assert!(operation.rewritten_source_text.is_some());
         assert!(operation.private_fields.is_some());
     }
 
@@ -5049,11 +5077,40 @@ mod tests {
         .unwrap()
         .unwrap();
 
-        let stripped = operation.stripped_source_text.unwrap();
+        let stripped = operation.rewritten_source_text.unwrap();
         assert!(!stripped.contains("@private"));
         assert!(stripped.contains("fieldB"));
     }
 
+    #[test]
+    fn operation_method_returns_text_with_typename_for_abstract_selections() {
+        let schema = Schema::parse(
+            "type Query { node: Node } interface Node { id: ID! } type User implements Node { id: ID! }",
+            "schema.graphql",
+        )
+        .unwrap()
+        .validate()
+        .unwrap();
+
+        let operation = RawOperation::from(("query TestOp { node { id } }".to_string(), None))
+            .into_operation(
+                &schema,
+                None,
+                MutationMode::All,
+                false,
+                false,
+                true,
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+            .unwrap()
+            .unwrap();
+
+        let details = operation.operation(Value::Null).unwrap();
+        assert_eq!(details.query, "query TestOp { node { __typename id } }");
+        assert!(details.private_fields.is_none());
+    }
+
     #[test]
     fn operation_method_returns_stripped_text_when_private() {
         let schema = Schema::parse(
@@ -5135,7 +5192,7 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        let stripped = operation.stripped_source_text.unwrap();
+        let stripped = operation.rewritten_source_text.unwrap();
         assert!(
             stripped.contains("fragment UserFields"),
             "stripped text should include fragment definitions, got: {stripped}"
@@ -17,6 +17,8 @@ use tracing::warn;
 use crate::custom_scalar_map::CustomScalarMap;
 use crate::operations::private_fields::PrivateFieldTree;
 
+const TYPENAME_FIELD: &str = "__typename";
+
 /// Generate a JSON Schema for the output of a GraphQL operation.
 ///
 /// This walks the selection set and generates a schema that describes
@@ -127,7 +129,7 @@ fn build_selection_set_schema(
                 }
 
                 // Skip __typename - it's always a string
-                if field_name == "__typename" {
+                if field_name == TYPENAME_FIELD {
                     properties.insert(
                         response_key,
                         json_schema!({"type": "string", "description": "The typename of this object"}).into(),
@@ -173,9 +175,11 @@ fn build_selection_set_schema(
                 // Merge fields from named fragment
                 if let Some(fragment_def) =
                     named_fragments.get(fragment_spread.fragment_name.as_str())
-                    && let Some(target_type) = graphql_schema
-                        .types
-                        .get(fragment_def.type_condition.as_str())
+                    && let Some(target_type) = fragment_target_type(
+                        Some(fragment_def.type_condition.as_str()),
+                        parent_type,
+                        graphql_schema,
+                    )
                 {
                     let fragment_schema = build_selection_set_schema(
                         &fragment_def.selection_set,
@@ -201,11 +205,11 @@ fn build_selection_set_schema(
             }
             Selection::InlineFragment(inline_fragment) => {
                 // For inline fragments, we need to handle type conditions
-                let target_type = if let Some(type_condition) = &inline_fragment.type_condition {
-                    graphql_schema.types.get(type_condition.as_str())
-                } else {
-                    Some(parent_type)
-                };
+                let target_type = fragment_target_type(
+                    inline_fragment.type_condition.as_ref().map(|t| t.as_str()),
+                    parent_type,
+                    graphql_schema,
+                );
 
                 if let Some(target_type) = target_type {
                     let fragment_schema = build_selection_set_schema(
@@ -399,14 +403,14 @@ fn named_type_to_output_schema(
                 }
             }
 
-            // Interface types - similar to objects
-            Some(ExtendedType::Interface(iface)) => {
+            // Interface and union types - oneOf the possible concrete types, discriminated by __typename
+            Some(ExtendedType::Interface(_) | ExtendedType::Union(_)) => {
                 if selection_set.is_empty() {
                     json_schema!({})
                 } else {
-                    build_selection_set_schema(
+                    abstract_type_to_output_schema(
+                        other,
                         selection_set,
-                        &ExtendedType::Interface(iface.clone()),
                         graphql_schema,
                         custom_scalar_map,
                         named_fragments,
@@ -416,44 +420,6 @@ fn named_type_to_output_schema(
                 }
             }
 
-            // Union types - oneOf the possible types based on inline fragments
-            Some(ExtendedType::Union(_union_def)) => {
-                if selection_set.is_empty() {
-                    json_schema!({})
-                } else {
-                    // Collect schemas for each possible type from inline fragments
-                    let mut type_schemas = Vec::new();
-
-                    for selection in selection_set {
-                        if let Selection::InlineFragment(fragment) = selection
-                            && let Some(type_condition) = &fragment.type_condition
-                            && let Some(member_type) =
-                                graphql_schema.types.get(type_condition.as_str())
-                        {
-                            let member_schema = build_selection_set_schema(
-                                &fragment.selection_set,
-                                member_type,
-                                graphql_schema,
-                                custom_scalar_map,
-                                named_fragments,
-                                definitions,
-                                private_tree,
-                            );
-                            type_schemas.push(member_schema);
-                        }
-                    }
-
-                    if type_schemas.is_empty() {
-                        // No inline fragments - just return empty schema
-                        json_schema!({})
-                    } else if type_schemas.len() == 1 {
-                        type_schemas.remove(0)
-                    } else {
-                        json_schema!({"oneOf": type_schemas})
-                    }
-                }
-            }
-
             // Enum types
             // Note: We only include the enum's type description (not per-value descriptions)
             // to avoid token bloat with large enums. The `enum` constraint already lists
@@ -513,6 +479,151 @@ fn named_type_to_output_schema(
     }
 }
 
+/// Build a `oneOf` schema for an abstract type, discriminated by `__typename`.
+///
+/// Possible types singled out by a type condition in the selection get a variant of their
+/// own. The others only receive the fields selected on the abstract type, so they share a
+/// variant whose `__typename` is one of their names. The server injects `__typename` into
+/// abstract selection sets before execution, so it is always present in the response.
+fn abstract_type_to_output_schema(
+    abstract_type: &str,
+    selection_set: &[Selection],
+    graphql_schema: &GraphQLSchema,
+    custom_scalar_map: Option<&CustomScalarMap>,
+    named_fragments: &HashMap<String, Node<apollo_compiler::ast::FragmentDefinition>>,
+    definitions: &mut Map<String, Value>,
+    private_tree: &PrivateFieldTree,
+) -> JSONSchema {
+    let possible_types: Vec<&GraphQLName> = graphql_schema
+        .types
+        .iter()
+        .filter(|(name, ty)| ty.is_object() && graphql_schema.is_subtype(abstract_type, name))
+        .map(|(name, _)| name)
+        .collect();
+
+    let applies = |condition: &str, type_name: &str| {
+        condition == type_name || graphql_schema.is_subtype(condition, type_name)
+    };
+    let mut conditions = Vec::new();
+    collect_type_conditions(selection_set, named_fragments, &mut conditions);
+    conditions.retain(|condition| {
+        !possible_types
+            .iter()
+            .all(|type_name| applies(condition, type_name))
+    });
+
+    // Group the types by their schema so that types selected the same way share a variant
+    let mut groups: Vec<(JSONSchema, Vec<&str>)> = Vec::new();
+    for type_name in possible_types {
+        let Some(ty) = graphql_schema.types.get(type_name) else {
+            continue;
+        };
+        let variant = build_selection_set_schema(
+            selection_set,
+            ty,
+            graphql_schema,
+            custom_scalar_map,
+            named_fragments,
+            definitions,
+            private_tree,
+        );
+        let singled_out = conditions
+            .iter()
+            .any(|condition| applies(condition, type_name));
+        match groups
+            .iter_mut()
+            .find(|(schema, _)| !singled_out && *schema == variant)
+        {
+            Some((_, names)) => names.push(type_name.as_str()),
+            None => groups.push((variant, vec![type_name.as_str()])),
+        }
+    }
+
+    let mut variants: Vec<JSONSchema> = groups
+        .into_iter()
+        .map(|(mut variant, names)| {
+            let typename = match names.as_slice() {
+                [name] => json_schema!({"const": name}),
+                _ => json_schema!({"enum": names}),
+            };
+            let obj = variant.ensure_object();
+            if let Value::Object(properties) = obj
+                .entry("properties")
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                properties.insert(TYPENAME_FIELD.to_string(), typename.into());
+            }
+            if let Value::Array(required) = obj
+                .entry("required")
+                .or_insert_with(|| Value::Array(Vec::new()))
+                && !required.iter().any(|r| r == TYPENAME_FIELD)
+            {
+                required.insert(0, TYPENAME_FIELD.into());
+            }
+            variant
+        })
+        .collect();
+
+    match variants.len() {
+        0 => json_schema!({}),
+        1 => variants.remove(0),
+        _ => json_schema!({"oneOf": variants}),
+    }
+}
+
+/// Collect the type conditions of the fragments applied directly to a selection set
+fn collect_type_conditions<'a>(
+    selection_set: &'a [Selection],
+    named_fragments: &'a HashMap<String, Node<apollo_compiler::ast::FragmentDefinition>>,
+    conditions: &mut Vec<&'a str>,
+) {
+    for selection in selection_set {
+        match selection {
+            Selection::Field(_) => {}
+            Selection::FragmentSpread(fragment_spread) => {
+                if let Some(fragment_def) =
+                    named_fragments.get(fragment_spread.fragment_name.as_str())
+                {
+                    conditions.push(fragment_def.type_condition.as_str());
+                    collect_type_conditions(
+                        &fragment_def.selection_set,
+                        named_fragments,
+                        conditions,
+                    );
+                }
+            }
+            Selection::InlineFragment(inline_fragment) => {
+                if let Some(condition) = &inline_fragment.type_condition {
+                    conditions.push(condition.as_str());
+                }
+                collect_type_conditions(
+                    &inline_fragment.selection_set,
+                    named_fragments,
+                    conditions,
+                );
+            }
+        }
+    }
+}
+
+/// Resolve the type a fragment's selections should be evaluated against.
+///
+/// Within a concrete object type, fragments on that type or on an abstract type it belongs
+/// to keep the object as their parent, and fragments that cannot apply are skipped.
+fn fragment_target_type<'a>(
+    type_condition: Option<&str>,
+    parent_type: &'a ExtendedType,
+    graphql_schema: &'a GraphQLSchema,
+) -> Option<&'a ExtendedType> {
+    match type_condition {
+        None => Some(parent_type),
+        Some(condition) if parent_type.is_object() => (condition == parent_type.name().as_str()
+            || graphql_schema.is_subtype(condition, parent_type.name()))
+        .then_some(parent_type),
+        Some(condition) => graphql_schema.types.get(condition),
+    }
+}
+
 /// Get field definition from a parent type (Object or Interface)
 fn get_field_definition(
     parent_type: &ExtendedType,
@@ -803,4 +914,124 @@ mod tests {
             "email should be excluded from schema because it is marked @private, but got: {output_str}"
         );
     }
+
+    #[test]
+    fn abstract_type_output_schema_is_discriminated_by_typename() {
+        let schema = parse_schema(
+            r#"
+            type Query {
+                node(id: ID!): Node
+            }
+
+            interface Node {
+                id: ID!
+            }
+
+            type User implements Node {
+                id: ID!
+                name: String!
+            }
+
+            type Post implements Node {
+                id: ID!
+                title: String
+            }
+            "#,
+        );
+
+        let (_, selection_set) = parse_operation(
+            r#"
+            query GetNode($id: ID!) {
+                node(id: $id) {
+                    __typename
+                    id
+                    ... on User {
+                        name
+                    }
+                }
+            }
+            "#,
+        );
+
+        let query_type = schema.types.get("Query").unwrap();
+        let output_schema = selection_set_to_schema(
+            &selection_set,
+            query_type,
+            &schema,
+            None,
+            &HashMap::new(),
+            None,
+        );
+
+        insta::assert_snapshot!(serde_json::to_string_pretty(&output_schema).unwrap());
+    }
+
+    #[test]
+    fn abstract_type_output_schema_collapses_types_without_type_conditions() {
+        let schema = parse_schema(
+            r#"
+            type Query {
+                node(id: ID!): Node
+            }
+
+            interface Node {
+                id: ID!
+            }
+
+            type User implements Node {
+                id: ID!
+                name: String!
+            }
+
+            type Post implements Node {
+                id: ID!
+                title: String
+            }
+
+            type Comment implements Node {
+                id: ID!
+                body: String
+            }
+            "#,
+        );
+
+        let (_, selection_set) = parse_operation(
+            r#"
+            query GetNode($id: ID!) {
+                node(id: $id) {
+                    id
+                    ... on User {
+                        name
+                    }
+                }
+            }
+            "#,
+        );
+
+        let query_type = schema.types.get("Query").unwrap();
+        let output_schema = selection_set_to_schema(
+            &selection_set,
+            query_type,
+            &schema,
+            None,
+            &HashMap::new(),
+            None,
+        );
+
+        let variants = &output_schema.as_value()["properties"]["data"]["properties"]["node"]["oneOf"]
+            [0]["oneOf"];
+        let typenames: Vec<&Value> = variants
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["properties"][TYPENAME_FIELD])
+            .collect();
+        assert_eq!(
+            typenames,
+            vec![
+                &serde_json::json!({"const": "User"}),
+                &serde_json::json!({"enum": ["Post", "Comment"]}),
+            ]
+        );
+    }
 }
@@ -0,0 +1,217 @@
+use apollo_compiler::{
+    Node, Schema,
+    ast::{Definition, Document, Field, Selection},
+    name,
+    parser::Parser,
+    schema::ExtendedType,
+};
+
+const TYPENAME_FIELD: &str = "__typename";
+
+/// Clone the document and add `__typename` to every selection set on an interface or union.
+///
+/// Abstract selection sets are otherwise ambiguous in the response: without `__typename`
+/// there is no way to tell which concrete type a given object belongs to.
+///
+/// Returns `None` when every abstract selection set already selects `__typename`.
+pub(crate) fn inject_typename(document: &Document, schema: &Schema) -> Option<Document> {
+    let mut doc = document.clone();
+    let mut changed = false;
+    for def in &mut doc.definitions {
+        match def {
+            Definition::OperationDefinition(op) => {
+                if let Some(root_type) = schema.root_operation(op.operation_type).cloned() {
+                    let op = Node::make_mut(op);
+                    changed |= inject_into_selection_set(&mut op.selection_set, &root_type, schema);
+                }
+            }
+            Definition::FragmentDefinition(frag) => {
+                let type_condition = frag.type_condition.clone();
+                let frag = Node::make_mut(frag);
+                changed |=
+                    inject_into_selection_set(&mut frag.selection_set, &type_condition, schema);
+            }
+            _ => {}
+        }
+    }
+    changed.then_some(doc)
+}
+
+/// Parse a raw query string and add `__typename` to its abstract selection sets.
+///
+/// Returns `None` if parsing fails or nothing needed to be injected.
+pub(crate) fn inject_typename_into_query(query: &str, schema: &Schema) -> Option<String> {
+    // Parse failure returns None (no injection). The invalid query will still be
+    // forwarded and will fail at the GraphQL endpoint with a proper error.
+    let document = Parser::new().parse_ast(query, "query.graphql").ok()?;
+    inject_typename(&document, schema).map(|doc| doc.serialize().no_indent().to_string())
+}
+
+fn inject_into_selection_set(selections: &mut [Selection], parent: &str, schema: &Schema) -> bool {
+    let mut changed = false;
+    for selection in selections.iter_mut() {
+        match selection {
+            Selection::Field(field) => {
+                if field.selection_set.is_empty() {
+                    continue;
+                }
+                let Ok(field_def) = schema.type_field(parent, &field.name) else {
+                    continue;
+                };
+                let field_type = field_def.ty.inner_named_type().clone();
+                let field = Node::make_mut(field);
+                if is_abstract(schema, &field_type) && !selects_typename(&field.selection_set) {
+                    field
+                        .selection_set
+                        .insert(0, Selection::Field(Node::new(typename_field())));
+                    changed = true;
+                }
+                changed |= inject_into_selection_set(&mut field.selection_set, &field_type, schema);
+            }
+            Selection::InlineFragment(inline) => {
+                let target = inline
+                    .type_condition
+                    .as_ref()
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| parent.to_string());
+                let inline = Node::make_mut(inline);
+                changed |= inject_into_selection_set(&mut inline.selection_set, &target, schema);
+            }
+            // Named fragments are handled when visiting their definitions
+            Selection::FragmentSpread(_) => {}
+        }
+    }
+    changed
+}
+
+fn is_abstract(schema: &Schema, type_name: &str) -> bool {
+    matches!(
+        schema.types.get(type_name),
+        Some(ExtendedType::Interface(_) | ExtendedType::Union(_))
+    )
+}
+
+fn selects_typename(selections: &[Selection]) -> bool {
+    selections.iter().any(|selection| {
+        matches!(selection, Selection::Field(field) if field.name == TYPENAME_FIELD && field.alias.is_none())
+    })
+}
+
+fn typename_field() -> Field {
+    Field {
+        alias: None,
+        name: name!("__typename"),
+        arguments: Vec::new(),
+        directives: Default::default(),
+        selection_set: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::parse_and_validate(
+            r#"
+            type Query {
+                node(id: ID!): Node
+                search(term: String!): [SearchResult!]!
+                user(id: ID!): User
+            }
+
+            interface Node {
+                id: ID!
+            }
+
+            type User implements Node {
+                id: ID!
+                name: String!
+                friends: [Node!]!
+            }
+
+            type Post implements Node {
+                id: ID!
+                title: String!
+            }
+
+            union SearchResult = User | Post
+            "#,
+            "schema.graphql",
+        )
+        .unwrap()
+        .into_inner()
+    }
+
+    #[test]
+    fn injects_typename_into_interface_selection() {
+        let query = inject_typename_into_query(
+            "query GetNode($id: ID!) { node(id: $id) { id } }",
+            &schema(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            "query GetNode($id: ID!) { node(id: $id) { __typename id } }"
+        );
+    }
+
+    #[test]
+    fn injects_typename_into_union_and_nested_selections() {
+        let query = inject_typename_into_query(
+            "query Search { search(term: \"a\") { ... on User { name friends { id } } ... on Post { title } } }",
+            &schema(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            "query Search { search(term: \"a\") { __typename ... on User { name friends { __typename id } } ... on Post { title } } }"
+        );
+    }
+
+    #[test]
+    fn injects_typename_into_fragment_definitions() {
+        let query = inject_typename_into_query(
+            "query GetUser { user(id: \"1\") { ...UserFields } } fragment UserFields on User { friends { id } }",
+            &schema(),
+        )
+        .unwrap();
+
+        assert!(query.contains("fragment UserFields on User { friends { __typename id } }"));
+    }
+
+    #[test]
+    fn leaves_existing_typename_alone() {
+        assert_eq!(
+            inject_typename_into_query(
+                "query GetNode { node(id: \"1\") { __typename id } }",
+                &schema(),
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn leaves_object_selections_alone() {
+        assert_eq!(
+            inject_typename_into_query("query GetUser { user(id: \"1\") { id name } }", &schema()),
+            None
+        );
+    }
+
+    #[test]
+    fn aliased_typename_does_not_count() {
+        let query = inject_typename_into_query(
+            "query GetNode { node(id: \"1\") { kind: __typename id } }",
+            &schema(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            query,
+            "query GetNode { node(id: \"1\") { __typename kind: __typename id } }"
+        );
+    }
+}
@@ -0,0 +1,467 @@
+//! Authorization policies for tools
+//!
+//! Scopes only say what a token may do in general. Policies add rules on the claims of the
+//! validated token for specific tools, operation types, or tools with certain annotations, such
+//! as "mutations require an `acr` of at least `mfa`". Tools that a caller isn't allowed to use
+//! are hidden from `tools/list` and rejected by `tools/call`.
+
+use std::fmt;
+
+use apollo_compiler::ast::OperationType;
+use rmcp::model::{ErrorCode, ToolAnnotations};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+use crate::auth::{ValidToken, lookup_claim};
+use crate::errors::McpError;
+
+/// JSON-RPC error code returned when a policy denies a tool call
+pub const POLICY_DENIED: ErrorCode = ErrorCode(-32030);
+
+/// A rule that tools matching its selectors may only be used by callers meeting its conditions
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// The name of the rule, reported in audit logs and errors
+    pub name: String,
+
+    /// Tools the rule applies to (default: every tool)
+    #[serde(default)]
+    pub tools: Vec<String>,
+
+    /// Operation types the rule applies to (default: every type). Only tools whose operation
+    /// type is known are matched, so the `execute` tool is matched when it's called, but never
+    /// hidden from `tools/list`.
+    #[serde(default)]
+    pub operation_types: Vec<PolicyOperationType>,
+
+    /// Tool annotation hints the rule applies to (default: any)
+    #[serde(default)]
+    pub annotations: AnnotationSelector,
+
+    /// Conditions on the claims of the access token, all of which must hold
+    pub require: Vec<ClaimCondition>,
+}
+
+/// A GraphQL operation type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyOperationType {
+    Query,
+    Mutation,
+    Subscription,
+}
+
+impl From<OperationType> for PolicyOperationType {
+    fn from(operation_type: OperationType) -> Self {
+        match operation_type {
+            OperationType::Query => Self::Query,
+            OperationType::Mutation => Self::Mutation,
+            OperationType::Subscription => Self::Subscription,
+        }
+    }
+}
+
+/// Tool annotation hints to match. Unset hints match any value.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AnnotationSelector {
+    pub read_only: Option<bool>,
+    pub destructive: Option<bool>,
+    pub idempotent: Option<bool>,
+    pub open_world: Option<bool>,
+}
+
+impl AnnotationSelector {
+    fn matches(&self, annotations: Option<&ToolAnnotations>) -> bool {
+        let hint = |selected: Option<bool>, hint: fn(&ToolAnnotations) -> Option<bool>| {
+            selected.is_none_or(|selected| annotations.and_then(hint) == Some(selected))
+        };
+        hint(self.read_only, |a| a.read_only_hint)
+            && hint(self.destructive, |a| a.destructive_hint)
+            && hint(self.idempotent, |a| a.idempotent_hint)
+            && hint(self.open_world, |a| a.open_world_hint)
+    }
+}
+
+/// A condition on one claim of the access token. Without any comparison, the claim only has to
+/// be present.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ClaimCondition {
+    /// The claim, with `.` separating the segments of nested claims
+    pub claim: String,
+
+    /// The claim must equal this value
+    pub equals: Option<Value>,
+
+    /// The claim must equal one of these values
+    pub one_of: Option<Vec<Value>>,
+
+    /// The claim must be an array containing this value, or a space-separated string (such as
+    /// `scope`) containing this word
+    pub contains: Option<Value>,
+
+    /// The claim must be this level or a later one in `levels`
+    pub at_least: Option<String>,
+
+    /// Levels for `at_least`, from lowest to highest
+    #[serde(default)]
+    pub levels: Vec<String>,
+}
+
+impl ClaimCondition {
+    /// Check the condition, explaining why it fails
+    fn check(&self, token: Option<&ValidToken>) -> Result<(), String> {
+        let claim = &self.claim;
+        let value = token
+            .and_then(|token| lookup_claim(&token.claims, claim))
+            .ok_or_else(|| format!("claim `{claim}` is missing"))?;
+
+        if let Some(expected) = &self.equals
+            && value != expected
+        {
+            return Err(format!("claim `{claim}` is {value}, not {expected}"));
+        }
+        if let Some(allowed) = &self.one_of
+            && !allowed.contains(value)
+        {
+            return Err(format!("claim `{claim}` is {value}, which is not allowed"));
+        }
+        if let Some(expected) = &self.contains {
+            let contained = match (value, expected) {
+                (Value::Array(values), _) => values.contains(expected),
+                (Value::String(words), Value::String(word)) => {
+                    words.split_whitespace().any(|w| w == word)
+                }
+                _ => false,
+            };
+            if !contained {
+                return Err(format!("claim `{claim}` does not contain {expected}"));
+            }
+        }
+        if let Some(minimum) = &self.at_least {
+            let rank = |level: &str| self.levels.iter().position(|l| l == level);
+            let sufficient = match (value.as_str().and_then(rank), rank(minimum)) {
+                (Some(level), Some(minimum)) => level >= minimum,
+                _ => false,
+            };
+            if !sufficient {
+                return Err(format!("claim `{claim}` is {value}, below `{minimum}`"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tool a policy decision is about
+pub(crate) struct PolicyTarget<'a> {
+    pub(crate) tool: &'a str,
+    pub(crate) annotations: Option<&'a ToolAnnotations>,
+    /// The type of the operation the tool runs, if known
+    pub(crate) operation_type: Option<OperationType>,
+}
+
+/// A tool call or listing was denied by a policy rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Denied {
+    pub tool: String,
+    pub rule: String,
+    pub reason: String,
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Access to tool {} is denied by policy `{}`.",
+            self.tool, self.rule
+        )
+    }
+}
+
+impl From<Denied> for McpError {
+    fn from(denied: Denied) -> Self {
+        McpError::new(
+            POLICY_DENIED,
+            denied.to_string(),
+            Some(json!({ "policy": denied.rule })),
+        )
+    }
+}
+
+/// The configured policy rules
+#[derive(Debug, Clone)]
+pub struct Policies {
+    rules: Vec<PolicyRule>,
+}
+
+impl Policies {
+    /// Create the policies, or `None` if no rules are configured
+    pub fn new(rules: Vec<PolicyRule>) -> Option<Self> {
+        for rule in &rules {
+            for condition in &rule.require {
+                if let Some(minimum) = &condition.at_least
+                    && !condition.levels.contains(minimum)
+                {
+                    warn!(
+                        rule = rule.name,
+                        claim = condition.claim,
+                        "Policy condition requires level {minimum:?}, which is not one of its `levels`, so it never holds"
+                    );
+                }
+            }
+        }
+        (!rules.is_empty()).then_some(Self { rules })
+    }
+
+    /// Whether the caller may see `target` in `tools/list`
+    pub(crate) fn allows_listing(&self, target: &PolicyTarget, token: Option<&ValidToken>) -> bool {
+        match self.decide(target, token) {
+            Ok(_) => true,
+            Err(denied) => {
+                debug!(
+                    subject = subject(token),
+                    tool = denied.tool,
+                    rule = denied.rule,
+                    reason = denied.reason,
+                    "Policy hid tool from tool list"
+                );
+                false
+            }
+        }
+    }
+
+    /// Check that the caller may call `target`, recording the decision in the audit log
+    pub(crate) fn check_call(
+        &self,
+        target: &PolicyTarget,
+        token: Option<&ValidToken>,
+    ) -> Result<(), Denied> {
+        match self.decide(target, token) {
+            Ok(rules) => {
+                if !rules.is_empty() {
+                    info!(
+                        subject = subject(token),
+                        tool = target.tool,
+                        rules = ?rules,
+                        decision = "allow",
+                        "Policy decision"
+                    );
+                }
+                Ok(())
+            }
+            Err(denied) => {
+                info!(
+                    subject = subject(token),
+                    tool = denied.tool,
+                    rule = denied.rule,
+                    reason = denied.reason,
+                    decision = "deny",
+                    "Policy decision"
+                );
+                Err(denied)
+            }
+        }
+    }
+
+    /// Evaluate every rule that applies to `target`, returning the names of the rules that
+    /// allowed it, or the first rule that denied it
+    fn decide(
+        &self,
+        target: &PolicyTarget,
+        token: Option<&ValidToken>,
+    ) -> Result<Vec<&str>, Denied> {
+        let mut applied = Vec::new();
+        for rule in self.rules.iter().filter(|rule| applies(rule, target)) {
+            if let Err(reason) = rule
+                .require
+                .iter()
+                .try_for_each(|condition| condition.check(token))
+            {
+                return Err(Denied {
+                    tool: target.tool.to_string(),
+                    rule: rule.name.clone(),
+                    reason,
+                });
+            }
+            applied.push(rule.name.as_str());
+        }
+        Ok(applied)
+    }
+}
+
+fn applies(rule: &PolicyRule, target: &PolicyTarget) -> bool {
+    (rule.tools.is_empty() || rule.tools.iter().any(|tool| tool == target.tool))
+        && (rule.operation_types.is_empty()
+            || target.operation_type.is_some_and(|operation_type| {
+                rule.operation_types.contains(&operation_type.into())
+            }))
+        && rule.annotations.matches(target.annotations)
+}
+
+fn subject(token: Option<&ValidToken>) -> &str {
+    token.map_or("anonymous", |token| token.subject.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use headers::Authorization;
+
+    use super::*;
+
+    fn token(claims: Value) -> ValidToken {
+        ValidToken {
+            token: Authorization::bearer("token").unwrap(),
+            subject: "alice".to_string(),
+            scopes: vec![],
+            claims: claims.as_object().unwrap().clone(),
+            api_key: false,
+        }
+    }
+
+    fn policies(yaml: &str) -> Policies {
+        Policies::new(serde_yaml::from_str(yaml).unwrap()).unwrap()
+    }
+
+    fn target(tool: &str, operation_type: Option<OperationType>) -> PolicyTarget<'_> {
+        PolicyTarget {
+            tool,
+            annotations: None,
+            operation_type,
+        }
+    }
+
+    #[test]
+    fn no_policies_without_rules() {
+        assert!(Policies::new(vec![]).is_none());
+    }
+
+    #[test]
+    fn tool_requires_claim_value() {
+        let policies = policies(
+            r#"
+            - name: finance-only
+              tools: [GetInvoices]
+              require:
+                - claim: department
+                  equals: finance
+            "#,
+        );
+        let finance = token(json!({"department": "finance"}));
+        let sales = token(json!({"department": "sales"}));
+
+        assert!(
+            policies
+                .check_call(&target("GetInvoices", None), Some(&finance))
+                .is_ok()
+        );
+        let denied = policies
+            .check_call(&target("GetInvoices", None), Some(&sales))
+            .unwrap_err();
+        assert_eq!(denied.rule, "finance-only");
+        assert_eq!(
+            denied.reason,
+            r#"claim `department` is "sales", not "finance""#
+        );
+        // Other tools and anonymous callers of other tools are unaffected
+        assert!(
+            policies
+                .check_call(&target("GetUser", None), Some(&sales))
+                .is_ok()
+        );
+        assert!(policies.check_call(&target("GetUser", None), None).is_ok());
+    }
+
+    #[test]
+    fn mutations_require_minimum_level() {
+        let policies = policies(
+            r#"
+            - name: mutations-need-mfa
+              operation_types: [mutation]
+              require:
+                - claim: acr
+                  at_least: mfa
+                  levels: [pwd, mfa, hwk]
+            "#,
+        );
+        let mutation = target("UpdateUser", Some(OperationType::Mutation));
+
+        assert!(
+            policies
+                .check_call(&mutation, Some(&token(json!({"acr": "hwk"}))))
+                .is_ok()
+        );
+        assert!(
+            policies
+                .check_call(&mutation, Some(&token(json!({"acr": "pwd"}))))
+                .is_err()
+        );
+        assert!(
+            policies
+                .check_call(&mutation, Some(&token(json!({"acr": "unknown"}))))
+                .is_err()
+        );
+        // Tools with an unknown operation type, such as `execute` in a tool list, don't match
+        assert!(policies.allows_listing(&target("execute", None), None));
+        assert!(policies.allows_listing(&target("GetUser", Some(OperationType::Query)), None));
+    }
+
+    #[test]
+    fn contains_matches_arrays_and_space_separated_strings() {
+        let policies = policies(
+            r#"
+            - name: execute-admins
+              tools: [execute]
+              require:
+                - claim: groups
+                  contains: admins
+            - name: execute-scope
+              tools: [execute]
+              require:
+                - claim: scope
+                  contains: graphql:execute
+            "#,
+        );
+        let admin = token(json!({"groups": ["users", "admins"], "scope": "read graphql:execute"}));
+        let user = token(json!({"groups": ["users"], "scope": "read graphql:execute"}));
+
+        assert!(policies.allows_listing(&target("execute", None), Some(&admin)));
+        assert!(!policies.allows_listing(&target("execute", None), Some(&user)));
+        assert!(!policies.allows_listing(&target("execute", None), None));
+    }
+
+    #[test]
+    fn matches_tool_annotations() {
+        let policies = policies(
+            r#"
+            - name: destructive-needs-approval
+              annotations:
+                destructive: true
+              require:
+                - claim: approved
+            "#,
+        );
+        let destructive = ToolAnnotations::new().destructive(true);
+        let read_only = ToolAnnotations::new().read_only(true);
+
+        assert!(!policies.allows_listing(
+            &PolicyTarget {
+                tool: "DeleteUser",
+                annotations: Some(&destructive),
+                operation_type: None,
+            },
+            Some(&token(json!({})))
+        ));
+        assert!(policies.allows_listing(
+            &PolicyTarget {
+                tool: "GetUser",
+                annotations: Some(&read_only),
+                operation_type: None,
+            },
+            Some(&token(json!({})))
+        ));
+    }
+}
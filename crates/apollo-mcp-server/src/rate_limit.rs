@@ -0,0 +1,514 @@
+//! Token-bucket rate limiting for tool calls
+//!
+//! Limits can be configured per authenticated subject, per MCP session, per client IP address
+//! and per tool name. A tool call must be allowed by every limit that applies to it.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use axum::extract::ConnectInfo;
+use http::request::Parts;
+use opentelemetry::KeyValue;
+use parking_lot::Mutex;
+use rmcp::model::ErrorCode;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::debug;
+
+use crate::auth::ValidToken;
+use crate::errors::McpError;
+use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
+use crate::meter;
+
+/// JSON-RPC error code returned when a tool call is throttled
+pub const RATE_LIMITED: ErrorCode = ErrorCode(-32029);
+
+/// Idle buckets are pruned once more than this many are being tracked
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// Buckets tracked by the limiter
+#[derive(Debug)]
+struct Buckets {
+    entries: HashMap<(RateLimitScope, String), Bucket>,
+    /// Idle buckets are next pruned once there are more entries than this. After a prune it is
+    /// twice the number of buckets still in use, so that the cost of each prune is spread over
+    /// the calls that added buckets since the last one.
+    prune_at: usize,
+}
+
+impl Default for Buckets {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            prune_at: PRUNE_THRESHOLD,
+        }
+    }
+}
+
+/// A token bucket that allows `requests` calls every `per`, with bursts of up to `requests`
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BucketConfig {
+    /// The number of calls allowed in each period
+    pub requests: u32,
+
+    /// The length of the period
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[serde(serialize_with = "humantime_serde::serialize")]
+    #[schemars(with = "String")]
+    pub per: Duration,
+}
+
+/// Rate limits for tool calls
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Limit for each authenticated subject (the `sub` claim of the access token)
+    pub subject: Option<BucketConfig>,
+
+    /// Limit for each MCP session
+    pub session: Option<BucketConfig>,
+
+    /// Limit for each client IP address
+    pub client_ip: Option<BucketConfig>,
+
+    /// Limit applied to every tool, shared by all clients
+    pub tool: Option<BucketConfig>,
+
+    /// Limits for specific tools, overriding `tool`
+    pub tools: HashMap<String, BucketConfig>,
+}
+
+impl RateLimitConfig {
+    fn is_empty(&self) -> bool {
+        self.subject.is_none()
+            && self.session.is_none()
+            && self.client_ip.is_none()
+            && self.tool.is_none()
+            && self.tools.is_empty()
+    }
+}
+
+/// What a rate limit is keyed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitScope {
+    Subject,
+    Session,
+    ClientIp,
+    Tool,
+}
+
+impl RateLimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::Subject => "subject",
+            RateLimitScope::Session => "session",
+            RateLimitScope::ClientIp => "client_ip",
+            RateLimitScope::Tool => "tool",
+        }
+    }
+}
+
+/// A tool call was rejected by a rate limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throttled {
+    pub scope: RateLimitScope,
+    pub retry_after: Duration,
+}
+
+impl Throttled {
+    fn retry_after_secs(&self) -> u64 {
+        self.retry_after.as_secs() + u64::from(self.retry_after.subsec_nanos() > 0)
+    }
+}
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rate limit exceeded for {}. Retry after {}s.",
+            self.scope.as_str(),
+            self.retry_after_secs()
+        )
+    }
+}
+
+impl From<Throttled> for McpError {
+    fn from(throttled: Throttled) -> Self {
+        McpError::new(
+            RATE_LIMITED,
+            throttled.to_string(),
+            Some(json!({
+                "scope": throttled.scope.as_str(),
+                "retryAfter": throttled.retry_after_secs(),
+            })),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(config: &BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(config.requests),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, config: &BucketConfig, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate(config)).min(f64::from(config.requests));
+        self.updated = now;
+    }
+
+    /// How long until a token is available, or `None` if one is available now
+    fn wait(&self, config: &BucketConfig) -> Option<Duration> {
+        if self.tokens >= 1.0 {
+            return None;
+        }
+        let rate = rate(config);
+        if rate <= 0.0 {
+            return Some(config.per);
+        }
+        Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+    }
+}
+
+/// Tokens added per second
+fn rate(config: &BucketConfig) -> f64 {
+    let per = config.per.as_secs_f64();
+    if per > 0.0 {
+        f64::from(config.requests) / per
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Rate limiter shared by every session of the server
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: Arc<RateLimitConfig>,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter, or `None` if no limits are configured
+    pub fn new(config: RateLimitConfig) -> Option<Self> {
+        (!config.is_empty()).then(|| Self {
+            config: Arc::new(config),
+            buckets: Default::default(),
+        })
+    }
+
+    /// Take a token for a tool call from every bucket that applies to it.
+    ///
+    /// Tokens are only taken when every bucket allows the call.
+    pub fn check(&self, tool_name: &str, parts: Option<&Parts>) -> Result<(), Throttled> {
+        let keys = self.keys(tool_name, parts);
+        if keys.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        if buckets.entries.len() > buckets.prune_at {
+            self.prune(&mut buckets.entries, now);
+            buckets.prune_at = (buckets.entries.len() * 2).max(PRUNE_THRESHOLD);
+        }
+
+        let mut throttled: Option<Throttled> = None;
+        for (scope, key, config) in &keys {
+            let bucket = buckets
+                .entries
+                .entry((*scope, key.clone()))
+                .or_insert_with(|| Bucket::full(config, now));
+            bucket.refill(config, now);
+            if let Some(wait) = bucket.wait(config)
+                && throttled.as_ref().is_none_or(|t| wait > t.retry_after)
+            {
+                throttled = Some(Throttled {
+                    scope: *scope,
+                    retry_after: wait,
+                });
+            }
+        }
+
+        if let Some(throttled) = throttled {
+            debug!(
+                tool = tool_name,
+                scope = throttled.scope.as_str(),
+                retry_after = ?throttled.retry_after,
+                "Tool call throttled"
+            );
+            let attributes = [
+                KeyValue::new(TelemetryAttribute::ToolName.to_key(), tool_name.to_string()),
+                KeyValue::new(
+                    TelemetryAttribute::RateLimitScope.to_key(),
+                    throttled.scope.as_str(),
+                ),
+            ];
+            meter::METER
+                .u64_counter(TelemetryMetric::RateLimitThrottledCount.as_str())
+                .build()
+                .add(1, &attributes);
+            return Err(throttled);
+        }
+
+        for (scope, key, _) in keys {
+            if let Some(bucket) = buckets.entries.get_mut(&(scope, key)) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    fn keys(
+        &self,
+        tool_name: &str,
+        parts: Option<&Parts>,
+    ) -> Vec<(RateLimitScope, String, &BucketConfig)> {
+        let mut keys = Vec::new();
+        if let Some(config) = &self.config.subject
+            && let Some(token) = parts.and_then(|p| p.extensions.get::<ValidToken>())
+        {
+            keys.push((RateLimitScope::Subject, token.subject.clone(), config));
+        }
+        if let Some(config) = &self.config.session
+            && let Some(session_id) = parts
+                .and_then(|p| p.headers.get("mcp-session-id"))
+                .and_then(|v| v.to_str().ok())
+        {
+            keys.push((RateLimitScope::Session, session_id.to_string(), config));
+        }
+        if let Some(config) = &self.config.client_ip
+            && let Some(ConnectInfo(address)) =
+                parts.and_then(|p| p.extensions.get::<ConnectInfo<SocketAddr>>())
+        {
+            keys.push((RateLimitScope::ClientIp, address.ip().to_string(), config));
+        }
+        if let Some(config) = self
+            .config
+            .tools
+            .get(tool_name)
+            .or(self.config.tool.as_ref())
+        {
+            keys.push((RateLimitScope::Tool, tool_name.to_string(), config));
+        }
+        keys
+    }
+
+    /// Forget buckets that have refilled completely, since they behave like new ones
+    fn prune(&self, buckets: &mut HashMap<(RateLimitScope, String), Bucket>, now: Instant) {
+        buckets.retain(|(scope, key), bucket| {
+            let config = match scope {
+                RateLimitScope::Subject => self.config.subject.as_ref(),
+                RateLimitScope::Session => self.config.session.as_ref(),
+                RateLimitScope::ClientIp => self.config.client_ip.as_ref(),
+                RateLimitScope::Tool => self.config.tools.get(key).or(self.config.tool.as_ref()),
+            };
+            config.is_some_and(|config| {
+                bucket.refill(config, now);
+                bucket.tokens < f64::from(config.requests)
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use headers::Authorization;
+    use http::Request;
+
+    fn bucket(requests: u32, per: Duration) -> Option<BucketConfig> {
+        Some(BucketConfig { requests, per })
+    }
+
+    fn parts(session_id: &str, subject: Option<&str>) -> Parts {
+        let (mut parts, _) = Request::builder()
+            .header("mcp-session-id", session_id)
+            .body(())
+            .unwrap()
+            .into_parts();
+        if let Some(subject) = subject {
+            parts.extensions.insert(ValidToken {
+                token: Authorization::bearer("token").unwrap(),
+                subject: subject.to_string(),
+                scopes: vec![],
+                claims: Default::default(),
+                api_key: false,
+            });
+        }
+        parts
+    }
+
+    #[test]
+    fn no_limiter_without_limits() {
+        assert!(RateLimiter::new(RateLimitConfig::default()).is_none());
+    }
+
+    #[test]
+    fn throttles_per_session() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            session: bucket(2, Duration::from_secs(60)),
+            ..Default::default()
+        })
+        .unwrap();
+        let first = parts("first", None);
+
+        assert!(limiter.check("GetUser", Some(&first)).is_ok());
+        assert!(limiter.check("GetUser", Some(&first)).is_ok());
+        let throttled = limiter.check("GetUser", Some(&first)).unwrap_err();
+        assert_eq!(throttled.scope, RateLimitScope::Session);
+        assert!(throttled.retry_after > Duration::from_secs(29));
+
+        // Other sessions have their own bucket
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("second", None)))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn throttles_per_subject_across_sessions() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            subject: bucket(1, Duration::from_secs(60)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("first", Some("alice"))))
+                .is_ok()
+        );
+        assert_eq!(
+            limiter
+                .check("GetUser", Some(&parts("second", Some("alice"))))
+                .unwrap_err()
+                .scope,
+            RateLimitScope::Subject
+        );
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("first", Some("bob"))))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn tool_overrides_take_precedence() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            tool: bucket(100, Duration::from_secs(1)),
+            tools: HashMap::from([(
+                "ExportReport".to_string(),
+                BucketConfig {
+                    requests: 1,
+                    per: Duration::from_secs(60),
+                },
+            )]),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(limiter.check("ExportReport", None).is_ok());
+        assert!(limiter.check("ExportReport", None).is_err());
+        assert!(limiter.check("GetUser", None).is_ok());
+        assert!(limiter.check("GetUser", None).is_ok());
+    }
+
+    #[test]
+    fn does_not_take_tokens_when_throttled() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            session: bucket(1, Duration::from_secs(60)),
+            tool: bucket(2, Duration::from_secs(60)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("first", None)))
+                .is_ok()
+        );
+        // Throttled by the session limit, so the tool bucket keeps its token
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("first", None)))
+                .is_err()
+        );
+        assert!(
+            limiter
+                .check("GetUser", Some(&parts("second", None)))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            tool: bucket(1, Duration::from_millis(20)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(limiter.check("GetUser", None).is_ok());
+        assert!(limiter.check("GetUser", None).is_err());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.check("GetUser", None).is_ok());
+    }
+
+    #[test]
+    fn prunes_again_only_after_the_buckets_in_use_double() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            session: bucket(1, Duration::from_secs(60)),
+            ..Default::default()
+        })
+        .unwrap();
+
+        // Every bucket is in use, so pruning keeps them all
+        for session in 0..=PRUNE_THRESHOLD {
+            assert!(
+                limiter
+                    .check("GetUser", Some(&parts(&session.to_string(), None)))
+                    .is_ok()
+            );
+        }
+        assert!(limiter.check("GetUser", Some(&parts("new", None))).is_ok());
+
+        let buckets = limiter.buckets.lock();
+        assert_eq!(buckets.entries.len(), PRUNE_THRESHOLD + 2);
+        assert_eq!(buckets.prune_at, (PRUNE_THRESHOLD + 1) * 2);
+    }
+
+    #[test]
+    fn throttled_error_includes_retry_after() {
+        let error = McpError::from(Throttled {
+            scope: RateLimitScope::ClientIp,
+            retry_after: Duration::from_millis(1500),
+        });
+
+        assert_eq!(error.code, RATE_LIMITED);
+        assert_eq!(
+            error.message,
+            "Rate limit exceeded for client_ip. Retry after 2s."
+        );
+        assert_eq!(
+            error.data,
+            Some(json!({ "scope": "client_ip", "retryAfter": 2 }))
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Configuration of the Rhai scripts that customize the server
+//!
+//! Hooks and script tools run concurrently, each with a copy of the scripts' global variables
+//! taken from a pool. Calls beyond the size of the pool wait for a copy to free up.
+
+use std::time::Duration;
+
+use apollo_mcp_rhai::{
+    DEFAULT_MAX_STORE_SIZE, DEFAULT_POOL_SIZE, Limits, RhaiEngine, ScriptTelemetry, TelemetryValue,
+};
+use opentelemetry::{KeyValue, Value};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::generated::telemetry::{ALL_METRICS, TelemetryMetric};
+use crate::meter;
+
+/// Rhai scripting configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+pub struct RhaiConfig {
+    /// Maximum number of Rhai hooks and script tools that can run at the same time (default: 8)
+    pub pool_size: usize,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[serde(serialize_with = "humantime_serde::serialize")]
+    #[schemars(with = "Option<String>")]
+    /// How long a hook or script tool can run, including time spent waiting for a free copy of the
+    /// global variables and for promises (default: unlimited)
+    pub timeout: Option<Duration>,
+
+    /// Maximum number of operations a hook or script tool can run (default: unlimited)
+    pub max_operations: Option<u64>,
+
+    /// Maximum depth of nested function calls (default: 64)
+    pub max_call_levels: Option<usize>,
+
+    /// Maximum length of strings, in bytes (default: unlimited)
+    pub max_string_size: Option<usize>,
+
+    /// Maximum number of items in arrays (default: unlimited)
+    pub max_array_size: Option<usize>,
+
+    /// Maximum number of properties in object maps (default: unlimited)
+    pub max_map_size: Option<usize>,
+
+    /// Maximum size of the keys and values in the `Store` module, in bytes (default: 10 MiB)
+    pub max_store_size: usize,
+}
+
+impl Default for RhaiConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: DEFAULT_POOL_SIZE,
+            timeout: None,
+            max_operations: None,
+            max_call_levels: None,
+            max_string_size: None,
+            max_array_size: None,
+            max_map_size: None,
+            max_store_size: DEFAULT_MAX_STORE_SIZE,
+        }
+    }
+}
+
+impl RhaiConfig {
+    /// Create the engine that runs the scripts in the `rhai` directory
+    pub(crate) fn engine(&self) -> RhaiEngine {
+        RhaiEngine::new("rhai")
+            .with_pool_size(self.pool_size)
+            .with_max_store_size(self.max_store_size)
+            .with_telemetry(MeterTelemetry)
+            .with_limits(Limits {
+                max_operations: self.max_operations,
+                max_call_levels: self.max_call_levels,
+                max_string_size: self.max_string_size,
+                max_array_size: self.max_array_size,
+                max_map_size: self.max_map_size,
+                timeout: self.timeout,
+            })
+            .with_wait_observer(|wait| {
+                meter::METER
+                    .f64_histogram(TelemetryMetric::RhaiWaitDuration.as_str())
+                    .build()
+                    .record(wait.as_millis() as f64, &[]);
+            })
+    }
+}
+
+/// Records what scripts emit with the `Telemetry` module in the server's meter and spans
+struct MeterTelemetry;
+
+impl MeterTelemetry {
+    /// Keep scripts from recording into the metrics that the server itself records
+    fn check_name(name: &str) -> Result<(), String> {
+        if ALL_METRICS.iter().any(|metric| metric.as_str() == name) {
+            return Err(format!(
+                "Metric name '{name}' is reserved for the server's own metrics"
+            ));
+        }
+        Ok(())
+    }
+
+    fn key_values(attributes: &[(String, TelemetryValue)]) -> Vec<KeyValue> {
+        attributes
+            .iter()
+            .map(|(key, value)| KeyValue::new(key.clone(), Self::value(value.clone())))
+            .collect()
+    }
+
+    fn value(value: TelemetryValue) -> Value {
+        match value {
+            TelemetryValue::Bool(value) => value.into(),
+            TelemetryValue::Int(value) => value.into(),
+            TelemetryValue::Float(value) => value.into(),
+            TelemetryValue::String(value) => value.into(),
+        }
+    }
+}
+
+impl ScriptTelemetry for MeterTelemetry {
+    fn increment(
+        &self,
+        name: &str,
+        value: u64,
+        attributes: &[(String, TelemetryValue)],
+    ) -> Result<(), String> {
+        Self::check_name(name)?;
+        meter::METER
+            .u64_counter(name.to_string())
+            .build()
+            .add(value, &Self::key_values(attributes));
+        Ok(())
+    }
+
+    fn record(
+        &self,
+        name: &str,
+        value: f64,
+        attributes: &[(String, TelemetryValue)],
+    ) -> Result<(), String> {
+        Self::check_name(name)?;
+        meter::METER
+            .f64_histogram(name.to_string())
+            .build()
+            .record(value, &Self::key_values(attributes));
+        Ok(())
+    }
+
+    fn set_span_attribute(&self, key: &str, value: TelemetryValue) {
+        tracing::Span::current().set_attribute(key.to_string(), Self::value(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use apollo_mcp_rhai::ScriptTelemetry as _;
+
+    use std::time::Duration;
+
+    use super::{MeterTelemetry, RhaiConfig};
+    use crate::generated::telemetry::TelemetryMetric;
+
+    #[test]
+    fn scripts_have_no_timeout_unless_configured() {
+        assert_eq!(RhaiConfig::default().timeout, None);
+
+        let config: RhaiConfig = serde_yaml::from_str("timeout: 5s").unwrap();
+        assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn scripts_cannot_record_into_server_metrics() {
+        let err = MeterTelemetry
+            .record(TelemetryMetric::RhaiWaitDuration.as_str(), 1.0, &[])
+            .expect_err("Should reject the server's metric");
+
+        assert_eq!(
+            err,
+            "Metric name 'apollo.mcp.rhai.wait.duration' is reserved for the server's own metrics"
+        );
+    }
+
+    #[test]
+    fn scripts_can_record_their_own_metrics() {
+        assert!(
+            MeterTelemetry
+                .increment("apollo.mcp.rhai.cache.miss", 1, &[])
+                .is_ok()
+        );
+        assert!(
+            MeterTelemetry
+                .record("apollo.mcp.rhai.lookup.duration", 1.5, &[])
+                .is_ok()
+        );
+    }
+}
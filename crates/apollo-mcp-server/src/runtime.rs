@@ -200,6 +200,43 @@ mod test {
                     website_url: None,
                     description: None,
                 },
+                policies: [],
+                upstream_auth: None,
+                concurrency: ConcurrencyConfig {
+                    max_in_flight: None,
+                    max_in_flight_per_tool: None,
+                    tools: {},
+                    queue_size: 100,
+                    queue_timeout: 30s,
+                },
+                rate_limits: RateLimitConfig {
+                    subject: None,
+                    session: None,
+                    client_ip: None,
+                    tool: None,
+                    tools: {},
+                },
+                circuit_breaker: CircuitBreakerConfig {
+                    enabled: false,
+                    failure_threshold: 5,
+                    open_duration: 30s,
+                    half_open_probes: 1,
+                },
+                error_mapping: ErrorMappingConfig {
+                    codes: {},
+                    statuses: {},
+                    hints: {},
+                },
+                rhai: RhaiConfig {
+                    pool_size: 8,
+                    timeout: None,
+                    max_operations: None,
+                    max_call_levels: None,
+                    max_string_size: None,
+                    max_array_size: None,
+                    max_map_size: None,
+                    max_store_size: 10485760,
+                },
                 instructions: None,
                 custom_scalars: None,
                 endpoint: Endpoint(
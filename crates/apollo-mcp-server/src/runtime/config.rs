@@ -1,7 +1,16 @@
 use std::path::PathBuf;
 
 use apollo_mcp_server::{
-    cors::CorsConfig, headers::ForwardHeaders, health::HealthCheckConfig, server::Transport,
+    circuit_breaker::CircuitBreakerConfig,
+    concurrency::ConcurrencyConfig,
+    cors::CorsConfig,
+    error_mapping::ErrorMappingConfig,
+    headers::ForwardHeaders,
+    health::HealthCheckConfig,
+    policy::PolicyRule,
+    rate_limit::RateLimitConfig,
+    rhai::RhaiConfig,
+    server::{Transport, UpstreamAuthConfig},
 };
 use reqwest::header::HeaderMap;
 use schemars::JsonSchema;
@@ -26,6 +35,34 @@ pub struct Config {
     #[serde(default)]
     pub server_info: ServerInfoConfig,
 
+    /// Authorization policies for tools, evaluated against the claims of the access token
+    #[serde(default)]
+    pub policies: Vec<PolicyRule>,
+
+    /// OAuth client credentials the server uses to obtain access tokens for the GraphQL endpoint
+    #[serde(default)]
+    pub upstream_auth: Option<UpstreamAuthConfig>,
+
+    /// Concurrency limits for tool calls
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+
+    /// Rate limits for tool calls
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+
+    /// Circuit breaker for the upstream GraphQL endpoint
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+
+    /// Classification of GraphQL errors into model-facing summaries with remediation hints
+    #[serde(default)]
+    pub error_mapping: ErrorMappingConfig,
+
+    /// Rhai scripting configuration
+    #[serde(default)]
+    pub rhai: RhaiConfig,
+
     /// Optional instructions returned in the MCP `initialize` response (protocol 2025-06-18+).
     /// Clients may inject this into the model context as server-level guidance.
     #[serde(default)]
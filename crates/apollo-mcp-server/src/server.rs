@@ -11,14 +11,22 @@ use serde::Deserialize;
 use url::Url;
 
 use crate::auth;
+use crate::auth::ClaimHeadersConfig;
+pub use crate::auth::UpstreamAuthConfig;
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::concurrency::ConcurrencyConfig;
 use crate::cors::CorsConfig;
 use crate::custom_scalar_map::CustomScalarMap;
+use crate::error_mapping::ErrorMappingConfig;
 use crate::errors::ServerError;
 use crate::event::Event as ServerEvent;
 use crate::headers::ForwardHeaders;
 use crate::health::HealthCheckConfig;
 use crate::host_validation::HostValidationConfig;
 use crate::operations::{AnnotationOverrides, MutationMode, OperationSource};
+use crate::policy::PolicyRule;
+use crate::rate_limit::RateLimitConfig;
+use crate::rhai::RhaiConfig;
 use crate::server_info::ServerInfoConfig;
 
 pub(crate) mod states;
@@ -73,6 +81,14 @@ pub struct Server {
     health_check: HealthCheckConfig,
     cors: CorsConfig,
     server_info: ServerInfoConfig,
+    policies: Vec<PolicyRule>,
+    upstream_auth: Option<UpstreamAuthConfig>,
+    claim_headers: ClaimHeadersConfig,
+    concurrency: ConcurrencyConfig,
+    rate_limits: RateLimitConfig,
+    circuit_breaker: CircuitBreakerConfig,
+    error_mapping: ErrorMappingConfig,
+    rhai: RhaiConfig,
     config_validator: Option<ConfigValidator>,
     instructions: Option<String>,
 }
@@ -158,6 +174,14 @@ impl Server {
         health_check: HealthCheckConfig,
         cors: CorsConfig,
         server_info: ServerInfoConfig,
+        policies: Vec<PolicyRule>,
+        upstream_auth: Option<UpstreamAuthConfig>,
+        claim_headers: ClaimHeadersConfig,
+        concurrency: ConcurrencyConfig,
+        rate_limits: RateLimitConfig,
+        circuit_breaker: CircuitBreakerConfig,
+        error_mapping: ErrorMappingConfig,
+        rhai: RhaiConfig,
         config_validator: Option<ConfigValidator>,
         instructions: Option<String>,
     ) -> Self {
@@ -199,6 +223,14 @@ impl Server {
             health_check,
             cors,
             server_info,
+            policies,
+            upstream_auth,
+            claim_headers,
+            concurrency,
+            rate_limits,
+            circuit_breaker,
+            error_mapping,
+            rhai,
             config_validator,
             instructions,
         }
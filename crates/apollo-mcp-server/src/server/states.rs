@@ -11,12 +11,19 @@ use tracing::info;
 use url::Url;
 
 use crate::{
+    auth::{ClaimHeadersConfig, UpstreamAuthConfig},
+    circuit_breaker::CircuitBreakerConfig,
+    concurrency::ConcurrencyConfig,
     cors::CorsConfig,
     custom_scalar_map::CustomScalarMap,
+    error_mapping::ErrorMappingConfig,
     errors::{OperationError, ServerError},
     headers::ForwardHeaders,
     health::HealthCheckConfig,
     operations::{AnnotationOverrides, MutationMode},
+    policy::PolicyRule,
+    rate_limit::RateLimitConfig,
+    rhai::RhaiConfig,
     server_info::ServerInfoConfig,
 };
 
@@ -68,6 +75,14 @@ struct Config {
     health_check: HealthCheckConfig,
     cors: CorsConfig,
     server_info: ServerInfoConfig,
+    policies: Vec<PolicyRule>,
+    upstream_auth: Option<UpstreamAuthConfig>,
+    claim_headers: ClaimHeadersConfig,
+    concurrency: ConcurrencyConfig,
+    rate_limits: RateLimitConfig,
+    circuit_breaker: CircuitBreakerConfig,
+    error_mapping: ErrorMappingConfig,
+    rhai: RhaiConfig,
     instructions: Option<String>,
 }
 
@@ -124,6 +139,14 @@ impl StateMachine {
                 health_check: server.health_check,
                 cors: server.cors,
                 server_info: server.server_info,
+                policies: server.policies,
+                upstream_auth: server.upstream_auth,
+                claim_headers: server.claim_headers,
+                concurrency: server.concurrency,
+                rate_limits: server.rate_limits,
+                circuit_breaker: server.circuit_breaker,
+                error_mapping: server.error_mapping,
+                rhai: server.rhai,
                 instructions: server.instructions,
             },
         });
@@ -222,7 +245,7 @@ impl StateMachine {
             },
             ServerEvent::RhaiScriptsChanged => match state {
                 State::Running(running) => {
-                    running.reload_rhai_scripts();
+                    running.reload_rhai_scripts().await;
                     running.into()
                 }
                 other => other,
@@ -462,7 +485,7 @@ mod tests {
             .unwrap();
 
         Running {
-            schema: Arc::new(RwLock::new(schema)),
+            schema: Arc::new(RwLock::new(Arc::new(schema))),
             operations: Arc::new(RwLock::new(vec![])),
             apps: vec![],
             prompts: vec![],
@@ -486,8 +509,14 @@ mod tests {
             annotations: HashMap::new(),
             health_check: None,
             server_info: ServerInfoConfig::default(),
+            claim_headers: Default::default(),
+            upstream: Default::default(),
+            rate_limiter: None,
+            policies: None,
+            concurrency_limiter: None,
             instructions: None,
-            rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+            rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+            listed_tool_names: Default::default(),
         }
     }
 
@@ -528,6 +557,14 @@ mod tests {
             health_check: HealthCheckConfig::default(),
             cors: CorsConfig::default(),
             server_info: ServerInfoConfig::default(),
+            policies: Default::default(),
+            upstream_auth: Default::default(),
+            claim_headers: Default::default(),
+            concurrency: Default::default(),
+            rate_limits: Default::default(),
+            circuit_breaker: Default::default(),
+            error_mapping: Default::default(),
+            rhai: Default::default(),
             instructions: None,
         }
     }
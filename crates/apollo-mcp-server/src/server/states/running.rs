@@ -1,15 +1,17 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::Arc;
 
-use apollo_compiler::{Schema, validation::Valid};
+use apollo_compiler::{Schema, ast::OperationType, validation::Valid};
+use headers::HeaderMapExt;
 use opentelemetry::KeyValue;
-use parking_lot::Mutex;
 use reqwest::header::HeaderMap;
 use rmcp::ErrorData;
 use rmcp::model::{
     ClientCapabilities, Extensions, GetPromptRequestParams, GetPromptResult, Implementation,
-    ListPromptsResult, ListResourcesResult, PromptMessage, PromptMessageRole, PromptsCapability,
-    ReadResourceResult, ResourcesCapability, ToolsCapability,
+    JsonObject, ListPromptsResult, ListResourcesResult, PromptMessage, PromptMessageRole,
+    PromptsCapability, ReadResourceResult, ResourcesCapability, Tool, ToolAnnotations,
+    ToolsCapability,
 };
 use rmcp::{
     Peer, RoleServer, ServerHandler, ServiceError,
@@ -21,9 +23,10 @@ use rmcp::{
     service::RequestContext,
 };
 use serde_json::Value;
+use sha2::{Digest as _, Sha256};
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use url::Url;
 
 use crate::apps::app::AppTarget;
@@ -31,13 +34,18 @@ use crate::apps::resource::{attach_resource_mime_type, get_app_resource};
 use crate::apps::tool::{attach_tool_metadata, find_and_execute_app_tool, make_tool_private};
 use crate::generated::telemetry::{TelemetryAttribute, TelemetryMetric};
 use crate::meter;
-use crate::operations::{execute_operation, find_and_execute_operation};
+use crate::operations::{
+    ScriptOperations, execute_operation, find_and_execute_operation, operation_defs,
+};
 use crate::server::states::telemetry::get_parent_span;
 use crate::server_info::ServerInfoConfig;
 use crate::{
+    auth::{ClaimHeadersConfig, ValidToken},
+    concurrency::{ConcurrencyLimiter, ConcurrencyPermit},
     custom_scalar_map::CustomScalarMap,
     errors::McpError,
     explorer::{EXPLORER_TOOL_NAME, Explorer},
+    graphql::Upstream,
     headers::{ForwardHeaders, build_request_headers},
     health::HealthCheck,
     introspection::tools::{
@@ -47,12 +55,15 @@ use crate::{
         validate::{VALIDATE_TOOL_NAME, Validate},
     },
     operations::{AnnotationOverrides, MutationMode, Operation, RawOperation},
+    policy::{Policies, PolicyTarget},
+    rate_limit::RateLimiter,
 };
 use apollo_mcp_rhai::RhaiEngine;
+use apollo_mcp_rhai::checkpoints::{self, ListedTool, OperationsUpdate, SchemaUpdate};
 
 #[derive(Clone)]
 pub(super) struct Running {
-    pub(super) schema: Arc<RwLock<Valid<Schema>>>,
+    pub(super) schema: Arc<RwLock<Arc<Valid<Schema>>>>,
     pub(super) operations: Arc<RwLock<Vec<Operation>>>,
     pub(super) apps: Vec<crate::apps::App>,
     pub(super) prompts: Vec<crate::prompts::PromptFile>,
@@ -76,9 +87,70 @@ pub(super) struct Running {
     pub(super) annotations: HashMap<String, AnnotationOverrides>,
     pub(super) health_check: Option<HealthCheck>,
     pub(super) server_info: ServerInfoConfig,
+    pub(super) claim_headers: ClaimHeadersConfig,
+    pub(super) upstream: Upstream,
+    pub(super) rate_limiter: Option<RateLimiter>,
+    pub(super) policies: Option<Policies>,
+    pub(super) concurrency_limiter: Option<ConcurrencyLimiter>,
     /// MCP initialize-response instructions (optional).
     pub(super) instructions: Option<String>,
-    pub(super) rhai_engine: Arc<Mutex<RhaiEngine>>,
+    pub(super) rhai_engine: Arc<RhaiEngine>,
+    pub(super) listed_tool_names: Arc<parking_lot::Mutex<ListedToolNames>>,
+}
+
+/// Sessions whose tool names are remembered; past this many, the oldest session is dropped
+const MAX_LISTED_SESSIONS: usize = 10_000;
+
+/// The original names of the tools last listed to each session and app, by the names the
+/// `on_list_tools` hook gave them, so that calls don't run the hook again to map them back
+#[derive(Default)]
+pub(super) struct ListedToolNames {
+    sessions: HashMap<ListingSession, SessionToolNames>,
+    /// Bumped whenever the tools may have changed, so that lists computed before are discarded
+    generation: u64,
+}
+
+/// The MCP session and the app that tools were listed for
+type ListingSession = (String, Option<String>);
+
+struct SessionToolNames {
+    listed_at: std::time::Instant,
+    /// Original tool names by listed name
+    names: HashMap<String, String>,
+}
+
+impl ListedToolNames {
+    fn insert(&mut self, key: ListingSession, generation: u64, listed: &[ListedTool]) {
+        if generation != self.generation {
+            return;
+        }
+        if self.sessions.len() >= MAX_LISTED_SESSIONS
+            && !self.sessions.contains_key(&key)
+            && let Some(oldest) = self
+                .sessions
+                .iter()
+                .min_by_key(|(_, session)| session.listed_at)
+                .map(|(key, _)| key.clone())
+        {
+            self.sessions.remove(&oldest);
+        }
+        let names = listed
+            .iter()
+            .map(|listed| (listed.tool.name.to_string(), listed.original_name.clone()))
+            .collect();
+        self.sessions.insert(
+            key,
+            SessionToolNames {
+                listed_at: std::time::Instant::now(),
+                names,
+            },
+        );
+    }
+
+    fn clear(&mut self) {
+        self.sessions.clear();
+        self.generation += 1;
+    }
 }
 
 impl Running {
@@ -89,18 +161,81 @@ impl Running {
             && protocol_version.is_some_and(|v| *v >= ProtocolVersion::V_2025_06_18)
     }
 
+    /// Prepare a tool call that executes a GraphQL operation: wait for a concurrency slot and
+    /// build the headers to send to the GraphQL endpoint.
+    ///
+    /// Returns the tool error to respond with when the call cannot proceed.
+    async fn prepare_upstream_call(
+        &self,
+        tool_name: &str,
+        axum_parts: Option<&axum::http::request::Parts>,
+    ) -> Result<(Option<ConcurrencyPermit>, HeaderMap), CallToolResult> {
+        let permit = match &self.concurrency_limiter {
+            Some(limiter) => Some(limiter.acquire(tool_name).await.map_err(|saturated| {
+                CallToolResult::error(vec![Content::text(saturated.to_string())])
+            })?),
+            None => None,
+        };
+
+        // API keys can't be exchanged, so callers with one authenticate upstream as the server
+        let client_token = axum_parts
+            .and_then(|parts| parts.extensions.get::<ValidToken>())
+            .filter(|token| !token.api_key);
+        let mut headers = match axum_parts {
+            Some(axum_parts) => build_request_headers(
+                &self.headers,
+                &self.forward_headers,
+                &axum_parts.headers,
+                &axum_parts.extensions,
+                self.disable_auth_token_passthrough,
+                &self.claim_headers,
+            ),
+            None => self.headers.clone(),
+        };
+
+        match (
+            client_token,
+            &self.upstream.token_exchange,
+            &self.upstream.upstream_auth,
+        ) {
+            // Replace the client's token with one issued for the GraphQL API
+            (Some(token), Some(exchanger), _) => {
+                let exchanged = exchanger.exchange(token).await.map_err(|e| {
+                    CallToolResult::error(vec![Content::text(format!(
+                        "Upstream authentication failed: the access token could not be exchanged for a GraphQL API token ({e}). Re-authenticate with the MCP server and try again."
+                    ))])
+                })?;
+                headers.typed_insert(exchanged);
+            }
+            // Without a client token, authenticate as the server itself
+            (None, _, Some(upstream_auth)) => {
+                let token = upstream_auth.token().await.map_err(|e| {
+                    CallToolResult::error(vec![Content::text(format!(
+                        "Upstream authentication failed: the server could not obtain an access token for the GraphQL API ({e}). Check the `upstream_auth` configuration and try again."
+                    ))])
+                })?;
+                headers.typed_insert(token);
+            }
+            _ => {}
+        }
+
+        Ok((permit, headers))
+    }
+
     /// Update a running server with a new schema.
     ///
+    /// Updates are applied one at a time by the server's event loop. The `on_schema_updated` hook
+    /// runs without holding the operations lock, so tool calls and lists aren't blocked by it.
+    ///
     /// Note: It's important that this takes an immutable reference to ensure we're only updating things that are shared with the server (`RwLock`s)
     pub(super) async fn update_schema(&self, schema: Valid<Schema>) {
         debug!("Schema updated:\n{}", schema);
 
-        // We hold this lock for the entire update process to make sure there are no race conditions with simultaneous updates
-        let mut operations_lock = self.operations.write().await;
+        let current_operations = self.operations.read().await.clone();
 
         // Update the operations based on the new schema. This is necessary because the MCP tool
         // input schemas and description are derived from the schema.
-        let operations: Vec<Operation> = operations_lock
+        let operations: Vec<Operation> = current_operations
             .iter()
             .cloned()
             .map(|operation| operation.into_inner())
@@ -128,83 +263,130 @@ impl Running {
             operations.len(),
             serde_json::to_string_pretty(&operations).unwrap_or_default()
         );
-        // Update the schema itself
-        *self.schema.write().await = schema;
-
-        *operations_lock = operations;
+        if checkpoints::has_on_schema_updated(&self.rhai_engine) {
+            let previous_schema = self.schema.read().await.clone();
+            let (added_types, removed_types) = type_changes(&previous_schema, &schema);
+            let update = SchemaUpdate {
+                schema_hash: schema_hash(&schema),
+                previous_schema_hash: schema_hash(&previous_schema),
+                added_types,
+                removed_types,
+                tools_before: tool_names(&current_operations),
+                tools_after: tool_names(&operations),
+            };
+            if let Err(err) = checkpoints::on_schema_updated(&self.rhai_engine, &update) {
+                warn!(
+                    "Rhai on_schema_updated hook rejected the schema update, keeping the previous schema: {}",
+                    err.message
+                );
+                return;
+            }
+        }
 
-        // Drop the operations lock before notifying peers. The operations are
-        // already written, so clients will see the updated list when they
-        // re-fetch. Holding the lock during notification can starve all
-        // list_tools / call_tool / initialize requests if any peer notification
-        // is slow or hangs.
-        drop(operations_lock);
+        // Update the schema and the operations together, so that calls see both or neither
+        {
+            let mut operations_lock = self.operations.write().await;
+            *self.schema.write().await = Arc::new(schema);
+            *operations_lock = operations;
+            self.listed_tool_names.lock().clear();
+        }
 
-        // Notify MCP clients that tools have changed
+        // Notify MCP clients that tools have changed. The operations lock is already released, so
+        // clients see the updated list when they re-fetch, and a slow or hung peer notification
+        // can't starve list_tools / call_tool / initialize requests.
         Self::notify_tool_list_changed(self.peers.clone()).await;
     }
 
     /// Update a running server with new operations.
     ///
+    /// Updates are applied one at a time by the server's event loop. The `on_operations_updated`
+    /// hook runs without holding the operations lock, so tool calls and lists aren't blocked by it.
+    ///
     /// Note: It's important that this takes an immutable reference to ensure we're only updating things that are shared with the server (`RwLock`s)
     #[tracing::instrument(skip_all)]
     pub(super) async fn update_operations(&self, operations: Vec<RawOperation>) {
         debug!("Operations updated:\n{:?}", operations);
 
-        // We hold this lock for the entire update process to make sure there are no race conditions with simultaneous updates
-        let mut operations_lock = self.operations.write().await;
-
         // Update the operations based on the current schema
-        let updated_operations: Vec<Operation> = {
-            let schema = &*self.schema.read().await;
-            operations
-                .into_iter()
-                .filter_map(|operation| {
-                    operation
-                        .into_operation(
-                            schema,
-                            self.custom_scalar_map.as_ref(),
-                            self.mutation_mode,
-                            self.disable_type_description,
-                            self.disable_schema_description,
-                            self.enable_output_schema,
-                            &self.annotations,
-                            &self.descriptions,
-                        )
-                        .unwrap_or_else(|error| {
-                            error!("Invalid operation: {}", error);
-                            None
-                        })
-                })
-                .collect()
-        };
+        let schema = self.schema.read().await.clone();
+        let updated_operations: Vec<Operation> = operations
+            .into_iter()
+            .filter_map(|operation| {
+                operation
+                    .into_operation(
+                        &schema,
+                        self.custom_scalar_map.as_ref(),
+                        self.mutation_mode,
+                        self.disable_type_description,
+                        self.disable_schema_description,
+                        self.enable_output_schema,
+                        &self.annotations,
+                        &self.descriptions,
+                    )
+                    .unwrap_or_else(|error| {
+                        error!("Invalid operation: {}", error);
+                        None
+                    })
+            })
+            .collect();
 
         debug!(
             "Loaded {} operations:\n{}",
             updated_operations.len(),
             serde_json::to_string_pretty(&updated_operations).unwrap_or_default()
         );
-        *operations_lock = updated_operations;
 
-        // Drop the operations lock before notifying peers (same rationale as update_schema).
-        drop(operations_lock);
+        if checkpoints::has_on_operations_updated(&self.rhai_engine) {
+            let update = OperationsUpdate {
+                schema_hash: schema_hash(&schema),
+                tools_before: tool_names(&self.operations.read().await),
+                tools_after: tool_names(&updated_operations),
+            };
+            if let Err(err) = checkpoints::on_operations_updated(&self.rhai_engine, &update) {
+                warn!(
+                    "Rhai on_operations_updated hook rejected the operations update, keeping the previous operations: {}",
+                    err.message
+                );
+                return;
+            }
+        }
+
+        *self.operations.write().await = updated_operations;
+        self.listed_tool_names.lock().clear();
 
-        // Notify MCP clients that tools have changed
+        // Notify MCP clients that tools have changed (same rationale as update_schema)
         Self::notify_tool_list_changed(self.peers.clone()).await;
     }
 
     /// Reload Rhai scripts from the rhai/ directory.
     /// On failure, logs the error and keeps the previous scripts.
-    pub(super) fn reload_rhai_scripts(&self) {
-        let mut engine = self.rhai_engine.lock();
-        match engine.reload() {
+    pub(super) async fn reload_rhai_scripts(&self) {
+        let previous_tools = self.script_tools();
+        match self.rhai_engine.reload() {
             Ok(()) => {
                 info!("Rhai scripts reloaded successfully");
+                // The `on_list_tools` hook may name the tools differently now
+                self.listed_tool_names.lock().clear();
             }
             Err(err) => {
                 error!("Failed to reload Rhai scripts, keeping previous version: {err}");
+                return;
             }
         }
+
+        // Notify MCP clients when the scripts changed the tools they define
+        if self.script_tools() != previous_tools {
+            Self::notify_tool_list_changed(self.peers.clone()).await;
+        }
+    }
+
+    /// The tools defined by Rhai scripts
+    fn script_tools(&self) -> Vec<Tool> {
+        self.rhai_engine
+            .tools()
+            .iter()
+            .map(|script_tool| script_tool.tool().clone())
+            .collect()
     }
 
     /// Notify any peers that tools have changed. Drops unreachable peers from the list.
@@ -281,9 +463,33 @@ impl Running {
             .build()
             .add(1, &[]);
 
+        let tools = self
+            .listed_tools(extensions, client_capabilities, protocol_version)
+            .await?;
+        Ok(ListToolsResult {
+            next_cursor: None,
+            tools: tools.into_iter().map(|listed| listed.tool).collect(),
+            meta: None,
+        })
+    }
+
+    /// The tools listed to the caller of a request, with the names they had before the
+    /// `on_list_tools` hook renamed them
+    async fn listed_tools(
+        &self,
+        extensions: Extensions,
+        client_capabilities: Option<&ClientCapabilities>,
+        protocol_version: Option<&ProtocolVersion>,
+    ) -> Result<Vec<ListedTool>, McpError> {
         let app_param = extract_app_param(&extensions);
+        let session = session_id(&extensions).map(|id| (id, app_param.clone()));
+        let generation = self.listed_tool_names.lock().generation;
+        let axum_parts = extensions.get::<axum::http::request::Parts>().cloned();
+        let token = axum_parts
+            .as_ref()
+            .and_then(|parts| parts.extensions.get::<ValidToken>())
+            .cloned();
         let app_target = AppTarget::try_from((extensions, client_capabilities))?;
-
         // If we get the app param, we'll run in a special "app mode" where we only expose the tools for that app (+execute)
         let mut result = if let Some(app_name) = app_param {
             let app = self.apps.iter().find(|app| app.name == app_name);
@@ -322,20 +528,34 @@ impl Running {
                 }
             }
         } else {
+            let mut tools: Vec<Tool> = self
+                .operations
+                .read()
+                .await
+                .iter()
+                .map(|op| op.as_ref().clone())
+                .chain(self.execute_tool.as_ref().iter().map(|e| e.tool.clone()))
+                .chain(self.introspect_tool.as_ref().iter().map(|e| e.tool.clone()))
+                .chain(self.search_tool.as_ref().iter().map(|e| e.tool.clone()))
+                .chain(self.explorer_tool.as_ref().iter().map(|e| e.tool.clone()))
+                .chain(self.validate_tool.as_ref().iter().map(|e| e.tool.clone()))
+                .collect();
+
+            // Operations and built-in tools take precedence over tools defined by scripts
+            for script_tool in self.script_tools() {
+                if tools.iter().any(|tool| tool.name == script_tool.name) {
+                    warn!(
+                        "Skipping tool {} defined by Rhai scripts because another tool has the same name",
+                        script_tool.name
+                    );
+                } else {
+                    tools.push(script_tool);
+                }
+            }
+
             ListToolsResult {
                 next_cursor: None,
-                tools: self
-                    .operations
-                    .read()
-                    .await
-                    .iter()
-                    .map(|op| op.as_ref().clone())
-                    .chain(self.execute_tool.as_ref().iter().map(|e| e.tool.clone()))
-                    .chain(self.introspect_tool.as_ref().iter().map(|e| e.tool.clone()))
-                    .chain(self.search_tool.as_ref().iter().map(|e| e.tool.clone()))
-                    .chain(self.explorer_tool.as_ref().iter().map(|e| e.tool.clone()))
-                    .chain(self.validate_tool.as_ref().iter().map(|e| e.tool.clone()))
-                    .collect(),
+                tools,
                 meta: None,
             }
         };
@@ -346,7 +566,140 @@ impl Running {
             }
         }
 
-        Ok(result)
+        // Hide the tools that the caller isn't allowed to call
+        if let Some(policies) = &self.policies {
+            let operations = self.operations.read().await;
+            result.tools.retain(|tool| {
+                let operation_type = operations
+                    .iter()
+                    .find(|op| op.tool.name == tool.name)
+                    .map(|op| op.operation_type);
+                policies.allows_listing(
+                    &PolicyTarget {
+                        tool: &tool.name,
+                        annotations: tool.annotations.as_ref(),
+                        operation_type,
+                    },
+                    token.as_ref(),
+                )
+            });
+        }
+
+        if !checkpoints::has_on_list_tools(&self.rhai_engine) {
+            return Ok(result.tools.into_iter().map(ListedTool::from).collect());
+        }
+
+        // Let scripts hide, reorder, rename, or describe the tools for this caller
+        let listed =
+            checkpoints::on_list_tools(&self.rhai_engine, result.tools, axum_parts.as_ref())?;
+        validate_listed_tools(&listed).map_err(|err| {
+            error!("Error when executing on_list_tools hook: {err}");
+            McpError::new(ErrorCode::INTERNAL_ERROR, "Internal error", None)
+        })?;
+        if let Some(session) = session {
+            self.listed_tool_names
+                .lock()
+                .insert(session, generation, &listed);
+        }
+        Ok(listed)
+    }
+
+    /// The name of a called tool before the `on_list_tools` hook renamed it. Tools that the hook
+    /// doesn't list can't be called.
+    ///
+    /// The names last listed to the caller's session are used when there are any; otherwise the
+    /// tools are listed again.
+    async fn original_tool_name(
+        &self,
+        name: Cow<'static, str>,
+        extensions: &Extensions,
+        protocol_version: Option<&ProtocolVersion>,
+    ) -> Result<Cow<'static, str>, McpError> {
+        if !checkpoints::has_on_list_tools(&self.rhai_engine) {
+            return Ok(name);
+        }
+
+        if let Some(session) = session_id(extensions).map(|id| (id, extract_app_param(extensions)))
+            && let Some(listed) = self.listed_tool_names.lock().sessions.get(&session)
+        {
+            return listed
+                .names
+                .get(name.as_ref())
+                .map(|original| Cow::Owned(original.clone()))
+                .ok_or_else(|| tool_not_found(&name));
+        }
+
+        self.listed_tools(extensions.clone(), None, protocol_version)
+            .await?
+            .into_iter()
+            .find(|listed| listed.tool.name == name)
+            .map(|listed| Cow::Owned(listed.original_name))
+            .ok_or_else(|| tool_not_found(&name))
+    }
+
+    /// Find the tool called `name`, returning its annotations and the type of the operation it
+    /// runs as far as they are known before the call, or `None` if there is no such tool
+    async fn resolve_tool(
+        &self,
+        name: &str,
+        arguments: Option<&JsonObject>,
+        app_param: Option<&str>,
+    ) -> Option<(Option<ToolAnnotations>, Option<OperationType>)> {
+        if name == EXECUTE_TOOL_NAME
+            && let Some(execute_tool) = &self.execute_tool
+        {
+            let operation_type = arguments
+                .and_then(|arguments| arguments.get("query"))
+                .and_then(Value::as_str)
+                .and_then(|query| operation_defs(query, true, None).ok().flatten())
+                .map(|(_, operation, _)| operation.operation_type);
+            return Some((execute_tool.tool.annotations.clone(), operation_type));
+        }
+
+        let built_in = [
+            self.introspect_tool.as_ref().map(|t| &t.tool),
+            self.search_tool.as_ref().map(|t| &t.tool),
+            self.explorer_tool.as_ref().map(|t| &t.tool),
+            self.validate_tool.as_ref().map(|t| &t.tool),
+        ];
+        if let Some(tool) = built_in.into_iter().flatten().find(|t| t.name == name) {
+            return Some((tool.annotations.clone(), None));
+        }
+
+        // Calls for an app only reach that app's tools
+        if let Some(app_param) = app_param {
+            return self
+                .apps
+                .iter()
+                .find(|app| app.name == app_param)?
+                .tools
+                .iter()
+                .find(|tool| tool.tool.name == name)
+                .map(|tool| {
+                    (
+                        tool.tool.annotations.clone(),
+                        Some(tool.operation.operation_type),
+                    )
+                });
+        }
+
+        if let Some(operation) = self
+            .operations
+            .read()
+            .await
+            .iter()
+            .find(|op| op.tool.name == name)
+        {
+            return Some((
+                operation.tool.annotations.clone(),
+                Some(operation.operation_type),
+            ));
+        }
+
+        self.script_tools()
+            .into_iter()
+            .find(|tool| tool.name == name)
+            .map(|tool| (tool.annotations, None))
     }
 
     async fn call_tool_impl(
@@ -357,10 +710,34 @@ impl Running {
     ) -> Result<CallToolResult, McpError> {
         let meter = &meter::METER;
         let start = std::time::Instant::now();
-        let tool_name = request.name;
+        let tool_name = self
+            .original_tool_name(request.name, extensions, protocol_version)
+            .await?;
         let app_param = extract_app_param(extensions);
         let axum_parts = extensions.get::<axum::http::request::Parts>();
 
+        // Resolve the tool before limiting it, so that only real tools get rate limit buckets
+        let (annotations, operation_type) = self
+            .resolve_tool(&tool_name, request.arguments.as_ref(), app_param.as_deref())
+            .await
+            .ok_or_else(|| tool_not_found(&tool_name))?;
+
+        // Check policies first, so that denied calls don't use up rate limit tokens
+        if let Some(policies) = &self.policies {
+            policies.check_call(
+                &PolicyTarget {
+                    tool: &tool_name,
+                    annotations: annotations.as_ref(),
+                    operation_type,
+                },
+                axum_parts.and_then(|parts| parts.extensions.get::<ValidToken>()),
+            )?;
+        }
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.check(&tool_name, axum_parts)?;
+        }
+
         let mut result = if tool_name == INTROSPECT_TOOL_NAME
             && let Some(introspect_tool) = &self.introspect_tool
         {
@@ -391,28 +768,24 @@ impl Running {
         } else if tool_name == EXECUTE_TOOL_NAME
             && let Some(execute_tool) = &self.execute_tool
         {
-            let headers = if let Some(axum_parts) = axum_parts {
-                build_request_headers(
-                    &self.headers,
-                    &self.forward_headers,
-                    &axum_parts.headers,
-                    &axum_parts.extensions,
-                    self.disable_auth_token_passthrough,
-                )
-            } else {
-                self.headers.clone()
-            };
-
-            execute_operation(
-                execute_tool,
-                &headers,
-                request.arguments.as_ref(),
-                &self.endpoint,
-                &self.rhai_engine,
-                axum_parts,
-                &tool_name,
-            )
-            .await
+            match self.prepare_upstream_call(&tool_name, axum_parts).await {
+                Err(result) => Ok(result),
+                Ok((_permit, headers)) => {
+                    let execute_tool = execute_tool.with_current_schema().await;
+                    execute_operation(
+                        &execute_tool,
+                        &headers,
+                        request.arguments.as_ref(),
+                        &self.endpoint,
+                        &self.upstream,
+                        execute_tool.schema(),
+                        &self.rhai_engine,
+                        axum_parts,
+                        &tool_name,
+                    )
+                    .await
+                }
+            }
         } else if tool_name == VALIDATE_TOOL_NAME
             && let Some(validate_tool) = &self.validate_tool
         {
@@ -423,52 +796,68 @@ impl Running {
                 ))])),
             }
         } else {
-            let headers = if let Some(axum_parts) = axum_parts {
-                build_request_headers(
-                    &self.headers,
-                    &self.forward_headers,
-                    &axum_parts.headers,
-                    &axum_parts.extensions,
-                    self.disable_auth_token_passthrough,
-                )
-            } else {
-                self.headers.clone()
-            };
-
-            // Acquire the lock once: reused for scope check and execution.
-            let ops = self.operations.read().await;
-
-            if let Some(app_param) = &app_param {
-                if let Some(res) = find_and_execute_app_tool(
-                    &self.apps,
-                    app_param,
-                    &tool_name,
-                    &headers,
-                    request.arguments.as_ref(),
-                    &self.endpoint,
-                    &self.rhai_engine,
-                    axum_parts,
-                )
-                .await
-                {
-                    res
-                } else {
-                    Err(tool_not_found(&tool_name))
+            match self.prepare_upstream_call(&tool_name, axum_parts).await {
+                Err(result) => Ok(result),
+                Ok((_permit, headers)) => {
+                    let schema = self.schema.read().await.clone();
+                    // Acquire the lock once: reused for scope check and execution.
+                    let ops = self.operations.read().await;
+
+                    if let Some(app_param) = &app_param {
+                        if let Some(res) = find_and_execute_app_tool(
+                            &self.apps,
+                            app_param,
+                            &tool_name,
+                            &headers,
+                            request.arguments.as_ref(),
+                            &self.endpoint,
+                            &self.upstream,
+                            &schema,
+                            &self.rhai_engine,
+                            axum_parts,
+                        )
+                        .await
+                        {
+                            res
+                        } else {
+                            Err(tool_not_found(&tool_name))
+                        }
+                    } else if let Some(res) = find_and_execute_operation(
+                        &ops,
+                        &tool_name,
+                        &headers,
+                        request.arguments.as_ref(),
+                        &self.endpoint,
+                        &self.upstream,
+                        &schema,
+                        &self.rhai_engine,
+                        axum_parts,
+                    )
+                    .await
+                    {
+                        res
+                    } else {
+                        // Release the operations so that the script's handler can execute them
+                        drop(ops);
+                        let executor = Arc::new(ScriptOperations {
+                            operations: self.operations.clone(),
+                            endpoint: self.endpoint.clone(),
+                            headers,
+                            upstream: self.upstream.clone(),
+                            rate_limiter: self.rate_limiter.clone(),
+                            policies: self.policies.clone(),
+                            concurrency_limiter: self.concurrency_limiter.clone(),
+                            axum_parts: axum_parts.cloned(),
+                        });
+                        apollo_mcp_rhai::tools::call_script_tool(
+                            &self.rhai_engine,
+                            &tool_name,
+                            request.arguments.as_ref(),
+                            executor,
+                        )
+                        .unwrap_or_else(|| Err(tool_not_found(&tool_name)))
+                    }
                 }
-            } else if let Some(res) = find_and_execute_operation(
-                &ops,
-                &tool_name,
-                &headers,
-                request.arguments.as_ref(),
-                &self.endpoint,
-                &self.rhai_engine,
-                axum_parts,
-            )
-            .await
-            {
-                res
-            } else {
-                Err(tool_not_found(&tool_name))
             }
         };
 
@@ -614,6 +1003,38 @@ impl Running {
     }
 }
 
+/// The hex-encoded SHA-256 hash of a schema's SDL
+fn schema_hash(schema: &Schema) -> String {
+    hex::encode(Sha256::digest(schema.to_string().as_bytes()))
+}
+
+/// The names of the types that `new` adds to and removes from `old`, ignoring built-in types
+fn type_changes(old: &Schema, new: &Schema) -> (Vec<String>, Vec<String>) {
+    let type_names = |schema: &Schema| -> BTreeSet<String> {
+        schema
+            .types
+            .iter()
+            .filter(|(_, ty)| !ty.is_built_in())
+            .map(|(name, _)| name.to_string())
+            .collect()
+    };
+    let old = type_names(old);
+    let new = type_names(new);
+
+    (
+        new.difference(&old).cloned().collect(),
+        old.difference(&new).cloned().collect(),
+    )
+}
+
+/// The tool names of operations
+fn tool_names(operations: &[Operation]) -> Vec<String> {
+    operations
+        .iter()
+        .map(|operation| operation.as_ref().name.to_string())
+        .collect()
+}
+
 impl ServerHandler for Running {
     #[tracing::instrument(skip_all, parent = get_parent_span(&context), fields(apollo.mcp.client_name = request.client_info.name, apollo.mcp.client_version = request.client_info.version))]
     async fn initialize(
@@ -821,6 +1242,15 @@ fn extract_app_param(extensions: &Extensions) -> Option<String> {
         })
 }
 
+/// The MCP session of a streamable HTTP request
+fn session_id(extensions: &Extensions) -> Option<String> {
+    extensions
+        .get::<axum::http::request::Parts>()
+        .and_then(|parts| parts.headers.get("mcp-session-id"))
+        .and_then(|value| value.to_str().ok())
+        .map(String::from)
+}
+
 fn tool_not_found(name: &str) -> McpError {
     McpError::new(
         ErrorCode::METHOD_NOT_FOUND,
@@ -829,6 +1259,37 @@ fn tool_not_found(name: &str) -> McpError {
     )
 }
 
+/// Check that the tools listed by the `on_list_tools` hook are valid MCP tools, so that a buggy
+/// script can't break clients
+fn validate_listed_tools(tools: &[ListedTool]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for ListedTool { tool, .. } in tools {
+        let name = tool.name.as_ref();
+        if name.is_empty()
+            || name.len() > 128
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        {
+            return Err(format!("tool name \"{name}\" is invalid"));
+        }
+        if !names.insert(name) {
+            return Err(format!("more than one tool is named {name}"));
+        }
+        if tool.input_schema.get("type") != Some(&Value::from("object")) {
+            return Err(format!(
+                "the input schema of {name} is not an object schema"
+            ));
+        }
+        if let Err(err) =
+            jsonschema::meta::validate(&Value::Object(tool.input_schema.as_ref().clone()))
+        {
+            return Err(format!("the input schema of {name} is invalid: {err}"));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use rmcp::model::{JsonObject, Tool};
@@ -841,7 +1302,7 @@ mod tests {
 
     use super::*;
 
-    fn test_running(schema: Arc<RwLock<Valid<Schema>>>) -> Running {
+    fn test_running(schema: Arc<RwLock<Arc<Valid<Schema>>>>) -> Running {
         Running {
             schema,
             operations: Arc::new(RwLock::new(vec![])),
@@ -867,8 +1328,14 @@ mod tests {
             annotations: HashMap::new(),
             health_check: None,
             server_info: ServerInfoConfig::default(),
+            claim_headers: Default::default(),
+            upstream: Default::default(),
+            rate_limiter: None,
+            policies: None,
+            concurrency_limiter: None,
             instructions: None,
-            rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+            rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+            listed_tool_names: Default::default(),
         }
     }
 
@@ -916,7 +1383,7 @@ mod tests {
 
         Running {
             apps: vec![app],
-            ..test_running(Arc::new(RwLock::new(schema)))
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
         }
     }
 
@@ -935,7 +1402,7 @@ mod tests {
 
             let running = Running {
                 operations: operations.clone(),
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let new_operations = vec![
@@ -979,7 +1446,7 @@ mod tests {
             let running = Running {
                 operations: operations.clone(),
                 descriptions,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let new_operations = vec![RawOperation::from((
@@ -1015,7 +1482,7 @@ mod tests {
             let running = Running {
                 operations: operations.clone(),
                 descriptions,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let new_operations = vec![RawOperation::from((
@@ -1055,7 +1522,7 @@ mod tests {
             let running = Running {
                 operations: operations.clone(),
                 annotations,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let new_operations = vec![RawOperation::from((
@@ -1105,7 +1572,7 @@ mod tests {
             let running = Running {
                 operations: operations.clone(),
                 annotations,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let new_operations = vec![RawOperation::from((
@@ -1127,7 +1594,7 @@ mod tests {
 
         #[tokio::test]
         async fn changing_schema_invalidates_outdated_operations() {
-            let schema = Arc::new(RwLock::new(
+            let schema = Arc::new(RwLock::new(Arc::new(
                 Schema::parse(
                     "type Query { data: String, something: String }",
                     "schema.graphql",
@@ -1135,7 +1602,7 @@ mod tests {
                 .unwrap()
                 .validate()
                 .unwrap(),
-            ));
+            )));
 
             let running = test_running(schema.clone());
 
@@ -1158,7 +1625,113 @@ mod tests {
                 .unwrap();
             running.update_schema(new_schema.clone()).await;
 
-            assert_eq!(*schema.read().await, new_schema);
+            assert_eq!(**schema.read().await, new_schema);
+        }
+
+        fn rhai_engine_with_script(dir: &tempfile::TempDir, script: &str) -> Arc<RhaiEngine> {
+            std::fs::write(dir.path().join("main.rhai"), script).unwrap();
+            let engine = RhaiEngine::new(dir.path());
+            engine.load_from_path().unwrap();
+            Arc::new(engine)
+        }
+
+        #[tokio::test]
+        async fn rhai_hook_can_reject_schema_update_that_removes_types() {
+            let old_schema = Schema::parse(
+                "type Query { data: String, something(filter: Filter): String } input Filter { id: ID }",
+                "schema.graphql",
+            )
+            .unwrap()
+            .validate()
+            .unwrap();
+            let schema = Arc::new(RwLock::new(Arc::new(old_schema.clone())));
+            let operations = Arc::new(RwLock::new(vec![]));
+            let scripts = tempfile::tempdir().unwrap();
+
+            let running = Running {
+                operations: operations.clone(),
+                rhai_engine: rhai_engine_with_script(
+                    &scripts,
+                    r#"fn on_schema_updated(info) {
+                        Store::set("removed_types", info.removed_types);
+                        throw #{ message: "Filter is still used" };
+                    }
+
+                    fn recorded_removed_types() {
+                        Store::get("removed_types")
+                    }"#,
+                ),
+                ..test_running(schema.clone())
+            };
+
+            running
+                .update_operations(vec![
+                    RawOperation::from((
+                        "query Valid { data }".to_string(),
+                        Some("valid.graphql".to_string()),
+                    )),
+                    RawOperation::from((
+                        "query Filtered($filter: Filter) { something(filter: $filter) }"
+                            .to_string(),
+                        Some("filtered.graphql".to_string()),
+                    )),
+                ])
+                .await;
+
+            let new_schema = Schema::parse("type Query { data: String }", "schema.graphql")
+                .unwrap()
+                .validate()
+                .unwrap();
+            running.update_schema(new_schema).await;
+
+            let removed_types = running
+                .rhai_engine
+                .execute_hook("recorded_removed_types", ())
+                .unwrap()
+                .unwrap();
+            assert_eq!(removed_types.to_string(), r#"["Filter"]"#);
+            assert_eq!(**schema.read().await, old_schema);
+            assert_eq!(operations.read().await.len(), 2);
+        }
+
+        #[tokio::test]
+        async fn rhai_hook_can_reject_operations_update() {
+            let schema = Schema::parse("type Query { id: String }", "schema.graphql")
+                .unwrap()
+                .validate()
+                .unwrap();
+            let operations = Arc::new(RwLock::new(vec![]));
+            let scripts = tempfile::tempdir().unwrap();
+
+            let running = Running {
+                operations: operations.clone(),
+                rhai_engine: rhai_engine_with_script(
+                    &scripts,
+                    r#"fn on_operations_updated(info) {
+                        if info.removed_tools.contains("Required") {
+                            throw "Required must stay";
+                        }
+                    }"#,
+                ),
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+            };
+
+            running
+                .update_operations(vec![RawOperation::from((
+                    "query Required { id }".to_string(),
+                    Some("required.graphql".to_string()),
+                ))])
+                .await;
+            running
+                .update_operations(vec![RawOperation::from((
+                    "query Other { id }".to_string(),
+                    Some("other.graphql".to_string()),
+                ))])
+                .await;
+
+            let operations = operations.read().await;
+            assert_eq!(operations.len(), 1);
+            assert_eq!(operations.first().unwrap().as_ref().name, "Required");
         }
     }
 
@@ -1991,7 +2564,7 @@ mod tests {
             let running = Running {
                 operations: Arc::new(RwLock::new(vec![operation])),
                 enable_output_schema: true,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let result = running
@@ -2037,7 +2610,7 @@ mod tests {
             let running = Running {
                 operations: Arc::new(RwLock::new(vec![operation])),
                 enable_output_schema: true,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let result = running
@@ -2071,7 +2644,7 @@ mod tests {
                 .validate()
                 .unwrap();
 
-            let running = test_running(Arc::new(RwLock::new(schema)));
+            let running = test_running(Arc::new(RwLock::new(Arc::new(schema))));
 
             let info = running.get_info();
 
@@ -2105,7 +2678,7 @@ mod tests {
 
             let running = Running {
                 instructions: Some("Prefer search before list.".to_string()),
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let info = running.get_info();
@@ -2132,7 +2705,7 @@ mod tests {
 
             let running = Running {
                 server_info: custom_config,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let info = running.get_info();
@@ -2159,12 +2732,12 @@ mod tests {
         fn advertises_latest_supported_version_regardless_of_output_schema(
             #[case] enable_output_schema: bool,
         ) {
-            let schema = Arc::new(RwLock::new(
+            let schema = Arc::new(RwLock::new(Arc::new(
                 Schema::parse("type Query { id: String }", "schema.graphql")
                     .unwrap()
                     .validate()
                     .unwrap(),
-            ));
+            )));
 
             // The advertised version no longer depends on `enable_output_schema`;
             // output schema fields are gated separately by the negotiated version.
@@ -2206,7 +2779,7 @@ mod tests {
                 .unwrap();
             Running {
                 prompts,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             }
         }
 
@@ -2342,7 +2915,7 @@ mod tests {
                 operations: Arc::new(RwLock::new(vec![operation])),
                 endpoint: server.url().parse().unwrap(),
                 enable_output_schema: true,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let mut request = CallToolRequestParams::new("Hello");
@@ -2396,7 +2969,7 @@ mod tests {
                 operations: Arc::new(RwLock::new(vec![operation])),
                 endpoint: server.url().parse().unwrap(),
                 enable_output_schema: true,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let mut request = CallToolRequestParams::new("Hello");
@@ -2501,7 +3074,7 @@ mod tests {
                 apps: vec![app],
                 endpoint: server.url().parse().unwrap(),
                 enable_output_schema: true,
-                ..test_running(Arc::new(RwLock::new(schema)))
+                ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
             };
 
             let mut extensions = Extensions::new();
@@ -2524,6 +3097,612 @@ mod tests {
             operation_mock.assert();
         }
     }
+
+    #[tokio::test]
+    async fn call_tool_is_throttled_by_rate_limits() {
+        use crate::introspection::tools::validate::{VALIDATE_TOOL_NAME, Validate};
+        use crate::rate_limit::{BucketConfig, RATE_LIMITED, RateLimitConfig};
+
+        let schema = Arc::new(RwLock::new(Arc::new(
+            Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap(),
+        )));
+        let running = Running {
+            validate_tool: Some(Validate::new(schema.clone(), None)),
+            rate_limiter: RateLimiter::new(RateLimitConfig {
+                tool: Some(BucketConfig {
+                    requests: 1,
+                    per: std::time::Duration::from_secs(60),
+                }),
+                ..Default::default()
+            }),
+            ..test_running(schema)
+        };
+        let validate = || {
+            let mut request = CallToolRequestParams::new(VALIDATE_TOOL_NAME);
+            request.arguments = Some(
+                serde_json::json!({ "operation": "{ id }" })
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            );
+            request
+        };
+
+        let first = running
+            .call_tool_impl(validate(), &Extensions::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(first.is_error, Some(false));
+
+        let second = running
+            .call_tool_impl(validate(), &Extensions::new(), None)
+            .await
+            .unwrap_err();
+        assert_eq!(second.code, RATE_LIMITED);
+        assert_eq!(second.data.unwrap()["scope"], "tool");
+    }
+
+    #[tokio::test]
+    async fn calls_denied_by_policies_do_not_use_rate_limit_tokens() {
+        use crate::introspection::tools::validate::{VALIDATE_TOOL_NAME, Validate};
+        use crate::policy::{POLICY_DENIED, Policies};
+        use crate::rate_limit::{BucketConfig, RateLimitConfig};
+
+        let schema = Arc::new(RwLock::new(Arc::new(
+            Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap(),
+        )));
+        let running = Running {
+            validate_tool: Some(Validate::new(schema.clone(), None)),
+            rate_limiter: RateLimiter::new(RateLimitConfig {
+                tool: Some(BucketConfig {
+                    requests: 1,
+                    per: std::time::Duration::from_secs(60),
+                }),
+                ..Default::default()
+            }),
+            policies: Policies::new(
+                serde_yaml::from_str(
+                    r#"
+                    - name: finance-only
+                      tools: [validate]
+                      require:
+                        - claim: department
+                          equals: finance
+                    "#,
+                )
+                .unwrap(),
+            ),
+            ..test_running(schema)
+        };
+        let validate = || {
+            let mut request = CallToolRequestParams::new(VALIDATE_TOOL_NAME);
+            request.arguments = Some(
+                serde_json::json!({ "operation": "{ id }" })
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            );
+            request
+        };
+        let mut extensions = Extensions::new();
+        let (mut parts, _) = axum::http::Request::builder()
+            .body(())
+            .unwrap()
+            .into_parts();
+        parts.extensions.insert(ValidToken {
+            token: headers::Authorization::bearer("token").unwrap(),
+            subject: "alice".to_string(),
+            scopes: vec![],
+            claims: serde_json::json!({ "department": "finance" })
+                .as_object()
+                .unwrap()
+                .clone(),
+            api_key: false,
+        });
+        extensions.insert(parts);
+
+        let denied = running
+            .call_tool_impl(validate(), &Extensions::new(), None)
+            .await
+            .unwrap_err();
+        let allowed = running
+            .call_tool_impl(validate(), &extensions, None)
+            .await
+            .unwrap();
+
+        assert_eq!(denied.code, POLICY_DENIED);
+        assert_eq!(allowed.is_error, Some(false));
+    }
+
+    #[tokio::test]
+    async fn call_tool_does_not_rate_limit_unknown_tools() {
+        use crate::rate_limit::{BucketConfig, RateLimitConfig};
+
+        let schema = Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap();
+        let running = Running {
+            rate_limiter: RateLimiter::new(RateLimitConfig {
+                tool: Some(BucketConfig {
+                    requests: 1,
+                    per: std::time::Duration::from_secs(60),
+                }),
+                ..Default::default()
+            }),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+
+        for _ in 0..2 {
+            let error = running
+                .call_tool_impl(
+                    CallToolRequestParams::new("Missing"),
+                    &Extensions::new(),
+                    None,
+                )
+                .await
+                .unwrap_err();
+            assert_eq!(error.code, ErrorCode::METHOD_NOT_FOUND);
+        }
+    }
+
+    #[tokio::test]
+    async fn call_tool_fails_when_concurrency_queue_is_full() {
+        use crate::concurrency::ConcurrencyConfig;
+
+        let schema = Arc::new(RwLock::new(Arc::new(
+            Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap(),
+        )));
+        let running = Running {
+            execute_tool: Some(Execute::new(schema.clone(), MutationMode::None, None)),
+            concurrency_limiter: ConcurrencyLimiter::new(ConcurrencyConfig {
+                tools: HashMap::from([(
+                    EXECUTE_TOOL_NAME.to_string(),
+                    std::num::NonZeroUsize::new(1).unwrap(),
+                )]),
+                queue_size: 0,
+                ..Default::default()
+            }),
+            ..test_running(schema)
+        };
+        let _held = running
+            .concurrency_limiter
+            .as_ref()
+            .unwrap()
+            .acquire(EXECUTE_TOOL_NAME)
+            .await
+            .unwrap();
+
+        let mut request = CallToolRequestParams::new(EXECUTE_TOOL_NAME);
+        request.arguments = Some(
+            serde_json::json!({ "query": "{ id }" })
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        let result = running
+            .call_tool_impl(request, &Extensions::new(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(result.is_error, Some(true));
+        let rmcp::model::RawContent::Text(text) = &result.content[0].raw else {
+            panic!("Expected text content");
+        };
+        assert!(text.text.contains("at capacity"));
+    }
+
+    #[tokio::test]
+    async fn policies_hide_and_deny_tools() {
+        use crate::policy::{POLICY_DENIED, Policies};
+
+        let schema = Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap();
+        let operation = RawOperation::from(("query GetId { id }".to_string(), None))
+            .into_operation(
+                &schema,
+                None,
+                MutationMode::None,
+                false,
+                false,
+                false,
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+            .unwrap()
+            .unwrap();
+        let running = Running {
+            operations: Arc::new(RwLock::new(vec![operation])),
+            policies: Policies::new(
+                serde_yaml::from_str(
+                    r#"
+                    - name: finance-only
+                      operation_types: [query]
+                      require:
+                        - claim: department
+                          equals: finance
+                    "#,
+                )
+                .unwrap(),
+            ),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+
+        let listed = running
+            .list_tools_impl(Extensions::new(), None, None)
+            .await
+            .unwrap();
+        let denied = running
+            .call_tool_impl(
+                CallToolRequestParams::new("GetId"),
+                &Extensions::new(),
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(listed.tools.is_empty());
+        assert_eq!(denied.code, POLICY_DENIED);
+        assert_eq!(denied.data.unwrap()["policy"], "finance-only");
+    }
+
+    #[tokio::test]
+    async fn list_tools_hook_renames_and_hides_tools() {
+        let schema =
+            Schema::parse_and_validate("type Query { id: ID, name: String }", "schema.graphql")
+                .unwrap();
+        let operations = ["query GetId { id }", "query GetName { name }"]
+            .map(|source| {
+                RawOperation::from((source.to_string(), None))
+                    .into_operation(
+                        &schema,
+                        None,
+                        MutationMode::None,
+                        false,
+                        false,
+                        false,
+                        &HashMap::new(),
+                        &HashMap::new(),
+                    )
+                    .unwrap()
+                    .unwrap()
+            })
+            .to_vec();
+
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r#"fn on_list_tools(ctx) {
+                let tools = [];
+                for tool in ctx.tools {
+                    if tool.name == "GetId" {
+                        tool.name = "lookup_id";
+                        tools.push(tool);
+                    }
+                }
+                ctx.tools = tools;
+            }"#,
+        )
+        .unwrap();
+        let engine = RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "operationName": "GetId" }),
+            ))
+            .with_body(r#"{"data": {"id": "1"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let running = Running {
+            operations: Arc::new(RwLock::new(operations)),
+            endpoint: server.url().parse().unwrap(),
+            rhai_engine: Arc::new(engine),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+
+        let listed = running
+            .list_tools_impl(Extensions::new(), None, None)
+            .await
+            .unwrap();
+        let renamed = running
+            .call_tool_impl(
+                CallToolRequestParams::new("lookup_id"),
+                &Extensions::new(),
+                None,
+            )
+            .await
+            .unwrap();
+        let hidden = running
+            .call_tool_impl(
+                CallToolRequestParams::new("GetName"),
+                &Extensions::new(),
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        let names: Vec<_> = listed.tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(names, vec!["lookup_id"]);
+        mock.assert_async().await;
+        assert_ne!(renamed.is_error, Some(true));
+        assert_eq!(hidden.code, ErrorCode::METHOD_NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn calls_map_renamed_tools_with_the_names_listed_to_the_session() {
+        let schema = Schema::parse_and_validate("type Query { id: ID }", "schema.graphql").unwrap();
+        let operation = RawOperation::from(("query GetId { id }".to_string(), None))
+            .into_operation(
+                &schema,
+                None,
+                MutationMode::None,
+                false,
+                false,
+                false,
+                &HashMap::new(),
+                &HashMap::new(),
+            )
+            .unwrap()
+            .unwrap();
+
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r#"fn on_list_tools(ctx) {
+                let tools = ctx.tools;
+                tools[0].name = "lookup_id";
+                ctx.tools = tools;
+            }"#,
+        )
+        .unwrap();
+        let engine = RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_body(r#"{"data": {"id": "1"}}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let running = Running {
+            operations: Arc::new(RwLock::new(vec![operation])),
+            endpoint: server.url().parse().unwrap(),
+            rhai_engine: Arc::new(engine),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+        let session = |id: &str| {
+            let mut extensions = Extensions::new();
+            let (parts, _) = axum::http::Request::builder()
+                .header("mcp-session-id", id)
+                .body(())
+                .unwrap()
+                .into_parts();
+            extensions.insert(parts);
+            extensions
+        };
+        let listed_sessions = |running: &Running| running.listed_tool_names.lock().sessions.len();
+
+        running
+            .list_tools_impl(session("listed"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(listed_sessions(&running), 1);
+
+        // The listed session uses the names it was given; others list the tools again
+        for id in ["listed", "unlisted"] {
+            let result = running
+                .call_tool_impl(CallToolRequestParams::new("lookup_id"), &session(id), None)
+                .await
+                .unwrap();
+            assert_ne!(result.is_error, Some(true));
+        }
+        mock.assert_async().await;
+        assert_eq!(listed_sessions(&running), 2);
+
+        running.update_operations(vec![]).await;
+        assert_eq!(listed_sessions(&running), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn script_tools_are_listed_and_execute_operations() {
+        let schema =
+            Schema::parse_and_validate("type Query { user(id: ID!): String }", "schema.graphql")
+                .unwrap();
+        let operation = RawOperation::from((
+            "query GetUser($id: ID!) { user(id: $id) }".to_string(),
+            None,
+        ))
+        .into_operation(
+            &schema,
+            None,
+            MutationMode::None,
+            false,
+            false,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap()
+        .unwrap();
+
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r#"fn define_tools() {
+                let schema = #{ type: "object", properties: #{ id: #{ type: "string" } } };
+                [#{ name: "GetUser", handler: Fn("get_user") },
+                 #{ name: "Greet", description: "Greet a user", input_schema: schema, handler: Fn("greet") }]
+            }
+
+            fn get_user(args, api) {
+                "shadowed"
+            }
+
+            fn greet(args, api) {
+                let response = api.execute("GetUser", #{ id: args.id }).wait();
+                "Hello " + response.data.user
+            }"#,
+        )
+        .unwrap();
+        let engine = RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "operationName": "GetUser",
+                "variables": { "id": "1" },
+            })))
+            .with_body(r#"{"data": {"user": "Ada"}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let running = Running {
+            operations: Arc::new(RwLock::new(vec![operation])),
+            endpoint: server.url().parse().unwrap(),
+            rhai_engine: Arc::new(engine),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+
+        let listed = running
+            .list_tools_impl(Extensions::new(), None, None)
+            .await
+            .unwrap();
+        let result = running
+            .call_tool_impl(
+                CallToolRequestParams::new("Greet").with_arguments(
+                    serde_json::json!({ "id": "1" })
+                        .as_object()
+                        .unwrap()
+                        .clone(),
+                ),
+                &Extensions::new(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let names: Vec<_> = listed.tools.iter().map(|tool| tool.name.as_ref()).collect();
+        assert_eq!(names, vec!["GetUser", "Greet"]);
+        mock.assert_async().await;
+        assert_eq!(
+            result.content[0].as_text().map(|text| text.text.as_str()),
+            Some("Hello Ada")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn script_tools_cannot_execute_operations_denied_by_policies() {
+        let schema =
+            Schema::parse_and_validate("type Query { user(id: ID!): String }", "schema.graphql")
+                .unwrap();
+        let operation = RawOperation::from((
+            "query GetUser($id: ID!) { user(id: $id) }".to_string(),
+            None,
+        ))
+        .into_operation(
+            &schema,
+            None,
+            MutationMode::None,
+            false,
+            false,
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+        .unwrap()
+        .unwrap();
+
+        let scripts = tempfile::tempdir().unwrap();
+        std::fs::write(
+            scripts.path().join("main.rhai"),
+            r#"fn define_tools() {
+                [#{ name: "Greet", handler: Fn("greet") }]
+            }
+
+            fn greet(args, api) {
+                let response = api.execute("GetUser", #{ id: "1" }).wait();
+                "Hello " + response.data.user
+            }"#,
+        )
+        .unwrap();
+        let engine = RhaiEngine::new(scripts.path());
+        engine.load_from_path().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/")
+            .with_body(r#"{"data": {"user": "Ada"}}"#)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let running = Running {
+            operations: Arc::new(RwLock::new(vec![operation])),
+            endpoint: server.url().parse().unwrap(),
+            rhai_engine: Arc::new(engine),
+            policies: Policies::new(
+                serde_yaml::from_str(
+                    r#"
+                    - name: finance-only
+                      tools: [GetUser]
+                      require:
+                        - claim: department
+                          equals: finance
+                    "#,
+                )
+                .unwrap(),
+            ),
+            ..test_running(Arc::new(RwLock::new(Arc::new(schema))))
+        };
+
+        let error = running
+            .call_tool_impl(
+                CallToolRequestParams::new("Greet"),
+                &Extensions::new(),
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        mock.assert_async().await;
+        assert_eq!(error.code, ErrorCode::INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn listed_tools_must_be_valid() {
+        let schema = serde_json::json!({"type": "object"});
+        let tool = |name: &str, schema: &Value| {
+            ListedTool::from(Tool::new(
+                name.to_string(),
+                "A tool",
+                schema.as_object().unwrap().clone(),
+            ))
+        };
+
+        assert!(
+            validate_listed_tools(&[tool("GetId", &schema), tool("get.name-2", &schema)]).is_ok()
+        );
+        assert!(validate_listed_tools(&[tool("GetId", &schema), tool("GetId", &schema)]).is_err());
+        assert!(validate_listed_tools(&[tool("Get Id", &schema)]).is_err());
+        assert!(validate_listed_tools(&[tool("", &schema)]).is_err());
+        assert!(
+            validate_listed_tools(&[tool("GetId", &serde_json::json!({"type": "string"}))])
+                .is_err()
+        );
+        assert!(
+            validate_listed_tools(&[tool(
+                "GetId",
+                &serde_json::json!({"type": "object", "required": "id"})
+            )])
+            .is_err()
+        );
+    }
 }
 
 #[cfg(test)]
@@ -2566,7 +3745,7 @@ mod integration_tests {
                 .expect("operation should be valid");
 
             Running {
-                schema: Arc::new(RwLock::new(schema)),
+                schema: Arc::new(RwLock::new(Arc::new(schema))),
                 operations: Arc::new(RwLock::new(vec![operation])),
                 apps: vec![],
                 prompts: vec![],
@@ -2590,8 +3769,14 @@ mod integration_tests {
                 annotations: HashMap::new(),
                 health_check: None,
                 server_info: Default::default(),
+                claim_headers: Default::default(),
+                upstream: Default::default(),
+                rate_limiter: None,
+                policies: None,
+                concurrency_limiter: None,
                 instructions: None,
-                rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+                rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+                listed_tool_names: Default::default(),
             }
         }
 
@@ -2824,7 +4009,7 @@ mod integration_tests {
                 .expect("operation should be valid");
 
             Running {
-                schema: Arc::new(RwLock::new(schema)),
+                schema: Arc::new(RwLock::new(Arc::new(schema))),
                 operations: Arc::new(RwLock::new(vec![operation])),
                 apps: vec![],
                 prompts: vec![],
@@ -2848,8 +4033,14 @@ mod integration_tests {
                 annotations: HashMap::new(),
                 health_check: None,
                 server_info: Default::default(),
+                claim_headers: Default::default(),
+                upstream: Default::default(),
+                rate_limiter: None,
+                policies: None,
+                concurrency_limiter: None,
                 instructions: None,
-                rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+                rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+                listed_tool_names: Default::default(),
             }
         }
 
@@ -3056,7 +4247,7 @@ mod integration_tests {
                 apollo_compiler::Schema::parse_and_validate("type Query { hello: String }", "test")
                     .unwrap();
             Running {
-                schema: Arc::new(RwLock::new(schema)),
+                schema: Arc::new(RwLock::new(Arc::new(schema))),
                 operations: Arc::new(RwLock::new(vec![])),
                 apps: vec![],
                 prompts: vec![],
@@ -3080,8 +4271,14 @@ mod integration_tests {
                 annotations: HashMap::new(),
                 health_check: None,
                 server_info: Default::default(),
+                claim_headers: Default::default(),
+                upstream: Default::default(),
+                rate_limiter: None,
+                policies: None,
+                concurrency_limiter: None,
                 instructions: None,
-                rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+                rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+                listed_tool_names: Default::default(),
             }
         }
 
@@ -3376,7 +4573,7 @@ mod integration_tests {
                 apollo_compiler::Schema::parse_and_validate("type Query { hello: String }", "test")
                     .unwrap();
             Running {
-                schema: Arc::new(RwLock::new(schema)),
+                schema: Arc::new(RwLock::new(Arc::new(schema))),
                 operations: Arc::new(RwLock::new(vec![])),
                 apps: vec![],
                 prompts: vec![],
@@ -3400,8 +4597,14 @@ mod integration_tests {
                 annotations: HashMap::new(),
                 health_check: None,
                 server_info: Default::default(),
+                claim_headers: Default::default(),
+                upstream: Default::default(),
+                rate_limiter: None,
+                policies: None,
+                concurrency_limiter: None,
                 instructions: None,
-                rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+                rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+                listed_tool_names: Default::default(),
             }
         }
 
@@ -3534,7 +4737,7 @@ mod integration_tests {
                 apollo_compiler::Schema::parse_and_validate("type Query { hello: String }", "test")
                     .unwrap();
             Running {
-                schema: Arc::new(RwLock::new(schema)),
+                schema: Arc::new(RwLock::new(Arc::new(schema))),
                 operations: Arc::new(RwLock::new(vec![])),
                 apps: vec![],
                 prompts: vec![],
@@ -3558,8 +4761,14 @@ mod integration_tests {
                 annotations: HashMap::new(),
                 health_check: None,
                 server_info: Default::default(),
+                claim_headers: Default::default(),
+                upstream: Default::default(),
+                rate_limiter: None,
+                policies: None,
+                concurrency_limiter: None,
                 instructions: None,
-                rhai_engine: Arc::new(parking_lot::Mutex::new(RhaiEngine::new("rhai"))),
+                rhai_engine: Arc::new(RhaiEngine::new("rhai")),
+                listed_tool_names: Default::default(),
             }
         }
 
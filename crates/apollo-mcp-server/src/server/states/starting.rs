@@ -13,17 +13,23 @@ use tracing::{debug, error, info};
 
 use crate::server::states::telemetry::otel_context_middleware;
 use crate::{
+    auth::{TokenExchanger, UpstreamAuth},
+    circuit_breaker::CircuitBreaker,
+    concurrency::ConcurrencyLimiter,
     cors::CorsConfig,
     errors::ServerError,
     explorer::Explorer,
+    graphql::Upstream,
     health::HealthCheck,
     introspection::tools::{
         execute::Execute, introspect::Introspect, search::Search, validate::Validate,
     },
     operations::{MutationMode, RawOperation},
+    policy::Policies,
+    rate_limit::RateLimiter,
     server::Transport,
 };
-use apollo_mcp_rhai::{RhaiEngine, checkpoints};
+use apollo_mcp_rhai::checkpoints;
 
 use super::{Config, Running, shutdown_signal};
 
@@ -65,13 +71,6 @@ impl Starting {
             serde_json::to_string_pretty(&operations)?
         );
 
-        let execute_tool = self.config.execute_introspection.then(|| {
-            Execute::new(
-                self.config.mutation_mode,
-                self.config.execute_tool_hint.as_deref(),
-            )
-        });
-
         let root_query_type = self
             .config
             .introspect_introspection
@@ -108,7 +107,14 @@ impl Starting {
         .map_err(ServerError::Apps)?;
         let prompts =
             crate::prompts::load_from_path(Path::new("prompts")).map_err(ServerError::Prompts)?;
-        let schema = Arc::new(RwLock::new(self.schema));
+        let schema = Arc::new(RwLock::new(Arc::new(self.schema)));
+        let execute_tool = self.config.execute_introspection.then(|| {
+            Execute::new(
+                schema.clone(),
+                self.config.mutation_mode,
+                self.config.execute_tool_hint.as_deref(),
+            )
+        });
         let introspect_tool = self.config.introspect_introspection.then(|| {
             Introspect::new(
                 schema.clone(),
@@ -139,20 +145,45 @@ impl Starting {
 
         let cancellation_token = CancellationToken::new();
 
+        let circuit_breaker = self
+            .config
+            .circuit_breaker
+            .enabled
+            .then(|| CircuitBreaker::new(self.config.circuit_breaker.clone()));
+
+        let token_exchange = match &self.config.transport {
+            Transport::StreamableHttp {
+                auth: Some(auth), ..
+            } => auth
+                .token_exchange
+                .clone()
+                .map(|config| TokenExchanger::new(config, &auth.tls))
+                .transpose()?,
+            _ => None,
+        };
+
+        let upstream_auth = self
+            .config
+            .upstream_auth
+            .clone()
+            .map(UpstreamAuth::new)
+            .transpose()?;
+
         // Create health checks only when StreamableHttp transport is enabled.
         let health_check = match (&self.config.transport, self.config.health_check.enabled) {
-            (Transport::StreamableHttp { .. }, true) => {
-                Some(HealthCheck::new(self.config.health_check.clone()))
-            }
+            (Transport::StreamableHttp { .. }, true) => Some(
+                HealthCheck::new(self.config.health_check.clone())
+                    .with_circuit_breaker(circuit_breaker.clone()),
+            ),
             _ => None, // No health checks for Stdio or when disabled.
         };
 
-        let mut engine = RhaiEngine::new("rhai");
+        let engine = self.config.rhai.engine();
         engine.load_from_path().map_err(|err| {
             error!("Error loading Rhai scripts: {err}");
             ServerError::RhaiError
         })?;
-        let engine = Arc::new(parking_lot::Mutex::new(engine));
+        let engine = Arc::new(engine);
 
         if cfg!(feature = "experimental_rhai") {
             checkpoints::on_startup(&engine).map_err(|err| {
@@ -189,8 +220,19 @@ impl Starting {
             annotations: self.config.annotations,
             health_check: health_check.clone(),
             server_info: self.config.server_info.clone(),
+            claim_headers: self.config.claim_headers.clone(),
+            upstream: Upstream {
+                error_mapping: self.config.error_mapping.clone(),
+                circuit_breaker,
+                token_exchange,
+                upstream_auth,
+            },
+            rate_limiter: RateLimiter::new(self.config.rate_limits.clone()),
+            policies: Policies::new(self.config.policies.clone()),
+            concurrency_limiter: ConcurrencyLimiter::new(self.config.concurrency.clone()),
             instructions,
             rhai_engine: engine,
+            listed_tool_names: Default::default(),
         };
 
         match self.config.transport {
@@ -244,9 +286,13 @@ impl Starting {
                         }
                     };
                     // Health check is already active from creation
-                    if let Err(e) = axum::serve(tcp_listener, router)
-                        .with_graceful_shutdown(graceful_shutdown)
-                        .await
+                    // Connection info gives rate limits access to the client address
+                    if let Err(e) = axum::serve(
+                        tcp_listener,
+                        router.into_make_service_with_connect_info::<SocketAddr>(),
+                    )
+                    .with_graceful_shutdown(graceful_shutdown)
+                    .await
                     {
                         // This can never really happen
                         error!("Failed to start MCP server: {e:?}");
@@ -334,6 +380,14 @@ mod tests {
                 },
                 cors: Default::default(),
                 server_info: Default::default(),
+                policies: Default::default(),
+                upstream_auth: Default::default(),
+                claim_headers: Default::default(),
+                concurrency: Default::default(),
+                rate_limits: Default::default(),
+                circuit_breaker: Default::default(),
+                error_mapping: Default::default(),
+                rhai: Default::default(),
                 instructions: None,
             },
             schema: Schema::parse_and_validate("type Query { hello: String }", "test.graphql")
@@ -41,6 +41,15 @@ impl TelemetryAttribute {
             TelemetryAttribute::GraphqlResponse => {
                 Key::from_static_str(TelemetryAttribute::GraphqlResponse.as_str())
             }
+            TelemetryAttribute::GraphqlOutcome => {
+                Key::from_static_str(TelemetryAttribute::GraphqlOutcome.as_str())
+            }
+            TelemetryAttribute::GraphqlErrorClass => {
+                Key::from_static_str(TelemetryAttribute::GraphqlErrorClass.as_str())
+            }
+            TelemetryAttribute::RateLimitScope => {
+                Key::from_static_str(TelemetryAttribute::RateLimitScope.as_str())
+            }
         }
     }
 
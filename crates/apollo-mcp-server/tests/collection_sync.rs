@@ -155,6 +155,13 @@ async fn collection_sync_with_bad_variables_keeps_server_alive() {
         .health_check(HealthCheckConfig::default())
         .cors(CorsConfig::default())
         .server_info(ServerInfoConfig::default())
+        .policies(Default::default())
+        .claim_headers(Default::default())
+        .concurrency(Default::default())
+        .rate_limits(Default::default())
+        .circuit_breaker(Default::default())
+        .error_mapping(Default::default())
+        .rhai(Default::default())
         .build();
 
     // Wait long enough for at least one poll cycle (500ms), then verify